@@ -0,0 +1,71 @@
+//! 系统剪贴板, 只在桌面平台可用
+
+/// 系统剪贴板, 作为 NonSend 资源一直留到程序退出
+///
+/// X11 和 Wayland 上剪贴板的内容由写入的程序提供,
+/// `arboard::Clipboard` 被丢掉之后写入的内容也就没了
+#[derive(Default)]
+pub struct Clipboard(platform::Inner);
+
+impl Clipboard {
+    pub fn get_text(&mut self) -> Option<String> {
+        self.0.get_text()
+    }
+
+    pub fn set_text(&mut self, text: String) -> bool {
+        self.0.set_text(text)
+    }
+}
+
+#[cfg(any(windows, target_os = "macos", target_os = "linux"))]
+mod platform {
+    use bevy::prelude::*;
+
+    /// 第一次用到时才打开剪贴板, 打开失败下次再试
+    #[derive(Default)]
+    pub struct Inner(Option<arboard::Clipboard>);
+
+    impl Inner {
+        fn clipboard(&mut self) -> Option<&mut arboard::Clipboard> {
+            if self.0.is_none() {
+                self.0 = arboard::Clipboard::new()
+                    .map_err(|error| warn!("Failed to open clipboard: {error}"))
+                    .ok();
+            }
+            self.0.as_mut()
+        }
+
+        pub fn get_text(&mut self) -> Option<String> {
+            self.clipboard()?
+                .get_text()
+                .map_err(|error| warn!("Failed to read clipboard: {error}"))
+                .ok()
+        }
+
+        pub fn set_text(&mut self, text: String) -> bool {
+            let Some(clipboard) = self.clipboard() else {
+                return false;
+            };
+            clipboard
+                .set_text(text)
+                .map_err(|error| warn!("Failed to write clipboard: {error}"))
+                .is_ok()
+        }
+    }
+}
+
+#[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+mod platform {
+    #[derive(Default)]
+    pub struct Inner;
+
+    impl Inner {
+        pub fn get_text(&mut self) -> Option<String> {
+            None
+        }
+
+        pub fn set_text(&mut self, _text: String) -> bool {
+            false
+        }
+    }
+}
@@ -65,3 +65,44 @@ pub static TRANSPARENT: Color = Color::linear_rgba(1.0, 1.0, 1.0, 0.0);
 /// #f8cd05
 pub static STRANDS_YELLOW: LazyLock<Color> =
     LazyLock::new(|| Color::Srgba(Srgba::hex("f8cd05").unwrap()));
+
+/// #e6eef9
+pub static HINT_HOUSE: LazyLock<Color> =
+    LazyLock::new(|| Color::Srgba(Srgba::hex("e6eef9").unwrap()));
+/// #bcd3f2
+pub static HINT_PATTERN: LazyLock<Color> =
+    LazyLock::new(|| Color::Srgba(Srgba::hex("bcd3f2").unwrap()));
+/// #e9f2e4
+pub static WINDOKU_SHADE: LazyLock<Color> =
+    LazyLock::new(|| Color::Srgba(Srgba::hex("e9f2e4").unwrap()));
+/// #d5e3cf
+pub static WINDOKU_FIXED: LazyLock<Color> =
+    LazyLock::new(|| Color::Srgba(Srgba::hex("d5e3cf").unwrap()));
+/// #d4d4d4
+pub static THERMO_GRAY: LazyLock<Color> =
+    LazyLock::new(|| Color::Srgba(Srgba::hex("d4d4d4").unwrap()));
+/// #2e9e5b
+pub static HINT_PLACE: LazyLock<Color> =
+    LazyLock::new(|| Color::Srgba(Srgba::hex("2e9e5b").unwrap()));
+/// #d64541
+pub static HINT_ELIMINATE: LazyLock<Color> =
+    LazyLock::new(|| Color::Srgba(Srgba::hex("d64541").unwrap()));
+/// #eef1f6
+pub static PEER_SHADE: LazyLock<Color> =
+    LazyLock::new(|| Color::Srgba(Srgba::hex("eef1f6").unwrap()));
+/// #d9dde5
+pub static PEER_FIXED: LazyLock<Color> =
+    LazyLock::new(|| Color::Srgba(Srgba::hex("d9dde5").unwrap()));
+/// #fbe68e
+pub static SAME_DIGIT: LazyLock<Color> =
+    LazyLock::new(|| Color::Srgba(Srgba::hex("fbe68e").unwrap()));
+/// #b88300
+pub static SAME_CANDIDATE: LazyLock<Color> =
+    LazyLock::new(|| Color::Srgba(Srgba::hex("b88300").unwrap()));
+/// 涂色用的调色板, 和 `model::PALETTE_SIZE` 一样多
+pub static PALETTE: LazyLock<[Color; 9]> = LazyLock::new(|| {
+    [
+        "f4a6a6", "f7c58c", "f5e58a", "b5e0a0", "9fd8d0", "a9c8f0", "c7b3ec", "eeb3d8", "c9c9c9",
+    ]
+    .map(|hex| Color::Srgba(Srgba::hex(hex).unwrap()))
+});
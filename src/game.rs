@@ -1,39 +1,58 @@
 use crate::game::dialog::{Opened, ShowCongrats, ShowSettings};
 use crate::loading::AudioAssets;
 use crate::{
+    clipboard::Clipboard,
     color::*,
     game::{
         board::ConflictContainer,
-        board::{play_board, PreviewCandidate},
+        board::{play_board, spawn_cell, spawn_edge_marks, BoardGrid, PreviewCandidate},
         cell_state::{
-            AutoCandidates, CandidatesValue, CellMode, CellValueBundle, ConflictCell,
-            CorrectionCell, DigitValueCell, FixedCell, ManualCandidates, RevealedCell,
+            AutoCandidates, CellColor, CellMode, CellValueBundle, CenterMarks, ConflictCell,
+            CorrectionCell, CursorCell, DigitValueCell, FixedCell, ManualCandidates, RevealedCell,
             SelectedCell,
         },
         control_tab::control_board,
         dialog::{dialog_container, PauseGame, ShowHint},
-        input::{keyboard_input, keyboard_move_cell},
+        format::{givens_line, pencil_marks, values_line},
+        grid::{Digit, DigitSet},
+        history::{Redo, Undo},
+        input::{keyboard_history, keyboard_input, keyboard_move_cell},
+        model::{sudoku_cells, Board},
+        pool::{GeneratingPuzzle, PuzzlePool},
         position::CellPosition,
+        save::{delete_game, save_game},
     },
     loading::{FontAssets, TextureAssets},
     share::title_bar,
+    stats::{GameKind, GameRecord, Statistics},
+    storage::{self, Folder},
     GameState,
 };
 use bevy::{prelude::*, time::Stopwatch, utils::HashSet};
 use bevy_kira_audio::{Audio, AudioControl};
-use sudoku::{
-    bitset::Set,
-    board::{CellState, Digit},
-    strategy::StrategySolver,
-    Sudoku,
-};
+use serde::{Deserialize, Serialize};
 
 mod board;
 mod cell_state;
 mod control_tab;
 mod dialog;
+mod format;
+mod generator;
+mod grid;
+mod hint;
+mod history;
 mod input;
+mod model;
+mod pool;
 mod position;
+mod save;
+mod solver;
+
+pub use format::{parse_puzzle, Imported};
+pub use generator::{grade, Difficulty, GeneratorOptions, PuzzleMode, Symmetry, Variant};
+pub use grid::GridSize;
+pub use model::Overlay;
+pub use save::{load_game, SavedGame};
 
 pub struct SudokuPlugin;
 
@@ -44,45 +63,92 @@ impl Plugin for SudokuPlugin {
         control_tab::plugin(app);
         board::plugin(app);
         dialog::plugin(app);
+        hint::plugin(app);
+        history::plugin(app);
+        pool::plugin(app);
+        save::plugin(app);
         app.init_resource::<AutoCandidateMode>()
-            .init_resource::<Settings>()
+            .init_resource::<PaintColor>()
+            .insert_resource(
+                storage::load::<Settings>(Folder::Config, SETTINGS_FILE).unwrap_or_default(),
+            )
+            .init_resource::<Difficulty>()
+            .init_resource::<PuzzleMode>()
+            .init_resource::<GeneratorOptions>()
             .add_event::<MoveSelectCell>()
+            // 资源刚插入时也算改变, 刚读出来的设置不用再写回去
+            .add_systems(
+                Update,
+                save_settings
+                    .run_if(resource_changed::<Settings>)
+                    .run_if(not(resource_added::<Settings>)),
+            )
             .add_systems(OnEnter(GameState::Playing), setup_ui)
-            .add_systems(OnExit(GameState::Playing), cleanup_game)
+            .add_systems(
+                OnExit(GameState::Playing),
+                (save_game, cleanup_game).chain(),
+            )
             .add_systems(
                 Update,
                 (
                     keyboard_input,
+                    keyboard_history,
                     update_game_time,
                     keyboard_move_cell,
                     show_conflict,
-                    kick_candidates,
                     check_solver,
                 )
-                    .run_if(in_state(GameState::Playing)),
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(resource_exists::<SudokuManager>),
+            )
+            .add_systems(
+                PostUpdate,
+                sync_board
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(resource_exists_and_changed::<SudokuManager>),
             )
             .add_observer(on_new_digit)
             .add_observer(on_new_candidate)
-            .add_observer(check_conflict)
+            .add_observer(on_new_center_mark)
+            .add_observer(on_new_color)
+            .add_observer(on_new_candidate_color)
+            .add_observer(on_clear_colors)
             .add_observer(init_puzzle)
-            .add_observer(find_hint)
+            .add_observer(start_puzzle)
+            .add_observer(show_rules_text)
             .add_observer(on_clean_cell)
-            .add_observer(on_select_cell)
-            .add_observer(remove_conflict)
-            .add_observer(on_unselect_cell)
             .add_observer(on_reset_puzzle)
             .add_observer(on_reveal_cell)
             .add_observer(on_reveal_puzzle)
             .add_observer(on_check_cell)
-            .add_observer(on_check_puzzle);
+            .add_observer(on_check_puzzle)
+            .add_observer(on_export_puzzle);
     }
 }
 
 #[derive(Resource, Debug)]
 pub struct SudokuManager {
-    pub solution: Sudoku,
-    pub solver: StrategySolver,
+    /// 答案, 每个格子一个数字
+    pub solution: Vec<u8>,
+    /// 当前棋盘, 格子组件由 `sync_board` 从它同步
+    pub board: Board,
     pub is_solved: bool,
+    pub hints_used: u32,
+    pub revealed_cells: u32,
+    /// 用过 "Reveal Puzzle"
+    pub fully_revealed: bool,
+}
+
+/// 下一局要载入的谜题, 没有时按难度生成新谜题
+///
+/// 开始一局时用一次就删掉, 不用为了大小装箱
+#[derive(Resource)]
+#[allow(clippy::large_enum_variant)]
+pub enum PendingPuzzle {
+    /// 继续上次没有完成的一局
+    Resume(SavedGame),
+    /// 玩家导入的谜题
+    Import(Imported),
 }
 
 #[derive(Component)]
@@ -92,9 +158,9 @@ fn setup_ui(
     mut commands: Commands,
     font_assets: Res<FontAssets>,
     texture_assets: Res<TextureAssets>,
+    difficulty: Res<Difficulty>,
 ) {
     commands.spawn((Game, Camera2d));
-    commands.insert_resource(GameTimer(Stopwatch::new()));
     commands
         .spawn((
             Game,
@@ -131,7 +197,7 @@ fn setup_ui(
                 ))
                 .with_children(|builder| {
                     // 工具栏
-                    toolbars(&font_assets, &texture_assets, builder);
+                    toolbars(&font_assets, &texture_assets, builder, *difficulty);
 
                     // 游戏容器
                     builder
@@ -172,6 +238,7 @@ fn toolbars(
     font_assets: &Res<FontAssets>,
     texture_assets: &Res<TextureAssets>,
     builder: &mut ChildBuilder,
+    difficulty: Difficulty,
 ) {
     builder
         .spawn((
@@ -203,7 +270,7 @@ fn toolbars(
                     // left bar
                     left_bar(font_assets, texture_assets, builder);
                     // center bar
-                    center_bar(font_assets, texture_assets, builder);
+                    center_bar(font_assets, texture_assets, builder, difficulty);
                     // right bar
                     right_bar(font_assets, texture_assets, builder);
                 });
@@ -307,6 +374,7 @@ fn center_bar(
     font_assets: &Res<FontAssets>,
     texture_assets: &Res<TextureAssets>,
     builder: &mut ChildBuilder,
+    difficulty: Difficulty,
 ) {
     builder
         .spawn((
@@ -320,7 +388,7 @@ fn center_bar(
         ))
         .with_children(|builder| {
             builder.spawn((
-                Text::new("Easy"),
+                Text::new(difficulty.to_string()),
                 TextFont {
                     font_size: 18.0,
                     font: font_assets.franklin_500.clone(),
@@ -332,6 +400,20 @@ fn center_bar(
                     ..default()
                 },
             ));
+            builder.spawn((
+                Text::default(),
+                TextFont {
+                    font_size: 14.0,
+                    font: font_assets.franklin_500.clone(),
+                    ..default()
+                },
+                TextColor(*DARK_GRAY),
+                Node {
+                    margin: UiRect::right(Val::Px(16.0)),
+                    ..default()
+                },
+                RulesText,
+            ));
             builder.spawn((
                 Text::new("1:02:34"),
                 TextFont {
@@ -431,9 +513,46 @@ fn left_bar(
                         next_state.set(GameState::Menu);
                     },
                 );
+
+            history_button(font_assets, builder, "Undo").observe(
+                |_trigger: Trigger<Pointer<Click>>, mut commands: Commands| {
+                    commands.trigger(Undo);
+                },
+            );
+            history_button(font_assets, builder, "Redo").observe(
+                |_trigger: Trigger<Pointer<Click>>, mut commands: Commands| {
+                    commands.trigger(Redo);
+                },
+            );
         });
 }
 
+fn history_button<'a>(
+    font_assets: &Res<FontAssets>,
+    builder: &'a mut ChildBuilder,
+    text: &str,
+) -> EntityCommands<'a> {
+    let mut button = builder.spawn((
+        Name::new(format!("{}-button", text.to_lowercase())),
+        Node {
+            margin: UiRect::left(Val::Px(24.0)),
+            display: Display::Flex,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+    ));
+    button.with_child((
+        Text::new(text),
+        TextFont {
+            font_size: 16.0,
+            font: font_assets.franklin_500.clone(),
+            ..default()
+        },
+        TextColor(*DARK_BLACK),
+    ));
+    button
+}
+
 #[derive(Event)]
 pub enum MoveSelectCell {
     Up,
@@ -442,6 +561,35 @@ pub enum MoveSelectCell {
     Right,
 }
 
+/// 选中目标格子, 选中的格子可以有多个, 输入的数字和候选数对所有选中的格子生效
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SelectCell {
+    /// 只选这一个格子
+    Only,
+    /// 加到已经选中的格子里
+    Add,
+    /// 没选中就加上, 已经选中就取消, 最后一个选中的格子不能取消
+    Toggle,
+}
+
+impl SelectCell {
+    /// 按住 Shift 加选, 按住 Ctrl 切换, 都没有按时只选一个
+    pub fn from_keys(keyboard_input: &ButtonInput<KeyCode>) -> SelectCell {
+        if keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+            SelectCell::Add
+        } else if keyboard_input.any_pressed([
+            KeyCode::ControlLeft,
+            KeyCode::ControlRight,
+            KeyCode::SuperLeft,
+            KeyCode::SuperRight,
+        ]) {
+            SelectCell::Toggle
+        } else {
+            SelectCell::Only
+        }
+    }
+}
+
 /// 数字格子容器
 #[derive(Component)]
 pub struct DigitCellContainer;
@@ -460,105 +608,231 @@ pub struct InitPuzzle;
 fn init_puzzle(
     _ev: Trigger<InitPuzzle>,
     mut commands: Commands,
-    cell_background: Query<(Entity, &CellPosition)>,
-    settings: Res<Settings>,
-    mut auto: ResMut<AutoCandidateMode>,
+    difficulty: Res<Difficulty>,
+    mode: Res<PuzzleMode>,
+    options: Res<GeneratorOptions>,
+    pending: Option<Res<PendingPuzzle>>,
+    mut pool: ResMut<PuzzlePool>,
 ) {
-    let (sudoku, solution) = loop {
-        let sudoku = Sudoku::generate();
-        if let Some(solution) = sudoku.solution() {
-            break (sudoku, solution);
+    commands.remove_resource::<PendingPuzzle>();
+    // 丢弃还没生成完的上一局
+    commands.remove_resource::<GeneratingPuzzle>();
+
+    match pending.as_deref() {
+        Some(PendingPuzzle::Resume(saved)) => {
+            if let Some((solution, board)) = saved.board() {
+                commands.trigger(StartPuzzle {
+                    solution,
+                    board,
+                    saved: Some(saved.clone()),
+                });
+                return;
+            }
         }
-    };
+        Some(PendingPuzzle::Import(imported)) => {
+            commands.trigger(StartPuzzle {
+                solution: sudoku_cells(imported.solution),
+                board: imported.board.clone(),
+                saved: None,
+            });
+            return;
+        }
+        None => {}
+    }
 
-    info!("sudoku: {:?}", sudoku);
-    if settings.start_in_automatic_mode {
-        *auto = AutoCandidateMode(true);
+    if *mode == PuzzleMode::Random {
+        if let Some((solution, board)) = pool.take(*difficulty, *options) {
+            commands.trigger(StartPuzzle {
+                solution,
+                board,
+                saved: None,
+            });
+            return;
+        }
     }
 
-    let solver = StrategySolver::from_sudoku(sudoku);
+    commands.remove_resource::<SudokuManager>();
+    commands.insert_resource(GeneratingPuzzle::spawn(*mode, *difficulty, *options));
+}
+
+/// 谜题已经准备好, 开始这一局
+#[derive(Event)]
+pub struct StartPuzzle {
+    pub solution: Vec<u8>,
+    pub board: Board,
+    /// 继续存档时恢复计时和格子状态
+    pub saved: Option<SavedGame>,
+}
 
-    commands.insert_resource(SudokuManager {
+#[allow(clippy::too_many_arguments)]
+fn start_puzzle(
+    trigger: Trigger<StartPuzzle>,
+    mut commands: Commands,
+    board_grid: Single<(Entity, &mut Node), With<BoardGrid>>,
+    font_assets: Res<FontAssets>,
+    settings: Res<Settings>,
+    mut auto: ResMut<AutoCandidateMode>,
+    difficulty: Res<Difficulty>,
+    mut statistics: ResMut<Statistics>,
+) {
+    let StartPuzzle {
         solution,
-        solver: solver.clone(),
-        is_solved: false,
-    });
+        board,
+        saved,
+    } = trigger.event();
+
+    info!(
+        "{} {} sudoku: {}",
+        *difficulty,
+        board.size(),
+        givens_line(board)
+    );
+    if saved.is_none() {
+        statistics.start(game_kind(board, *difficulty));
+    }
+    if let Some(saved) = saved {
+        *auto = AutoCandidateMode(saved.auto_mode);
+    } else if settings.start_in_automatic_mode {
+        *auto = AutoCandidateMode(true);
+    }
+
+    let mut stopwatch = Stopwatch::new();
+    if let Some(saved) = saved {
+        stopwatch.set_elapsed(saved.elapsed());
+    }
+    commands.insert_resource(GameTimer(stopwatch));
+
+    // 按棋盘大小重新生成格子, 上一局的格子连同状态一起丢掉
+    // 武士数独的格子和 9×9 一样大, 棋盘比视口大
+    let (grid, mut node) = board_grid.into_inner();
+    let size = board.size();
+    let side = size.side() as u16;
+    node.width = Val::Percent(100.0 * side as f32 / size.digit_count() as f32);
+    node.grid_template_columns = RepeatedGridTrack::flex(side, 1.0);
+    node.grid_template_rows = RepeatedGridTrack::flex(side, 1.0);
+    commands
+        .entity(grid)
+        .despawn_descendants()
+        .with_children(|builder| {
+            for index in 0..size.cell_count() as u16 {
+                // 武士数独空着的位置放一个空节点占住网格
+                if !size.contains(index) {
+                    builder.spawn((Node::default(), BackgroundColor(*DARK_BLACK)));
+                    continue;
+                }
+                let saved_cell = saved
+                    .as_ref()
+                    .map(|saved| &saved.cells[index as usize])
+                    .filter(|_| !board.is_given(index));
 
-    'l: for (index, cell_state) in solver.grid_state().into_iter().enumerate() {
-        let bundle = CellValueBundle::from_cell_state(cell_state, false);
+                let mut cell = spawn_cell(&font_assets, builder, board, index);
+                cell.insert(CellValueBundle::from_board(board, index, auto.0));
 
-        for (entity, cell_position) in cell_background.iter() {
-            if cell_position.0 == index as u8 {
                 // 如果一开始就是数字，那么这个格子是固定颜色
-                if bundle.cell_mode == CellMode::Digit {
-                    commands
-                        .entity(entity)
-                        .insert(bundle)
-                        .insert(FixedCell)
-                        .insert(BackgroundColor(*EXTRA_LIGHT_GRAY));
-                } else {
-                    commands.entity(entity).insert(bundle);
+                if board.is_given(index) {
+                    cell.insert(FixedCell);
+                }
+
+                if let Some(saved_cell) = saved_cell {
+                    if saved_cell.revealed {
+                        cell.insert(RevealedCell);
+                    }
+                    if saved_cell.corrected {
+                        cell.insert(CorrectionCell);
+                    }
                 }
 
                 // 如果是第一个格子，那么选中
                 if index == 0 {
-                    commands.entity(entity).insert(SelectedCell);
+                    cell.insert((SelectedCell, CursorCell));
                 }
-
-                commands
-                    .entity(entity)
-                    .remove::<ConflictCell>()
-                    .remove::<RevealedCell>();
-
-                continue 'l;
             }
-        }
-    }
-}
+            spawn_edge_marks(&font_assets, builder, board);
+        });
 
-fn on_select_cell(trigger: Trigger<OnInsert, SelectedCell>, mut cell: Query<&mut BackgroundColor>) {
-    let entity = trigger.entity();
-    if let Ok(mut background) = cell.get_mut(entity) {
-        background.0 = *STRANDS_YELLOW;
-    }
+    commands.insert_resource(SudokuManager {
+        solution: solution.clone(),
+        board: board.clone(),
+        is_solved: false,
+        hints_used: saved.as_ref().map_or(0, |saved| saved.hints_used),
+        revealed_cells: saved.as_ref().map_or(0, |saved| saved.revealed_cells),
+        fully_revealed: false,
+    });
 }
 
-fn on_unselect_cell(
-    trigger: Trigger<OnRemove, SelectedCell>,
-    mut cell: Query<(&mut BackgroundColor, Option<&FixedCell>)>,
+/// 把棋盘模型同步到格子组件, 包括冲突
+fn sync_board(
+    sudoku_manager: Res<SudokuManager>,
+    mut q_cell: Query<(
+        Entity,
+        &CellPosition,
+        &mut DigitValueCell,
+        &mut ManualCandidates,
+        &mut AutoCandidates,
+        &mut CenterMarks,
+        &mut CellColor,
+        Option<&mut ConflictCell>,
+    )>,
+    mut commands: Commands,
 ) {
-    let entity = trigger.entity();
-    if let Ok((mut background, opt_fixed)) = cell.get_mut(entity) {
-        if opt_fixed.is_some() {
-            background.0 = *EXTRA_LIGHT_GRAY;
-        } else {
-            background.0 = WHITE_COLOR;
+    let board = &sudoku_manager.board;
+    let mut entities = vec![Entity::PLACEHOLDER; board.size().cell_count()];
+    for (entity, cell_position, ..) in q_cell.iter() {
+        entities[cell_position.0 as usize] = entity;
+    }
+
+    for (
+        entity,
+        cell_position,
+        mut digit,
+        mut manual,
+        mut auto,
+        mut center,
+        mut color,
+        opt_conflict,
+    ) in q_cell.iter_mut()
+    {
+        let index = cell_position.0;
+        digit.set_if_neq(DigitValueCell(board.digit(index)));
+        manual.set_if_neq(ManualCandidates(board.manual_candidates(index)));
+        auto.set_if_neq(AutoCandidates(board.auto_candidates(index)));
+        center.set_if_neq(CenterMarks(board.center_marks(index)));
+        color.set_if_neq(CellColor(board.colors(index)));
+
+        let conflicts: HashSet<Entity> = board
+            .conflicts_of(index)
+            .into_iter()
+            .map(|other| entities[other as usize])
+            .collect();
+        match opt_conflict {
+            Some(mut conflict) if !conflicts.is_empty() => {
+                conflict.set_if_neq(ConflictCell(conflicts));
+            }
+            Some(_) => {
+                commands.entity(entity).remove::<ConflictCell>();
+            }
+            None if !conflicts.is_empty() => {
+                commands.entity(entity).insert(ConflictCell(conflicts));
+            }
+            None => {}
         }
     }
 }
 
 fn on_new_digit(
     trigger: Trigger<NewDigit>,
-    mut q_cell: Query<
-        (&mut DigitValueCell, &mut CellMode),
-        (Without<FixedCell>, Without<RevealedCell>),
-    >,
+    mut q_cell: Query<(&CellPosition, &mut CellMode), (Without<FixedCell>, Without<RevealedCell>)>,
+    mut sudoku_manager: ResMut<SudokuManager>,
     mut commands: Commands,
     settings: Res<Settings>,
 ) {
     let entity = trigger.entity();
     let new_digit = trigger.event().0;
-    if let Ok((mut cell_value, mut cell_mode)) = q_cell.get_mut(entity) {
-        *cell_mode = CellMode::Digit;
-
-        if let Some(old_digit) = cell_value.0 {
-            if old_digit != new_digit {
-                commands.trigger(RemoveDigit(old_digit));
-            }
+    if let Ok((cell_position, mut cell_mode)) = q_cell.get_mut(entity) {
+        if !sudoku_manager.board.place(cell_position.0, new_digit) {
+            return;
         }
-
-        cell_value.0 = Some(new_digit);
-        commands.trigger(CheckDigitConflict);
+        *cell_mode = CellMode::Digit;
 
         if settings.check_guesses_when_entered {
             commands.trigger_targets(CheckCell, vec![entity]);
@@ -569,12 +843,7 @@ fn on_new_digit(
 fn on_new_candidate(
     trigger: Trigger<NewCandidate>,
     mut q_cell: Query<
-        (
-            &mut DigitValueCell,
-            &mut ManualCandidates,
-            &mut AutoCandidates,
-            &mut CellMode,
-        ),
+        (&CellPosition, &mut CellMode),
         (
             With<SelectedCell>,
             Without<FixedCell>,
@@ -582,65 +851,152 @@ fn on_new_candidate(
         ),
     >,
     auto_mode: Res<AutoCandidateMode>,
-    mut commands: Commands,
+    mut sudoku_manager: ResMut<SudokuManager>,
 ) {
     let new_candidate = trigger.event().0;
+    let is_auto = |cell_mode: &CellMode| match cell_mode {
+        CellMode::Digit => **auto_mode,
+        CellMode::AutoCandidates => true,
+        CellMode::ManualCandidates => false,
+    };
 
-    for (mut digit_value, mut manual_candidates, mut auto_candidates, mut cell_mode) in
-        q_cell.iter_mut()
-    {
+    // 选中多个格子时跳过填了数字的格子, 其余格子都有这个候选数时一起删掉, 否则一起加上
+    let board = &sudoku_manager.board;
+    let batch = q_cell.iter().len() > 1;
+    let has_candidate = |cell: u16, auto: bool| {
+        let candidates = if auto {
+            board.auto_candidates(cell)
+        } else {
+            board.manual_candidates(cell)
+        };
+        candidates.contains(new_candidate.as_set())
+    };
+    let remove = q_cell
+        .iter()
+        .filter(|(cell_position, _)| board.digit(cell_position.0).is_none())
+        .all(|(cell_position, cell_mode)| has_candidate(cell_position.0, is_auto(cell_mode)));
+    let targets: Vec<(u16, bool)> = q_cell
+        .iter()
+        .map(|(cell_position, cell_mode)| (cell_position.0, is_auto(cell_mode)))
+        .filter(|&(cell, auto)| {
+            !batch || (board.digit(cell).is_none() && has_candidate(cell, auto) == remove)
+        })
+        .collect();
+
+    for (cell_position, mut cell_mode) in q_cell.iter_mut() {
+        let Some(&(cell, auto)) = targets.iter().find(|(cell, _)| *cell == cell_position.0) else {
+            continue;
+        };
         debug!("new candidate: {:?}", new_candidate);
-        match cell_mode.as_ref() {
-            CellMode::Digit => {
-                if let Some(digit) = digit_value.0 {
-                    commands.trigger(RemoveDigit(digit));
-                }
-                digit_value.0 = None;
-                if **auto_mode {
-                    *cell_mode = CellMode::AutoCandidates;
-                    auto_candidates.insert(new_candidate);
-                } else {
-                    *cell_mode = CellMode::ManualCandidates;
-                    manual_candidates.insert(new_candidate);
-                }
-            }
-            CellMode::AutoCandidates => {
-                *cell_mode = CellMode::AutoCandidates;
-                auto_candidates.insert(new_candidate);
-            }
-            CellMode::ManualCandidates => {
-                *cell_mode = CellMode::ManualCandidates;
-                manual_candidates.insert(new_candidate);
-            }
+        sudoku_manager
+            .board
+            .toggle_candidate(cell, new_candidate, auto);
+        *cell_mode = if auto {
+            CellMode::AutoCandidates
+        } else {
+            CellMode::ManualCandidates
+        };
+    }
+}
+
+/// 中心标记属于手动标记, 填了之后格子显示手动候选数
+fn on_new_center_mark(
+    trigger: Trigger<NewCenterMark>,
+    mut q_cell: Query<
+        (&CellPosition, &mut CellMode),
+        (
+            With<SelectedCell>,
+            Without<FixedCell>,
+            Without<RevealedCell>,
+        ),
+    >,
+    mut sudoku_manager: ResMut<SudokuManager>,
+) {
+    let new_mark = trigger.event().0;
+
+    // 和候选数一样, 选中多个格子时跳过填了数字的格子, 其余格子都有这个标记时一起删掉
+    let board = &sudoku_manager.board;
+    let batch = q_cell.iter().len() > 1;
+    let has_mark = |cell: u16| board.center_marks(cell).contains(new_mark.as_set());
+    let remove = q_cell
+        .iter()
+        .filter(|(cell_position, _)| board.digit(cell_position.0).is_none())
+        .all(|(cell_position, _)| has_mark(cell_position.0));
+    let targets: Vec<u16> = q_cell
+        .iter()
+        .map(|(cell_position, _)| cell_position.0)
+        .filter(|&cell| !batch || (board.digit(cell).is_none() && has_mark(cell) == remove))
+        .collect();
+
+    for (cell_position, mut cell_mode) in q_cell.iter_mut() {
+        if targets.contains(&cell_position.0) {
+            sudoku_manager
+                .board
+                .toggle_center_mark(cell_position.0, new_mark);
+            *cell_mode = CellMode::ManualCandidates;
         }
     }
 }
 
+/// 所有选中的格子都是这个颜色时一起擦掉, 否则一起涂上
+fn on_new_color(
+    trigger: Trigger<NewColor>,
+    q_selected: Query<&CellPosition, With<SelectedCell>>,
+    mut paint_color: ResMut<PaintColor>,
+    mut sudoku_manager: ResMut<SudokuManager>,
+) {
+    let color = trigger.event().0;
+    paint_color.set_if_neq(PaintColor(color));
+
+    let board = &mut sudoku_manager.board;
+    let remove = q_selected
+        .iter()
+        .all(|position| board.colors(position.0).cell == Some(color));
+    for position in q_selected.iter() {
+        let mut colors = board.colors(position.0);
+        colors.cell = (!remove).then_some(color);
+        board.set_colors(position.0, colors);
+    }
+}
+
+/// 用最后选的颜色涂一个候选数, 已经是这个颜色时擦掉
+fn on_new_candidate_color(
+    trigger: Trigger<NewCandidateColor>,
+    q_cell: Query<&CellPosition>,
+    paint_color: Res<PaintColor>,
+    mut sudoku_manager: ResMut<SudokuManager>,
+) {
+    let Ok(position) = q_cell.get(trigger.entity()) else {
+        return;
+    };
+    let digit = trigger.event().0;
+    let board = &mut sudoku_manager.board;
+    let mut colors = board.colors(position.0);
+    let color = (colors.candidate(digit) != Some(paint_color.0)).then_some(paint_color.0);
+    colors.set_candidate(digit, color);
+    board.set_colors(position.0, colors);
+}
+
+fn on_clear_colors(_trigger: Trigger<ClearColors>, mut sudoku_manager: ResMut<SudokuManager>) {
+    sudoku_manager.board.clear_colors();
+}
+
 fn on_clean_cell(
     trigger: Trigger<CleanCell>,
     mut q_cell: Query<
-        (
-            Entity,
-            &mut DigitValueCell,
-            &mut ManualCandidates,
-            &mut CellMode,
-        ),
+        (Entity, &CellPosition, &mut CellMode),
         (Without<FixedCell>, Without<RevealedCell>),
     >,
     auto_mode: Res<AutoCandidateMode>,
+    mut sudoku_manager: ResMut<SudokuManager>,
     children: Query<&Children>,
     q_preview: Query<&PreviewCandidate>,
     mut commands: Commands,
 ) {
-    if let Ok((entity, mut digit_value, mut manual_candidates, mut cell_mode)) =
-        q_cell.get_mut(trigger.entity())
-    {
+    if let Ok((entity, cell_position, mut cell_mode)) = q_cell.get_mut(trigger.entity()) {
         match *cell_mode {
             CellMode::Digit => {
-                if let Some(digit) = digit_value.0 {
-                    commands.trigger(RemoveDigit(digit));
-                }
-                digit_value.0 = None;
+                sudoku_manager.board.clear(cell_position.0);
                 if **auto_mode {
                     *cell_mode = CellMode::AutoCandidates;
                 } else {
@@ -648,12 +1004,13 @@ fn on_clean_cell(
                 }
             }
             CellMode::AutoCandidates => {}
-            CellMode::ManualCandidates => manual_candidates.0 = Set::NONE,
+            CellMode::ManualCandidates => {
+                let board = &mut sudoku_manager.board;
+                board.clear_candidates(cell_position.0, false);
+                board.set_center_marks(cell_position.0, DigitSet::NONE);
+            }
         }
-        commands
-            .entity(entity)
-            .remove::<ConflictCell>()
-            .remove::<CorrectionCell>();
+        commands.entity(entity).remove::<CorrectionCell>();
 
         for child in children.iter_descendants(entity) {
             if let Ok(_preview) = q_preview.get(child) {
@@ -663,49 +1020,60 @@ fn on_clean_cell(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn check_solver(
-    cell_query: Query<(&DigitValueCell, &CellPosition)>,
     mut sudoku_manager: ResMut<SudokuManager>,
     mut commands: Commands,
     audio: Res<Audio>,
     audio_assets: Res<AudioAssets>,
     settings: Res<Settings>,
+    game_timer: Res<GameTimer>,
+    difficulty: Res<Difficulty>,
+    mut statistics: ResMut<Statistics>,
 ) {
     if sudoku_manager.is_solved {
         return;
     }
 
-    let mut solved_count = 0;
-    for (cell_value, cell_position) in cell_query
-        .iter()
-        .sort_by::<&CellPosition>(|t1, t2| t1.0.cmp(&t2.0))
-    {
-        if let Some(digit) = cell_value.0 {
-            for (index, num) in sudoku_manager.solution.iter().enumerate() {
-                if cell_position.0 == index as u8 && Some(num.unwrap()) == Some(digit.get()) {
-                    solved_count += 1;
-                }
-            }
-        }
+    if !sudoku_manager.board.is_solved() {
+        return;
+    }
 
-        if solved_count == 81 {
-            sudoku_manager.is_solved = true;
+    sudoku_manager.is_solved = true;
+    delete_game();
+    statistics.finish(GameRecord {
+        kind: game_kind(&sudoku_manager.board, *difficulty),
+        elapsed_secs: game_timer.elapsed_secs_f64(),
+        hints_used: sudoku_manager.hints_used,
+        revealed_cells: sudoku_manager.revealed_cells,
+        fully_revealed: sudoku_manager.fully_revealed,
+        personal_best: false,
+    });
 
-            if settings.play_sound_on_solve {
-                audio
-                    .play(audio_assets.congrats.clone())
-                    // .with_volume(0.3)
-                    .handle();
-            }
+    if settings.play_sound_on_solve {
+        audio
+            .play(audio_assets.congrats.clone())
+            // .with_volume(0.3)
+            .handle();
+    }
 
-            commands.trigger(ShowCongrats(true));
-        }
+    commands.trigger(ShowCongrats(true));
+}
+
+/// 这一局在统计里的类别, 导入和继续的对局也按规则判断变体
+fn game_kind(board: &Board, difficulty: Difficulty) -> GameKind {
+    let variant = Variant::of(board.rules());
+    GameKind {
+        size: board.size(),
+        variant,
+        difficulty,
     }
 }
 
 #[derive(Event)]
 pub struct CleanCell;
 
+/// 在所有选中的格子里切换候选数, 不用指定格子
 #[derive(Event)]
 pub struct NewCandidate(pub Digit);
 
@@ -715,102 +1083,34 @@ impl NewCandidate {
     }
 }
 
+/// 在所有选中的格子里切换中心标记, 不用指定格子
 #[derive(Event)]
-pub struct NewDigit(pub Digit);
+pub struct NewCenterMark(pub Digit);
 
-impl NewDigit {
-    pub fn new(digit: u8) -> NewDigit {
-        NewDigit(Digit::new(digit))
+impl NewCenterMark {
+    pub fn new(digit: u8) -> NewCenterMark {
+        NewCenterMark(Digit::new(digit))
     }
 }
 
+/// 在所有选中的格子上涂调色板里的一个颜色, 不用指定格子
 #[derive(Event)]
-pub struct CheckDigitConflict;
+pub struct NewColor(pub u8);
 
+/// 给格子里的一个候选数涂色
 #[derive(Event)]
-pub struct RemoveDigit(pub Digit);
+pub struct NewCandidateColor(pub Digit);
 
-fn kick_candidates(
-    changed_cell: Query<
-        (&DigitValueCell, &CellPosition),
-        (Changed<DigitValueCell>, With<SelectedCell>),
-    >,
-    mut q_manual: Query<(&mut ManualCandidates, &CellPosition), Without<SelectedCell>>,
-    mut q_auto: Query<(&mut AutoCandidates, &CellPosition), Without<SelectedCell>>,
-    auto_mode: Res<AutoCandidateMode>,
-) {
-    for (cell_state, kicker_position) in changed_cell.iter() {
-        if let Some(digit) = cell_state.0 {
-            debug!("kick_candidates: {:?} {} ", digit, kicker_position);
-            if **auto_mode {
-                for (mut auto_candidates, cell_position) in q_auto.iter_mut() {
-                    if kicker_position.in_range(cell_position) {
-                        auto_candidates.0.remove(digit.as_set());
-                    }
-                }
-            } else {
-                for (mut manual_candidates, cell_position) in q_manual.iter_mut() {
-                    if kicker_position.in_range(cell_position) {
-                        manual_candidates.0.remove(digit.as_set());
-                    }
-                }
-            }
-        }
-    }
-}
+/// 擦掉所有格子和候选数上的颜色
+#[derive(Event)]
+pub struct ClearColors;
 
-/// 检查格子冲突
-fn check_conflict(
-    _check_digit: Trigger<CheckDigitConflict>,
-    update_cell: Query<
-        (Entity, &DigitValueCell, &CellPosition),
-        (With<SelectedCell>, Without<FixedCell>),
-    >,
-    mut q_cell: Query<(
-        Entity,
-        &DigitValueCell,
-        &CellPosition,
-        Option<&mut ConflictCell>,
-    )>,
-    mut commands: Commands,
-) {
-    if let Ok((check_entity, digit_cell, cell_position)) = update_cell.get_single() {
-        if let Some(check_digit) = digit_cell.0 {
-            debug!("check conflict: {:?}", check_digit);
-            let mut conflict_list = vec![];
-            for (other_entity, other_cell_value, other_cell_position, opt_conflict) in
-                q_cell.iter_mut()
-            {
-                if cell_position.in_range(other_cell_position) {
-                    if let Some(other_digit) = other_cell_value.0 {
-                        if check_digit == other_digit && cell_position != other_cell_position {
-                            conflict_list.push(other_entity);
-                            if let Some(mut conflict) = opt_conflict {
-                                conflict.insert(check_entity);
-                            } else {
-                                commands
-                                    .entity(other_entity)
-                                    .insert(ConflictCell(HashSet::from([check_entity])));
-                            }
-                        }
-                    }
-                }
-            }
+#[derive(Event)]
+pub struct NewDigit(pub Digit);
 
-            if !conflict_list.is_empty() {
-                if let Ok((entity, _other_cell_value, _other_cell_position, opt_conflict)) =
-                    q_cell.get_mut(check_entity)
-                {
-                    if let Some(mut conflict) = opt_conflict {
-                        conflict.insert(check_entity);
-                    } else {
-                        commands
-                            .entity(entity)
-                            .insert(ConflictCell(HashSet::from_iter(conflict_list)));
-                    }
-                }
-            }
-        };
+impl NewDigit {
+    pub fn new(digit: u8) -> NewDigit {
+        NewDigit(Digit::new(digit))
     }
 }
 
@@ -832,35 +1132,15 @@ fn show_conflict(
     }
 }
 
-fn remove_conflict(
-    remove_digit: Trigger<RemoveDigit>,
-    q_cell: Query<(Entity, &CellPosition), With<SelectedCell>>,
-    mut other_cell: Query<
-        (&DigitValueCell, &CellPosition, &mut ConflictCell),
-        Without<SelectedCell>,
-    >,
-    mut commands: Commands,
-) {
-    let remove_digit = remove_digit.0;
-    for (entity, cell_position) in q_cell.iter() {
-        commands.entity(entity).remove::<ConflictCell>();
-
-        for (other_cell_value, other_cell_position, mut conflict) in other_cell.iter_mut() {
-            if cell_position.in_range(other_cell_position) {
-                if let Some(other_digit) = other_cell_value.0 {
-                    if remove_digit == other_digit && cell_position != other_cell_position {
-                        conflict.remove(&entity);
-                    }
-                }
-            }
-        }
-    }
-}
-
 #[derive(Resource, Default, Deref, DerefMut)]
 pub struct AutoCandidateMode(pub bool);
 
+/// 最后选的颜色, 给候选数涂色时用
+#[derive(Resource, Default, PartialEq)]
+pub struct PaintColor(pub u8);
+
 fn cleanup_game(mut commands: Commands, menu: Query<Entity, With<Game>>) {
+    commands.remove_resource::<GeneratingPuzzle>();
     for entity in menu.iter() {
         commands.entity(entity).despawn_recursive();
     }
@@ -882,6 +1162,14 @@ impl core::fmt::Display for GameTimer {
 #[derive(Component)]
 struct TimerText;
 
+/// 这一局叠加的额外约束, 马步和王步在棋盘上没有标记, 只能写出来
+#[derive(Component)]
+struct RulesText;
+
+fn show_rules_text(trigger: Trigger<StartPuzzle>, mut text: Single<&mut Text, With<RulesText>>) {
+    text.0 = trigger.event().board.rules().overlays.to_string();
+}
+
 fn update_game_time(
     mut game_timer: ResMut<GameTimer>,
     time: Res<Time>,
@@ -947,7 +1235,7 @@ fn spawn_show_more(font_assets: &Res<FontAssets>, builder: &mut ChildBuilder, is
                     builder,
                     "Check Cell",
                     |_: Trigger<Pointer<Click>>, mut commands, q_selected| {
-                        commands.trigger_targets(CheckCell, vec![*q_selected]);
+                        commands.trigger_targets(CheckCell, q_selected.iter().collect::<Vec<_>>());
                     },
                 );
                 more_item(
@@ -963,7 +1251,7 @@ fn spawn_show_more(font_assets: &Res<FontAssets>, builder: &mut ChildBuilder, is
                     builder,
                     "Reveal Cell",
                     |_: Trigger<Pointer<Click>>, mut commands, q_selected| {
-                        commands.trigger_targets(RevealCell, vec![*q_selected]);
+                        commands.trigger_targets(RevealCell, q_selected.iter().collect::<Vec<_>>());
                     },
                 );
                 more_item(
@@ -982,6 +1270,38 @@ fn spawn_show_more(font_assets: &Res<FontAssets>, builder: &mut ChildBuilder, is
                         commands.trigger(ResetPuzzle);
                     },
                 );
+                more_item(
+                    font_assets,
+                    builder,
+                    "Clear Colors",
+                    |_: Trigger<Pointer<Click>>, mut commands, _q_selected| {
+                        commands.trigger(ClearColors);
+                    },
+                );
+                more_item(
+                    font_assets,
+                    builder,
+                    "Export Givens",
+                    |_: Trigger<Pointer<Click>>, mut commands, _q_selected| {
+                        commands.trigger(ExportPuzzle::Givens);
+                    },
+                );
+                more_item(
+                    font_assets,
+                    builder,
+                    "Export Values",
+                    |_: Trigger<Pointer<Click>>, mut commands, _q_selected| {
+                        commands.trigger(ExportPuzzle::Values);
+                    },
+                );
+                more_item(
+                    font_assets,
+                    builder,
+                    "Export Marks",
+                    |_: Trigger<Pointer<Click>>, mut commands, _q_selected| {
+                        commands.trigger(ExportPuzzle::PencilMarks);
+                    },
+                );
             }
         });
 }
@@ -990,7 +1310,7 @@ fn more_item(
     font_assets: &Res<FontAssets>,
     builder: &mut ChildBuilder,
     text: &str,
-    trigger: fn(Trigger<Pointer<Click>>, Commands, Single<Entity, With<SelectedCell>>),
+    trigger: fn(Trigger<Pointer<Click>>, Commands, Query<Entity, With<SelectedCell>>),
 ) {
     builder
         .spawn((
@@ -1079,65 +1399,34 @@ struct ResetPuzzle;
 
 fn on_reset_puzzle(
     _trigger: Trigger<ResetPuzzle>,
-    sudoku_manager: Res<SudokuManager>,
-    mut q_cell: Query<(
-        Entity,
-        &CellPosition,
-        &mut DigitValueCell,
-        &mut ManualCandidates,
-        &mut AutoCandidates,
-        &mut CellMode,
-    )>,
+    mut sudoku_manager: ResMut<SudokuManager>,
+    mut q_cell: Query<(Entity, &CellPosition, &mut CellMode)>,
     mut commands: Commands,
     mut auto_mode: ResMut<AutoCandidateMode>,
 ) {
     commands.insert_resource(GameTimer(Stopwatch::new()));
     auto_mode.0 = false;
+    sudoku_manager.board.reset();
+
     let mut entities = vec![];
-    for (entity, _, _, _, _, _) in q_cell.iter() {
+    for (entity, cell_position, mut cell_mode) in q_cell.iter_mut() {
         commands
             .entity(entity)
-            .remove::<SelectedCell>()
+            .remove::<(SelectedCell, CursorCell)>()
             .remove::<ConflictCell>()
             .remove::<RevealedCell>();
+        if cell_position.0 == 0 {
+            commands.entity(entity).insert((SelectedCell, CursorCell));
+        }
+        *cell_mode = if sudoku_manager.board.is_given(cell_position.0) {
+            CellMode::Digit
+        } else {
+            CellMode::ManualCandidates
+        };
         entities.push(entity);
     }
 
     commands.trigger_targets(CleanCell, entities);
-
-    'l: for (index, cell_state) in sudoku_manager.solver.grid_state().into_iter().enumerate() {
-        for (
-            entity,
-            cell_position,
-            mut digit_value,
-            mut manual_candidates,
-            mut auto_candidates,
-            mut cell_mode,
-        ) in q_cell.iter_mut()
-        {
-            if cell_position.0 == index as u8 {
-                if index == 0 {
-                    commands.entity(entity).insert(SelectedCell);
-                }
-                match cell_state {
-                    CellState::Digit(digit) => {
-                        *cell_mode = CellMode::Digit;
-                        digit_value.0 = Some(digit);
-                        manual_candidates.0 = Set::NONE;
-                        auto_candidates.0 = Set::NONE;
-                    }
-                    CellState::Candidates(cands) => {
-                        *cell_mode = CellMode::ManualCandidates;
-                        digit_value.0 = None;
-                        manual_candidates.0 = Set::NONE;
-                        auto_candidates.0 = cands;
-                    }
-                }
-
-                continue 'l;
-            }
-        }
-    }
 }
 
 #[derive(Event)]
@@ -1145,24 +1434,22 @@ struct RevealCell;
 
 fn on_reveal_cell(
     trigger: Trigger<RevealCell>,
-    q_select: Query<&CellPosition>,
-    sudoku_manager: Res<SudokuManager>,
+    q_select: Query<(&CellPosition, Has<FixedCell>, Has<RevealedCell>)>,
+    mut sudoku_manager: ResMut<SudokuManager>,
     mut commands: Commands,
 ) {
     let entity = trigger.entity();
 
-    if let Ok(cell_position) = q_select.get(entity) {
-        for (index, num) in sudoku_manager.solution.iter().enumerate() {
-            if cell_position.0 == index as u8 {
-                let num = num.unwrap();
-                commands.trigger_targets(NewDigit::new(num), vec![entity]);
-                commands
-                    .entity(entity)
-                    .remove::<CorrectionCell>()
-                    .insert(RevealedCell);
-                return;
-            }
+    if let Ok((cell_position, fixed, revealed)) = q_select.get(entity) {
+        if !fixed && !revealed {
+            sudoku_manager.revealed_cells += 1;
         }
+        let num = sudoku_manager.solution[cell_position.0 as usize];
+        commands.trigger_targets(NewDigit::new(num), vec![entity]);
+        commands
+            .entity(entity)
+            .remove::<CorrectionCell>()
+            .insert(RevealedCell);
     }
 }
 
@@ -1175,10 +1462,12 @@ fn on_reveal_puzzle(
     mut commands: Commands,
     mut auto: ResMut<AutoCandidateMode>,
     settings: Res<Settings>,
+    mut sudoku_manager: ResMut<SudokuManager>,
 ) {
     if settings.start_in_automatic_mode {
         *auto = AutoCandidateMode(true);
     }
+    sudoku_manager.fully_revealed = true;
 
     let entities = q_cell.iter().collect::<Vec<_>>();
     commands.trigger_targets(RevealCell, entities);
@@ -1189,25 +1478,21 @@ pub struct CheckCell;
 
 fn on_check_cell(
     trigger: Trigger<CheckCell>,
-    q_cell: Query<(&DigitValueCell, &CellPosition), Without<FixedCell>>,
+    q_cell: Query<&CellPosition, Without<FixedCell>>,
     sudoku_manager: Res<SudokuManager>,
     mut commands: Commands,
     settings: Res<Settings>,
 ) {
     let entity = trigger.entity();
-    if let Ok((cell_value, cell_position)) = q_cell.get(entity) {
-        if let Some(digit) = cell_value.0 {
-            for (index, num) in sudoku_manager.solution.iter().enumerate() {
-                if cell_position.0 == index as u8 {
-                    if num != Some(digit.get()) {
-                        commands.entity(entity).insert(CorrectionCell);
-                    } else {
-                        commands.entity(entity).remove::<CorrectionCell>();
-
-                        if settings.check_guesses_when_entered {
-                            commands.entity(entity).insert(RevealedCell);
-                        }
-                    }
+    if let Ok(cell_position) = q_cell.get(entity) {
+        if let Some(digit) = sudoku_manager.board.digit(cell_position.0) {
+            if sudoku_manager.solution[cell_position.0 as usize] != digit.get() {
+                commands.entity(entity).insert(CorrectionCell);
+            } else {
+                commands.entity(entity).remove::<CorrectionCell>();
+
+                if settings.check_guesses_when_entered {
+                    commands.entity(entity).insert(RevealedCell);
                 }
             }
         }
@@ -1219,16 +1504,14 @@ pub struct CheckPuzzle;
 
 fn on_check_puzzle(
     _trigger: Trigger<CheckPuzzle>,
-    q_cell: Query<(Entity, &DigitValueCell, &CellPosition), Without<FixedCell>>,
+    q_cell: Query<(Entity, &CellPosition), Without<FixedCell>>,
     sudoku_manager: Res<SudokuManager>,
     mut commands: Commands,
 ) {
-    for (entity, cell_value, cell_position) in q_cell.iter() {
-        if let Some(digit) = cell_value.0 {
-            for (index, num) in sudoku_manager.solution.iter().enumerate() {
-                if cell_position.0 == index as u8 && num != Some(digit.get()) {
-                    commands.entity(entity).insert(CorrectionCell);
-                }
+    for (entity, cell_position) in q_cell.iter() {
+        if let Some(digit) = sudoku_manager.board.digit(cell_position.0) {
+            if sudoku_manager.solution[cell_position.0 as usize] != digit.get() {
+                commands.entity(entity).insert(CorrectionCell);
             }
         }
     }
@@ -1237,35 +1520,50 @@ fn on_check_puzzle(
 #[derive(Event)]
 pub struct FindHint;
 
-/// 查找提示, 暂时按照候选数最少的格子来选中
-fn find_hint(
-    _trigger: Trigger<FindHint>,
-    q_selected: Query<Entity, With<SelectedCell>>,
-    q_cell: Query<(Entity, &AutoCandidates), Without<FixedCell>>,
-    mut commands: Commands,
-) {
-    for entity in q_selected.iter() {
-        commands.entity(entity).remove::<SelectedCell>();
-    }
-    if let Some((entity, _)) = q_cell
-        .iter()
-        .sort_by::<(Entity, &AutoCandidates)>(|t1, t2| {
-            let candidate_1 = t1.1;
-            let candidate_2 = t2.1;
+/// 把当前棋盘复制到剪贴板
+#[derive(Event, Clone, Copy)]
+enum ExportPuzzle {
+    /// 题目, 每格一个字符
+    Givens,
+    /// 题目加上填的数字, 每格一个字符
+    Values,
+    /// HoDoKu / SudokuWiki 格式的候选数格子
+    PencilMarks,
+}
 
-            candidate_1.0.len().cmp(&candidate_2.0.len())
-        })
-        .next()
-    {
-        commands.entity(entity).insert(SelectedCell);
+fn on_export_puzzle(
+    trigger: Trigger<ExportPuzzle>,
+    sudoku_manager: Res<SudokuManager>,
+    auto_mode: Res<AutoCandidateMode>,
+    mut clipboard: NonSendMut<Clipboard>,
+) {
+    let board = &sudoku_manager.board;
+    let text = match trigger.event() {
+        ExportPuzzle::Givens => givens_line(board),
+        ExportPuzzle::Values => values_line(board),
+        ExportPuzzle::PencilMarks => pencil_marks(board, auto_mode.0),
+    };
+    info!("Exported puzzle:\n{text}");
+    if clipboard.set_text(text) {
+        info!("Copied to clipboard");
     }
 }
 
-#[derive(Resource)]
+const SETTINGS_FILE: &str = "settings.json";
+
+/// 缺少的字段使用默认值
+#[derive(Resource, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Settings {
     pub check_guesses_when_entered: bool,
     pub start_in_automatic_mode: bool,
     pub highlight_conflicts: bool,
+    /// 选中格子所在的行、列和宫
+    pub highlight_peers: bool,
+    /// 和选中格子数字相同的格子
+    pub highlight_same_digit: bool,
+    /// 和选中格子数字相同的候选数
+    pub highlight_same_candidates: bool,
     pub play_sound_on_solve: bool,
     pub show_clock: bool,
 }
@@ -1276,8 +1574,15 @@ impl Default for Settings {
             check_guesses_when_entered: false,
             start_in_automatic_mode: false,
             highlight_conflicts: true,
+            highlight_peers: true,
+            highlight_same_digit: true,
+            highlight_same_candidates: false,
             play_sound_on_solve: true,
             show_clock: true,
         }
     }
 }
+
+fn save_settings(settings: Res<Settings>) {
+    storage::save(Folder::Config, SETTINGS_FILE, &*settings);
+}
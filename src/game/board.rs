@@ -4,29 +4,46 @@ use crate::{
     color::*,
     game::{
         cell_state::{
-            AutoCandidateCellMarker, CandidateMarker, CandidatesValue, ManualCandidateCellMarker,
-            RevealedCell,
+            AutoCandidateCellMarker, CandidateMarker, CandidatesValue, CursorCell, FixedCell,
+            ManualCandidateCellMarker, RevealedCell,
         },
-        cell_state::{AutoCandidates, CellMode, DigitValueCell, ManualCandidates},
+        cell_state::{
+            AutoCandidates, CellColor, CellMode, CenterMarks, DigitValueCell, ManualCandidates,
+        },
+        control_tab::{ControlTab, SelectedTab},
+        grid::{Digit, DigitSet, GridSize},
+        model::{windoku_box, Board, EdgeKind, LineKind, Overlay},
+        pool::GeneratingPuzzle,
         position::CellPosition,
         AutoCandidateMode, AutoCandidatesContainer, DigitCellContainer, ManualCandidatesContainer,
-        MoveSelectCell, SelectedCell,
+        MoveSelectCell, NewCandidate, NewCandidateColor, SelectCell, SelectedCell, SudokuManager,
     },
     loading::{FontAssets, TextureAssets},
     GameState,
 };
-use bevy::prelude::*;
-use sudoku::board::Digit;
+use bevy::{
+    ecs::system::EntityCommands,
+    input::mouse::{MouseScrollUnit, MouseWheel},
+    prelude::*,
+};
+use std::f32::consts::FRAC_PI_4;
 
 pub(crate) fn plugin(app: &mut App) {
     app.add_systems(
         Update,
         (
+            update_highlighted_digit,
             show_digit_cell,
             show_candidates::<AutoCandidates, AutoCandidateCellMarker>,
             show_candidates::<ManualCandidates, ManualCandidateCellMarker>,
+            show_center_marks,
+            show_cell_color,
             show_preview_number,
             change_cell_vis,
+            cell_background,
+            show_generating,
+            scroll_board,
+            scroll_to_cursor,
         )
             .run_if(in_state(GameState::Playing)),
     )
@@ -35,6 +52,8 @@ pub(crate) fn plugin(app: &mut App) {
         switch_candidate_cell_mode.run_if(resource_changed::<AutoCandidateMode>),
     )
     .add_systems(Update, conflict_vis.run_if(resource_changed::<Settings>))
+    .init_resource::<HighlightedDigit>()
+    .add_observer(on_select_cell)
     .add_observer(move_select_cell)
     .add_observer(on_insert_conflict)
     .add_observer(remove_child_cell::<ConflictCell, ConflictContainer>)
@@ -51,251 +70,652 @@ pub(crate) fn play_board(
         .spawn((
             Node {
                 width: Val::Vh(80.0),
+                height: Val::Vh(80.0),
                 // min_width: Val::Px(500.0),
                 // max_width: Val::Px(800.0),
+                overflow: Overflow::scroll(),
                 ..default()
             },
             BackgroundColor(*DARK_BLACK),
+            BoardViewport,
         ))
         .with_children(|builder| {
-            // 生成9宫格布局
+            // 格子在开始一局时按棋盘大小生成, 见 `spawn_cell`
+            builder.spawn((
+                Node {
+                    width: Val::Percent(100.0),
+                    aspect_ratio: Some(1.0),
+                    display: Display::Grid,
+                    grid_template_columns: RepeatedGridTrack::flex(9, 1.0),
+                    grid_template_rows: RepeatedGridTrack::flex(9, 1.0),
+                    border: UiRect::all(Val::Px(5.0)),
+                    ..default()
+                },
+                BackgroundColor(*GRAY),
+                BoardGrid,
+            ));
+
+            // 后台生成谜题时盖住棋盘
             builder
                 .spawn((
                     Node {
+                        position_type: PositionType::Absolute,
                         width: Val::Percent(100.0),
-                        aspect_ratio: Some(1.0),
-                        display: Display::Grid,
-                        grid_template_columns: RepeatedGridTrack::flex(3, 1.0),
-                        grid_template_rows: RepeatedGridTrack::flex(3, 1.0),
-                        row_gap: Val::Px(4.0),
-                        column_gap: Val::Px(4.0),
-                        border: UiRect::all(Val::Px(5.0)),
+                        height: Val::Percent(100.0),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
                         ..default()
                     },
-                    // BorderColor(Color::BLACK),
-                    BackgroundColor(*GRAY),
-                    // CellsLayout,
+                    BackgroundColor(*COOL_GRAY),
+                    Visibility::Hidden,
+                    GeneratingOverlay,
                 ))
+                .with_child((
+                    Text::new("Generating..."),
+                    TextFont {
+                        font: font_assets.franklin_700.clone(),
+                        font_size: 24.0,
+                        ..default()
+                    },
+                    TextColor(*DARK_BLACK),
+                ));
+        });
+}
+
+/// 放格子的网格, 每一局开始时按棋盘大小重新生成里面的格子
+#[derive(Component)]
+pub struct BoardGrid;
+
+/// 棋盘外面的视口, 武士数独的格子和 9×9 一样大, 放不下时可以滚动
+#[derive(Component)]
+pub struct BoardViewport;
+
+/// 鼠标滚轮滚动棋盘, 按住 Shift 时左右滚动
+fn scroll_board(
+    mut mouse_wheel: EventReader<MouseWheel>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut viewport: Single<&mut ScrollPosition, With<BoardViewport>>,
+) {
+    for event in mouse_wheel.read() {
+        let (mut dx, mut dy) = match event.unit {
+            MouseScrollUnit::Line => (event.x * 40.0, event.y * 40.0),
+            MouseScrollUnit::Pixel => (event.x, event.y),
+        };
+        if keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+            std::mem::swap(&mut dx, &mut dy);
+        }
+        viewport.offset_x -= dx;
+        viewport.offset_y -= dy;
+    }
+}
+
+/// 键盘移动到视口外面的格子时, 滚动到能看见这个格子
+fn scroll_to_cursor(
+    q_cursor: Query<&CellPosition, Added<CursorCell>>,
+    grid: Single<&ComputedNode, With<BoardGrid>>,
+    viewport: Single<(&ComputedNode, &mut ScrollPosition), With<BoardViewport>>,
+) {
+    let Some(position) = q_cursor.iter().next() else {
+        return;
+    };
+    let (view, mut scroll) = viewport.into_inner();
+    let cell = grid.size() / position.size().side() as f32;
+    let top_left = Vec2::new(position.col() as f32, position.row() as f32) * cell;
+    let current = Vec2::new(scroll.offset_x, scroll.offset_y);
+    let offset = current.min(top_left).max(top_left + cell - view.size());
+    if offset != current {
+        *scroll = ScrollPosition::from(&offset);
+    }
+}
+
+/// 按棋盘大小决定 (数字, 候选数) 的字号
+fn font_sizes(size: GridSize) -> (f32, f32) {
+    match size {
+        GridSize::Four => (72.0, 24.0),
+        GridSize::Six => (60.0, 20.0),
+        GridSize::Nine | GridSize::Samurai => (48.0, 16.0),
+        GridSize::Sixteen => (28.0, 9.0),
+    }
+}
+
+/// 生成一个格子: 涂的颜色、数字、两套候选数、中心标记, 以及宫的粗线、杀手数独的笼子、线条和额外约束的标记
+pub(crate) fn spawn_cell<'a>(
+    font_assets: &FontAssets,
+    builder: &'a mut ChildBuilder,
+    board: &Board,
+    cell: u16,
+) -> EntityCommands<'a> {
+    let size = board.size();
+    let (digit_size, candidate_size) = font_sizes(size);
+    let mut cell_commands = builder.spawn((
+        Node {
+            display: Display::Grid,
+            align_items: AlignItems::Center,
+            justify_items: JustifyItems::Center,
+            align_content: AlignContent::Center,
+            justify_content: JustifyContent::Center,
+            border: UiRect::all(Val::Px(0.5)),
+            ..default()
+        },
+        CellPosition::new(cell, size),
+        BorderColor(*LIGHT_GRAY),
+        BackgroundColor(Color::WHITE),
+    ));
+    let overlays = board.rules().overlays;
+    if overlays.contains(Overlay::Windoku) && windoku_box(cell).is_some() {
+        cell_commands.insert(WindokuCell);
+    }
+    cell_commands
+        .observe(on_click_cell)
+        .observe(on_drag_start_cell)
+        .observe(on_drag_enter_cell)
+        .with_children(|builder| {
+            // 涂的颜色在最下面, 四周留一点让选中的底色露出来
+            builder.spawn((
+                Node {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(2.0),
+                    right: Val::Px(2.0),
+                    top: Val::Px(2.0),
+                    bottom: Val::Px(2.0),
+                    ..default()
+                },
+                BackgroundColor(TRANSPARENT),
+                BorderRadius::all(Val::Px(2.0)),
+                PickingBehavior::IGNORE,
+                CellColorLayer,
+            ));
+
+            if overlays.contains(Overlay::Diagonals) {
+                spawn_diagonal_lines(builder, size, cell);
+            }
+            spawn_lines(builder, board, cell);
+
+            // 数字格子
+            builder.spawn((
+                Text::new(cell.to_string()),
+                TextFont {
+                    font: font_assets.franklin_800.clone(),
+                    font_size: digit_size,
+                    ..default()
+                },
+                TextColor(*DARK_BLACK),
+                Visibility::Hidden,
+                Node {
+                    margin: UiRect {
+                        bottom: Val::Px(1.0),
+                        ..default()
+                    },
+                    ..default()
+                },
+                DigitCellContainer,
+            ));
+
+            // 自动候选格子容器
+            builder
+                .spawn((candidates_node(size), AutoCandidatesContainer))
                 .with_children(|builder| {
-                    // 生成九个宫格
-                    for block_index in 0..9 {
+                    for digit in size.digits() {
                         builder
                             .spawn((
+                                candidate_text(font_assets, digit, candidate_size),
                                 Node {
-                                    height: Val::Percent(100.0),
-                                    aspect_ratio: Some(1.0),
-                                    display: Display::Grid,
-                                    grid_template_columns: RepeatedGridTrack::flex(3, 1.0),
-                                    grid_template_rows: RepeatedGridTrack::flex(3, 1.0),
-                                    // row_gap: Val::Px(1.0),
-                                    // column_gap: Val::Px(1.0),
-                                    // border: UiRect::all(Val::Px(1.)),
+                                    align_items: AlignItems::Center,
+                                    justify_items: JustifyItems::Center,
+                                    align_content: AlignContent::Center,
+                                    justify_content: JustifyContent::Center,
                                     ..default()
                                 },
-                                BackgroundColor(*GRAY),
+                                AutoCandidateCellMarker {
+                                    index: digit.get(),
+                                    selected: false,
+                                },
                             ))
-                            .with_children(|builder| {
-                                // 生成宫格里的9个格子
-                                for bi in 0..9 {
-                                    let cell = block_index * 9 + bi;
-                                    builder
-                                        .spawn((
-                                            Node {
-                                                display: Display::Grid,
-                                                align_items: AlignItems::Center,
-                                                justify_items: JustifyItems::Center,
-                                                align_content: AlignContent::Center,
-                                                justify_content: JustifyContent::Center,
-                                                border: UiRect::all(Val::Px(0.5)),
-                                                ..default()
-                                            },
-                                            CellPosition::from_block_row_col(block_index, bi),
-                                            BorderColor(*LIGHT_GRAY),
-                                            BackgroundColor(Color::WHITE),
-                                        ))
-                                        .observe(on_click_cell)
-                                        .with_children(|builder| {
-                                            // 数字格子
-                                            builder.spawn((
-                                                Text::new(cell.to_string()),
-                                                TextFont {
-                                                    font: font_assets.franklin_800.clone(),
-                                                    font_size: 48.0,
-                                                    ..default()
-                                                },
-                                                TextColor(*DARK_BLACK),
-                                                Visibility::Hidden,
-                                                Node {
-                                                    margin: UiRect {
-                                                        bottom: Val::Px(1.0),
-                                                        ..default()
-                                                    },
-                                                    ..default()
-                                                },
-                                                DigitCellContainer,
-                                            ));
-
-                                            // 自动候选格子容器
-                                            builder
-                                                .spawn((
-                                                    Visibility::Hidden,
-                                                    Node {
-                                                        height: Val::Percent(100.0),
-                                                        display: Display::Grid,
-                                                        aspect_ratio: Some(1.0),
-                                                        position_type: PositionType::Absolute,
-                                                        grid_template_columns:
-                                                            RepeatedGridTrack::flex(3, 1.0),
-                                                        grid_template_rows: RepeatedGridTrack::flex(
-                                                            3, 1.0,
-                                                        ),
-                                                        // row_gap: Val::Px(4.0),
-                                                        // column_gap: Val::Px(4.0),
-                                                        ..default()
-                                                    },
-                                                    AutoCandidatesContainer,
-                                                ))
-                                                .with_children(|builder| {
-                                                    // 9个候选数字格子
-                                                    for i in 1..=9u8 {
-                                                        builder
-                                                            .spawn((
-                                                                Text::new(i.to_string()),
-                                                                TextFont {
-                                                                    font: font_assets
-                                                                        .franklin_700
-                                                                        .clone(),
-                                                                    font_size: 16.0,
-                                                                    ..default()
-                                                                },
-                                                                TextColor(TRANSPARENT),
-                                                                TextLayout::new_with_justify(
-                                                                    JustifyText::Center,
-                                                                ),
-                                                                Node {
-                                                                    align_items: AlignItems::Center,
-                                                                    justify_items:
-                                                                        JustifyItems::Center,
-                                                                    align_content:
-                                                                        AlignContent::Center,
-                                                                    justify_content:
-                                                                        JustifyContent::Center,
-                                                                    // margin: UiRect {
-                                                                    //     top: Val::Px(4.),
-                                                                    //     ..default()
-                                                                    // },
-                                                                    ..default()
-                                                                },
-                                                                Visibility::Inherited,
-                                                                // BackgroundColor(RED.into()),
-                                                                AutoCandidateCellMarker {
-                                                                    index: i,
-                                                                    selected: false,
-                                                                },
-                                                            ))
-                                                            .observe(
-                                                                candidate_cell_move::<
-                                                                    AutoCandidates,
-                                                                    AutoCandidateCellMarker,
-                                                                >,
-                                                            )
-                                                            .observe(
-                                                                candidate_cell_out::<
-                                                                    AutoCandidateCellMarker,
-                                                                >,
-                                                            )
-                                                            .observe(
-                                                                candidate_cell_click::<
-                                                                    AutoCandidates,
-                                                                    AutoCandidateCellMarker,
-                                                                >,
-                                                            );
-                                                    }
-                                                });
-
-                                            // 手动候选格子容器
-                                            builder
-                                                .spawn((
-                                                    Visibility::Hidden,
-                                                    Node {
-                                                        height: Val::Percent(100.0),
-                                                        display: Display::Grid,
-                                                        aspect_ratio: Some(1.0),
-                                                        position_type: PositionType::Absolute,
-                                                        grid_template_columns:
-                                                            RepeatedGridTrack::flex(3, 1.0),
-                                                        grid_template_rows: RepeatedGridTrack::flex(
-                                                            3, 1.0,
-                                                        ),
-                                                        // row_gap: Val::Px(4.0),
-                                                        // column_gap: Val::Px(4.0),
-                                                        ..default()
-                                                    },
-                                                    ManualCandidatesContainer,
-                                                ))
-                                                .with_children(|builder| {
-                                                    // 9个候选数字格子
-                                                    for i in 1..=9u8 {
-                                                        builder
-                                                            .spawn((
-                                                                Text::new(i.to_string()),
-                                                                TextFont {
-                                                                    font: font_assets
-                                                                        .franklin_700
-                                                                        .clone(),
-                                                                    font_size: 16.0,
-                                                                    ..default()
-                                                                },
-                                                                TextColor(TRANSPARENT),
-                                                                TextLayout::new_with_justify(
-                                                                    JustifyText::Center,
-                                                                ),
-                                                                Node {
-                                                                    align_items: AlignItems::Center,
-                                                                    justify_items:
-                                                                        JustifyItems::Center,
-                                                                    align_content:
-                                                                        AlignContent::Center,
-                                                                    justify_content:
-                                                                        JustifyContent::Center,
-                                                                    margin: UiRect {
-                                                                        top: Val::Px(4.),
-                                                                        ..default()
-                                                                    },
-                                                                    ..default()
-                                                                },
-                                                                Visibility::Inherited,
-                                                                // BackgroundColor(YELLOW.into()),
-                                                                ManualCandidateCellMarker {
-                                                                    index: i,
-                                                                    selected: false,
-                                                                },
-                                                            ))
-                                                            .observe(
-                                                                candidate_cell_move::<
-                                                                    ManualCandidates,
-                                                                    ManualCandidateCellMarker,
-                                                                >,
-                                                            )
-                                                            .observe(
-                                                                candidate_cell_out::<
-                                                                    ManualCandidateCellMarker,
-                                                                >,
-                                                            )
-                                                            .observe(
-                                                                candidate_cell_click::<
-                                                                    ManualCandidates,
-                                                                    ManualCandidateCellMarker,
-                                                                >,
-                                                            );
-                                                    }
-                                                });
-                                        });
-                                }
-                            });
+                            .observe(candidate_cell_move::<AutoCandidates, AutoCandidateCellMarker>)
+                            .observe(candidate_cell_out::<AutoCandidateCellMarker>)
+                            .observe(
+                                candidate_cell_click::<AutoCandidates, AutoCandidateCellMarker>,
+                            );
                     }
                 });
+
+            // 手动候选格子容器
+            builder
+                .spawn((candidates_node(size), ManualCandidatesContainer))
+                .with_children(|builder| {
+                    for digit in size.digits() {
+                        builder
+                            .spawn((
+                                candidate_text(font_assets, digit, candidate_size),
+                                Node {
+                                    align_items: AlignItems::Center,
+                                    justify_items: JustifyItems::Center,
+                                    align_content: AlignContent::Center,
+                                    justify_content: JustifyContent::Center,
+                                    margin: UiRect {
+                                        top: Val::Px(candidate_size / 4.0),
+                                        ..default()
+                                    },
+                                    ..default()
+                                },
+                                ManualCandidateCellMarker {
+                                    index: digit.get(),
+                                    selected: false,
+                                },
+                            ))
+                            .observe(
+                                candidate_cell_move::<ManualCandidates, ManualCandidateCellMarker>,
+                            )
+                            .observe(candidate_cell_out::<ManualCandidateCellMarker>)
+                            .observe(
+                                candidate_cell_click::<ManualCandidates, ManualCandidateCellMarker>,
+                            );
+                    }
+
+                    // 中心标记盖在角标上面, 和手动候选数一起显示, 不挡住下面候选数的点击
+                    builder
+                        .spawn((
+                            Node {
+                                position_type: PositionType::Absolute,
+                                width: Val::Percent(100.0),
+                                height: Val::Percent(100.0),
+                                align_items: AlignItems::Center,
+                                justify_content: JustifyContent::Center,
+                                ..default()
+                            },
+                            PickingBehavior::IGNORE,
+                        ))
+                        .with_child((
+                            Text::default(),
+                            TextFont {
+                                font: font_assets.franklin_700.clone(),
+                                font_size: candidate_size,
+                                ..default()
+                            },
+                            TextColor(*ACCENT_BLUE),
+                            TextLayout::new_with_justify(JustifyText::Center),
+                            PickingBehavior::IGNORE,
+                            CenterMarksText,
+                        ));
+                });
+
+            spawn_region_border(builder, board, cell);
+            spawn_cage_outline(font_assets, builder, board, cell);
         });
+    cell_commands
+}
+
+/// 候选数按宫的形状排列, 9×9 是 3×3, 6×6 是 2 行 3 列
+fn candidates_node(size: GridSize) -> impl Bundle {
+    (
+        Visibility::Hidden,
+        Node {
+            height: Val::Percent(100.0),
+            display: Display::Grid,
+            aspect_ratio: Some(1.0),
+            position_type: PositionType::Absolute,
+            grid_template_columns: RepeatedGridTrack::flex(size.box_cols() as u16, 1.0),
+            grid_template_rows: RepeatedGridTrack::flex(size.box_rows() as u16, 1.0),
+            ..default()
+        },
+    )
+}
+
+fn candidate_text(font_assets: &FontAssets, digit: Digit, font_size: f32) -> impl Bundle {
+    (
+        Text::new(digit.symbol().to_string()),
+        TextFont {
+            font: font_assets.franklin_700.clone(),
+            font_size,
+            ..default()
+        },
+        TextColor(TRANSPARENT),
+        TextLayout::new_with_justify(JustifyText::Center),
+        Visibility::Inherited,
+        // 候选数涂的颜色
+        BackgroundColor(TRANSPARENT),
+        BorderRadius::all(Val::Percent(50.0)),
+    )
+}
+
+#[derive(Component)]
+struct GeneratingOverlay;
+
+fn show_generating(
+    generating: Option<Res<GeneratingPuzzle>>,
+    mut overlay: Single<&mut Visibility, With<GeneratingOverlay>>,
+) {
+    overlay.set_if_neq(if generating.is_some() {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    });
+}
+
+/// 按这一局的宫画粗线, 不规则数独的宫不是 3×3
+fn spawn_region_border(builder: &mut ChildBuilder, board: &Board, cell: u16) {
+    let side = board.size().side() as u16;
+    let (row, col) = (cell / side, cell % side);
+    // 棋盘最外面已经有边框, 只画两个宫之间的线
+    let edge = |other: Option<u16>| match other {
+        Some(other) if board.region(other) != board.region(cell) => Val::Px(2.0),
+        _ => Val::Px(0.0),
+    };
+    let border = UiRect::new(
+        edge(col.checked_sub(1).map(|col| row * side + col)),
+        edge((col < side - 1).then(|| cell + 1)),
+        edge(row.checked_sub(1).map(|row| row * side + col)),
+        edge((row < side - 1).then(|| cell + side)),
+    );
+
+    builder.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(-0.5),
+            bottom: Val::Px(-0.5),
+            left: Val::Px(-0.5),
+            right: Val::Px(-0.5),
+            border,
+            ..default()
+        },
+        BorderColor(*GRAY),
+        PickingBehavior::IGNORE,
+    ));
+}
+
+/// Windoku 额外宫里的格子, 底色和其他格子不同
+#[derive(Component)]
+pub struct WindokuCell;
+
+/// 对角线数独在主对角线上的格子画一条斜线, 连起来就是整条对角线
+fn spawn_diagonal_lines(builder: &mut ChildBuilder, size: GridSize, cell: u16) {
+    let side = size.side() as u16;
+    let (row, col) = (cell / side, cell % side);
+    // UI 的 y 轴朝下, 顺时针转 45° 是从左上到右下
+    let angles = [
+        (row == col).then_some(FRAC_PI_4),
+        (row + col == side - 1).then_some(-FRAC_PI_4),
+    ];
+    for angle in angles.into_iter().flatten() {
+        builder.spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(141.42),
+                height: Val::Px(1.5),
+                left: Val::Percent(-20.71),
+                top: Val::Percent(50.0),
+                ..default()
+            },
+            Transform::from_rotation(Quat::from_rotation_z(angle)),
+            BackgroundColor(*LIGHTER_GRAY),
+            PickingBehavior::IGNORE,
+        ));
+    }
+}
+
+/// 温度计和箭头经过这个格子的部分
+///
+/// 从格子中心画到线上前后两个格子的方向, 相邻格子画的两半在边上接起来.
+/// 温度计的灯泡是实心圆, 箭头从空心圆出发, 在最后一格画上箭头
+fn spawn_lines(builder: &mut ChildBuilder, board: &Board, cell: u16) {
+    for line in board.lines_of(cell) {
+        let Some(index) = line.path.iter().position(|position| position.0 == cell) else {
+            continue;
+        };
+        let here = line.path[index];
+        let direction = |other: CellPosition| {
+            let dx = other.col() as f32 - here.col() as f32;
+            let dy = other.row() as f32 - here.row() as f32;
+            (dy.atan2(dx), dx.hypot(dy))
+        };
+        let (thickness, color) = match line.kind {
+            LineKind::Thermometer => (12.0, *THERMO_GRAY),
+            LineKind::Arrow => (2.0, *DARK_GRAY),
+        };
+        // 箭头的圆圈里面不画线
+        let start = match (line.kind, index) {
+            (LineKind::Arrow, 0) => 36.0,
+            _ => 0.0,
+        };
+        let neighbours = [index.checked_sub(1), Some(index + 1)];
+        for &other in neighbours
+            .into_iter()
+            .flatten()
+            .filter_map(|other| line.path.get(other))
+        {
+            // 斜着相邻时画到格子的角上
+            let (angle, distance) = direction(other);
+            spawn_segment(builder, angle, start, distance * 50.0, thickness, color);
+        }
+
+        match (line.kind, index) {
+            (LineKind::Thermometer, 0) => {
+                builder.spawn((
+                    circle_node(66.0),
+                    BorderRadius::MAX,
+                    BackgroundColor(color),
+                    PickingBehavior::IGNORE,
+                ));
+            }
+            // 拐弯的地方补一个圆点, 线才是连着的
+            (LineKind::Thermometer, _) => {
+                builder.spawn((
+                    Node {
+                        position_type: PositionType::Absolute,
+                        width: Val::Px(thickness),
+                        height: Val::Px(thickness),
+                        left: Val::Percent(50.0),
+                        top: Val::Percent(50.0),
+                        margin: UiRect::new(
+                            Val::Px(-thickness / 2.0),
+                            Val::ZERO,
+                            Val::Px(-thickness / 2.0),
+                            Val::ZERO,
+                        ),
+                        ..default()
+                    },
+                    BorderRadius::MAX,
+                    BackgroundColor(color),
+                    PickingBehavior::IGNORE,
+                ));
+            }
+            (LineKind::Arrow, 0) => {
+                builder.spawn((
+                    Node {
+                        border: UiRect::all(Val::Px(thickness)),
+                        ..circle_node(72.0)
+                    },
+                    BorderRadius::MAX,
+                    BorderColor(color),
+                    PickingBehavior::IGNORE,
+                ));
+            }
+            // 箭头的两翼从格子中心往回斜着画
+            (LineKind::Arrow, last) if last == line.path.len() - 1 => {
+                let (back, _) = direction(line.path[last - 1]);
+                for wing in [-0.6, 0.6] {
+                    spawn_segment(builder, back + wing, 0.0, 24.0, thickness, color);
+                }
+            }
+            (LineKind::Arrow, _) => {}
+        }
+    }
+}
+
+/// 从格子中心朝 `angle` 方向, 离中心 `from` 到 `to` 的一段线, 距离是格子边长的百分比
+fn spawn_segment(
+    builder: &mut ChildBuilder,
+    angle: f32,
+    from: f32,
+    to: f32,
+    thickness: f32,
+    color: Color,
+) {
+    let (middle, length) = ((from + to) / 2.0, to - from);
+    builder.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(length),
+            height: Val::Px(thickness),
+            left: Val::Percent(50.0 + middle * angle.cos() - length / 2.0),
+            top: Val::Percent(50.0 + middle * angle.sin()),
+            margin: UiRect::top(Val::Px(-thickness / 2.0)),
+            ..default()
+        },
+        // UI 的 y 轴朝下, 正的角度是顺时针
+        Transform::from_rotation(Quat::from_rotation_z(angle)),
+        BackgroundColor(color),
+        PickingBehavior::IGNORE,
+    ));
+}
+
+/// 格子正中间的圆, 直径是格子边长的 `percent`%
+fn circle_node(percent: f32) -> Node {
+    Node {
+        position_type: PositionType::Absolute,
+        width: Val::Percent(percent),
+        height: Val::Percent(percent),
+        left: Val::Percent((100.0 - percent) / 2.0),
+        top: Val::Percent((100.0 - percent) / 2.0),
+        ..default()
+    }
+}
+
+/// 格子之间边上的黑白点以及 X、V
+///
+/// 画在棋盘网格里所有格子的后面, 才不会被右边和下边的格子盖住.
+/// 位置按网格的百分比算, 标记的中心在两个格子的分界线上
+pub(crate) fn spawn_edge_marks(
+    font_assets: &FontAssets,
+    builder: &mut ChildBuilder,
+    board: &Board,
+) {
+    let side = board.size().side() as f32;
+    for edge in board.edges() {
+        let [a, b] = edge.cells;
+        let (mut row, mut col) = (a.row() as f32 + 0.5, a.col() as f32 + 0.5);
+        if b.row() > a.row() {
+            row += 0.5;
+        } else {
+            col += 0.5;
+        }
+        let size = match edge.kind {
+            EdgeKind::White | EdgeKind::Black => 12.0,
+            EdgeKind::X | EdgeKind::V => 18.0,
+        };
+        let node = Node {
+            position_type: PositionType::Absolute,
+            width: Val::Px(size),
+            height: Val::Px(size),
+            left: Val::Percent(col / side * 100.0),
+            top: Val::Percent(row / side * 100.0),
+            margin: UiRect::new(
+                Val::Px(-size / 2.0),
+                Val::ZERO,
+                Val::Px(-size / 2.0),
+                Val::ZERO,
+            ),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        };
+        match edge.kind {
+            EdgeKind::White => {
+                builder.spawn((
+                    Node {
+                        border: UiRect::all(Val::Px(1.5)),
+                        ..node
+                    },
+                    BorderRadius::MAX,
+                    BorderColor(*DARK_BLACK),
+                    BackgroundColor(Color::WHITE),
+                    PickingBehavior::IGNORE,
+                ));
+            }
+            EdgeKind::Black => {
+                builder.spawn((
+                    node,
+                    BorderRadius::MAX,
+                    BackgroundColor(*DARK_BLACK),
+                    PickingBehavior::IGNORE,
+                ));
+            }
+            EdgeKind::X | EdgeKind::V => {
+                let letter = if edge.kind == EdgeKind::X { "X" } else { "V" };
+                builder
+                    .spawn((node, BackgroundColor(Color::WHITE), PickingBehavior::IGNORE))
+                    .with_child((
+                        Text::new(letter),
+                        TextFont {
+                            font: font_assets.franklin_700.clone(),
+                            font_size: 14.0,
+                            ..default()
+                        },
+                        TextColor(*DARK_BLACK),
+                        PickingBehavior::IGNORE,
+                    ));
+            }
+        }
+    }
+}
+
+/// 杀手数独的笼子边框和笼子的和
+fn spawn_cage_outline(
+    font_assets: &FontAssets,
+    builder: &mut ChildBuilder,
+    board: &Board,
+    cell: u16,
+) {
+    let Some(cage) = board.cage_of(cell) else {
+        return;
+    };
+    let side = board.size().side() as u16;
+    let (row, col) = (cell / side, cell % side);
+    let same_cage = |other: Option<u16>| other.is_some_and(|other| cage.cells.contains(&other));
+    // 和笼子外的格子相邻的一边画线, 线往里缩一点和宫格线分开
+    let edge = |inside: bool| {
+        if inside {
+            (Val::Px(0.0), Val::Px(0.0))
+        } else {
+            (Val::Px(3.0), Val::Px(1.0))
+        }
+    };
+    let (top, top_border) = edge(same_cage(row.checked_sub(1).map(|row| row * side + col)));
+    let (bottom, bottom_border) = edge(same_cage((row < side - 1).then(|| cell + side)));
+    let (left, left_border) = edge(same_cage(col.checked_sub(1).map(|col| row * side + col)));
+    let (right, right_border) = edge(same_cage((col < side - 1).then(|| cell + 1)));
+
+    builder.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            top,
+            bottom,
+            left,
+            right,
+            border: UiRect::new(left_border, right_border, top_border, bottom_border),
+            ..default()
+        },
+        BorderColor(*DARK_GRAY),
+        PickingBehavior::IGNORE,
+    ));
+    if cage.cells.first() == Some(&cell) {
+        builder.spawn((
+            Text::new(cage.sum.to_string()),
+            TextFont {
+                font: font_assets.franklin_600.clone(),
+                font_size: 11.0,
+                ..default()
+            },
+            TextColor(*DARK_GRAY),
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(4.0),
+                left: Val::Px(5.0),
+                ..default()
+            },
+            PickingBehavior::IGNORE,
+        ));
+    }
 }
 
 fn spawn_conflict_container(texture_assets: &Res<TextureAssets>, builder: &mut ChildBuilder) {
@@ -345,13 +765,59 @@ impl PreviewCandidate {
 fn on_click_cell(
     trigger: Trigger<Pointer<Click>>,
     mut commands: Commands,
-    exist: Query<Entity, With<SelectedCell>>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
 ) {
-    for entity in exist.iter() {
-        commands.entity(entity).remove::<SelectedCell>();
-    }
+    commands.trigger_targets(SelectCell::from_keys(&keyboard_input), trigger.entity());
+}
 
-    commands.entity(trigger.entity()).insert(SelectedCell);
+/// 按住鼠标拖过的格子都选中, 从第一个格子开始时和点击一样看修饰键
+fn on_drag_start_cell(
+    trigger: Trigger<Pointer<DragStart>>,
+    mut commands: Commands,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+) {
+    let select = match SelectCell::from_keys(&keyboard_input) {
+        SelectCell::Only => SelectCell::Only,
+        _ => SelectCell::Add,
+    };
+    commands.trigger_targets(select, trigger.entity());
+}
+
+fn on_drag_enter_cell(trigger: Trigger<Pointer<DragEnter>>, mut commands: Commands) {
+    commands.trigger_targets(SelectCell::Add, trigger.entity());
+}
+
+/// 选中格子, 方向键从最后选中的格子开始移动
+fn on_select_cell(
+    trigger: Trigger<SelectCell>,
+    mut commands: Commands,
+    q_selected: Query<Entity, With<SelectedCell>>,
+    q_cursor: Query<Entity, With<CursorCell>>,
+) {
+    let entity = trigger.entity();
+    let selected = q_selected.contains(entity);
+    match trigger.event() {
+        SelectCell::Only => {
+            for other in q_selected.iter().filter(|&other| other != entity) {
+                commands.entity(other).remove::<SelectedCell>();
+            }
+        }
+        SelectCell::Add => {}
+        SelectCell::Toggle if selected && q_selected.iter().len() > 1 => {
+            commands.entity(entity).remove::<SelectedCell>();
+            return;
+        }
+        SelectCell::Toggle => {}
+    }
+    if !selected {
+        commands.entity(entity).insert(SelectedCell);
+    }
+    if !q_cursor.contains(entity) {
+        for cursor in q_cursor.iter() {
+            commands.entity(cursor).remove::<CursorCell>();
+        }
+        commands.entity(entity).insert(CursorCell);
+    }
 }
 
 fn show_digit_cell(
@@ -364,7 +830,7 @@ fn show_digit_cell(
             if let Ok((mut text, mut visibility, mut text_color)) = digit_cell.get_mut(child) {
                 if let CellMode::Digit = cell_mode {
                     if let Some(digit) = digit_value.0 {
-                        text.0 = digit.get().to_string();
+                        text.0 = digit.symbol().to_string();
                     }
                     *visibility = Visibility::Visible;
                     if opt_revealed.is_some() {
@@ -381,21 +847,36 @@ fn show_digit_cell(
 }
 
 fn show_candidates<C: CandidatesValue, M: CandidateMarker>(
-    q_cell: Query<(Entity, &C)>,
+    q_cell: Query<(Entity, &C, &CellColor, Option<&HintCandidates>)>,
     children: Query<&Children>,
-    mut candidate_cell: Query<(&mut TextColor, &mut M)>,
+    mut candidate_cell: Query<(&mut TextColor, &mut BackgroundColor, &mut M)>,
+    highlighted: Res<HighlightedDigit>,
+    settings: Res<Settings>,
 ) {
-    for (entity, manual_candidates) in q_cell.iter() {
+    let highlighted = highlighted.digit(settings.highlight_same_candidates);
+    for (entity, manual_candidates, cell_color, opt_hint) in q_cell.iter() {
         for child in children.iter_descendants(entity) {
-            if let Ok((mut text_color, mut cell_marker)) = candidate_cell.get_mut(child) {
-                if manual_candidates
-                    .candidates()
-                    .contains(Digit::new(cell_marker.index()).as_set())
-                {
-                    cell_marker.set_selected(true);
+            if let Ok((mut text_color, mut background, mut cell_marker)) =
+                candidate_cell.get_mut(child)
+            {
+                let digit = Digit::new(cell_marker.index());
+                let selected = manual_candidates.candidates().contains(digit.as_set());
+                cell_marker.set_selected(selected);
+
+                let color = cell_color
+                    .0
+                    .candidate(digit)
+                    .filter(|_| selected)
+                    .and_then(palette_color);
+                background.set_if_neq(BackgroundColor(color.unwrap_or(TRANSPARENT)));
+
+                if let Some(color) = opt_hint.and_then(|hint| hint.color(digit)) {
+                    *text_color = TextColor(color);
+                } else if selected && highlighted == Some(digit) {
+                    *text_color = TextColor(*SAME_CANDIDATE);
+                } else if selected {
                     *text_color = TextColor(*GRAY2);
                 } else {
-                    cell_marker.set_selected(false);
                     *text_color = TextColor(TRANSPARENT);
                 }
             }
@@ -403,6 +884,161 @@ fn show_candidates<C: CandidatesValue, M: CandidateMarker>(
     }
 }
 
+/// 调色板里的颜色, 存档里可能有超出范围的序号
+fn palette_color(color: u8) -> Option<Color> {
+    PALETTE.get(color as usize).copied()
+}
+
+/// 格子涂的颜色
+#[derive(Component)]
+struct CellColorLayer;
+
+fn show_cell_color(
+    q_cell: Query<(Entity, &CellColor), Changed<CellColor>>,
+    children: Query<&Children>,
+    mut q_layer: Query<&mut BackgroundColor, With<CellColorLayer>>,
+) {
+    for (entity, cell_color) in q_cell.iter() {
+        let color = cell_color.0.cell.and_then(palette_color);
+        for child in children.iter_descendants(entity) {
+            if let Ok(mut background) = q_layer.get_mut(child) {
+                background.0 = color.unwrap_or(TRANSPARENT);
+            }
+        }
+    }
+}
+
+/// 中心标记的文字
+#[derive(Component)]
+struct CenterMarksText;
+
+/// 中心标记从小到大排成一行, 放不下时换行
+fn show_center_marks(
+    q_cell: Query<(Entity, &CenterMarks), Changed<CenterMarks>>,
+    children: Query<&Children>,
+    mut q_text: Query<&mut Text, With<CenterMarksText>>,
+) {
+    for (entity, center_marks) in q_cell.iter() {
+        for child in children.iter_descendants(entity) {
+            if let Ok(mut text) = q_text.get_mut(child) {
+                text.0 = center_marks.0.digits().map(Digit::symbol).collect();
+            }
+        }
+    }
+}
+
+/// 高亮的数字, 光标所在格子里填的数字, 或者鼠标停着的数字按钮的数字
+#[derive(Resource, Default)]
+pub struct HighlightedDigit {
+    pub cursor: Option<Digit>,
+    pub hovered: Option<Digit>,
+}
+
+impl HighlightedDigit {
+    /// 鼠标停在数字按钮上时不管设置都高亮, 光标的数字按设置决定
+    fn digit(&self, enabled: bool) -> Option<Digit> {
+        self.hovered.or(self.cursor.filter(|_| enabled))
+    }
+}
+
+fn update_highlighted_digit(
+    cursor: Query<&DigitValueCell, With<CursorCell>>,
+    mut highlighted: ResMut<HighlightedDigit>,
+) {
+    let digit = cursor.iter().next().and_then(|digit| digit.0);
+    if highlighted.cursor != digit {
+        highlighted.cursor = digit;
+    }
+}
+
+/// 提示覆盖层: 推理时查看的宫和构成技巧的格子
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HintCell {
+    House,
+    Pattern,
+}
+
+/// 提示覆盖层: 格子里需要标色的候选数
+#[derive(Component, Clone, Copy, Debug)]
+pub struct HintCandidates {
+    pub pattern: DigitSet,
+    pub eliminate: DigitSet,
+    pub place: Option<Digit>,
+}
+
+impl Default for HintCandidates {
+    fn default() -> Self {
+        HintCandidates {
+            pattern: DigitSet::NONE,
+            eliminate: DigitSet::NONE,
+            place: None,
+        }
+    }
+}
+
+impl HintCandidates {
+    pub fn is_empty(&self) -> bool {
+        self.pattern.is_empty() && self.eliminate.is_empty() && self.place.is_none()
+    }
+
+    fn color(&self, digit: Digit) -> Option<Color> {
+        if self.place == Some(digit) {
+            Some(*HINT_PLACE)
+        } else if self.eliminate.contains(digit.as_set()) {
+            Some(*HINT_ELIMINATE)
+        } else if self.pattern.contains(digit.as_set()) {
+            Some(*ACCENT_BLUE)
+        } else {
+            None
+        }
+    }
+}
+
+/// 按选中、提示和固定状态决定格子的背景色
+///
+/// 设置里打开时, 光标所在的行、列和宫以及和光标数字相同的格子也有底色,
+/// 鼠标停在数字按钮上时这个数字的格子总有底色
+fn cell_background(
+    mut q_cell: Query<(
+        &CellPosition,
+        &DigitValueCell,
+        &mut BackgroundColor,
+        Has<SelectedCell>,
+        Has<FixedCell>,
+        Has<WindokuCell>,
+        Option<&HintCell>,
+    )>,
+    cursor: Query<&CellPosition, With<CursorCell>>,
+    highlighted: Res<HighlightedDigit>,
+    settings: Res<Settings>,
+    sudoku_manager: Option<Res<SudokuManager>>,
+) {
+    let cursor = cursor.iter().next().map(|position| position.0);
+    let is_peer = |cell: u16| {
+        settings.highlight_peers
+            && cursor
+                .zip(sudoku_manager.as_ref())
+                .is_some_and(|(cursor, sudoku_manager)| sudoku_manager.board.in_house(cursor, cell))
+    };
+    let highlighted = highlighted.digit(settings.highlight_same_digit);
+    for (position, digit, mut background, selected, fixed, windoku, opt_hint) in q_cell.iter_mut() {
+        let same_digit = highlighted.is_some() && digit.0 == highlighted;
+        let color = match (selected, opt_hint) {
+            (true, _) => *STRANDS_YELLOW,
+            (false, Some(HintCell::Pattern)) => *HINT_PATTERN,
+            (false, Some(HintCell::House)) => *HINT_HOUSE,
+            (false, None) if same_digit => *SAME_DIGIT,
+            (false, None) if is_peer(position.0) && fixed => *PEER_FIXED,
+            (false, None) if is_peer(position.0) => *PEER_SHADE,
+            (false, None) if windoku && fixed => *WINDOKU_FIXED,
+            (false, None) if windoku => *WINDOKU_SHADE,
+            (false, None) if fixed => *EXTRA_LIGHT_GRAY,
+            (false, None) => WHITE_COLOR,
+        };
+        background.set_if_neq(BackgroundColor(color));
+    }
+}
+
 fn show_preview_number(
     mut candidate_cell: Query<(Entity, &mut TextColor, &mut PreviewCandidate)>,
     time: Res<Time>,
@@ -426,17 +1062,26 @@ fn show_preview_number(
     }
 }
 
+/// 点选中格子里的候选数时切换这个候选数, 涂色时给它涂色
 fn candidate_cell_click<C: CandidatesValue, M: CandidateMarker>(
     click: Trigger<Pointer<Click>>,
     cell: Query<&M>,
     parent_query: Query<&Parent>,
-    mut q_select: Query<&mut C, With<SelectedCell>>,
+    q_select: Query<&C, With<SelectedCell>>,
+    selected_tab: Res<SelectedTab>,
     mut commands: Commands,
 ) {
     let candidate_cell = cell.get(click.entity()).unwrap();
     for ancestor in parent_query.iter_ancestors(click.entity()) {
-        if let Ok(mut cell_value) = q_select.get_mut(ancestor) {
-            cell_value.insert(Digit::new(candidate_cell.index()));
+        if q_select.contains(ancestor) {
+            if selected_tab.0 == ControlTab::Color {
+                if candidate_cell.selected() {
+                    let digit = Digit::new(candidate_cell.index());
+                    commands.trigger_targets(NewCandidateColor(digit), ancestor);
+                }
+                continue;
+            }
+            commands.trigger(NewCandidate::new(candidate_cell.index()));
 
             commands.entity(click.entity()).remove::<PreviewCandidate>();
         }
@@ -483,38 +1128,29 @@ fn candidate_cell_out<M: CandidateMarker>(
     }
 }
 
+/// 方向键移动选中的格子, 按住 Shift 时把经过的格子加到选中的格子里
 fn move_select_cell(
     move_ev: Trigger<MoveSelectCell>,
     mut commands: Commands,
-    q_select: Single<(Entity, &CellPosition), With<SelectedCell>>,
-    q_other: Query<(Entity, &CellPosition), Without<SelectedCell>>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    cursor: Single<&CellPosition, With<CursorCell>>,
+    q_cell: Query<(Entity, &CellPosition)>,
 ) {
-    let (entity, cell_position) = q_select.into_inner();
     let new_position = match move_ev.event() {
-        MoveSelectCell::Up if cell_position.row() > 0 => Some(CellPosition::from_row_col(
-            cell_position.row() - 1,
-            cell_position.col(),
-        )),
-        MoveSelectCell::Down if cell_position.row() < 8 => Some(CellPosition::from_row_col(
-            cell_position.row() + 1,
-            cell_position.col(),
-        )),
-        MoveSelectCell::Left if cell_position.col() > 0 => Some(CellPosition::from_row_col(
-            cell_position.row(),
-            cell_position.col() - 1,
-        )),
-        MoveSelectCell::Right if cell_position.col() < 8 => Some(CellPosition::from_row_col(
-            cell_position.row(),
-            cell_position.col() + 1,
-        )),
-        _ => None,
+        MoveSelectCell::Up => cursor.step(-1, 0),
+        MoveSelectCell::Down => cursor.step(1, 0),
+        MoveSelectCell::Left => cursor.step(0, -1),
+        MoveSelectCell::Right => cursor.step(0, 1),
+    };
+    let select = match SelectCell::from_keys(&keyboard_input) {
+        SelectCell::Add => SelectCell::Add,
+        _ => SelectCell::Only,
     };
 
     if let Some(new_position) = new_position {
-        commands.entity(entity).remove::<SelectedCell>();
-        for (other_entity, cell_position) in q_other.iter() {
+        for (entity, cell_position) in q_cell.iter() {
             if cell_position == &new_position {
-                commands.entity(other_entity).insert(SelectedCell);
+                commands.trigger_targets(select, entity);
                 break;
             }
         }
@@ -1,85 +1,75 @@
+use crate::game::{
+    grid::{Digit, DigitSet},
+    model::{Board, CellColors},
+};
 use bevy::prelude::*;
 use bevy::utils::HashSet;
-use std::ops::BitXorAssign;
-use sudoku::bitset::Set;
-use sudoku::board::{CellState, Digit};
 
 #[derive(Bundle)]
 pub struct CellValueBundle {
     pub digit_value: DigitValueCell,
     pub auto_candidates: AutoCandidates,
     pub manual_candidates: ManualCandidates,
+    pub center_marks: CenterMarks,
+    pub color: CellColor,
     pub cell_mode: CellMode,
 }
 
 impl CellValueBundle {
-    pub fn from_cell_state(cell_state: CellState, auto_candidates: bool) -> Self {
-        let cell_mode = if auto_candidates {
+    /// 从棋盘模型的一个格子创建
+    pub fn from_board(board: &Board, cell: u16, auto_candidates: bool) -> Self {
+        let cell_mode = if board.digit(cell).is_some() {
+            CellMode::Digit
+        } else if auto_candidates {
             CellMode::AutoCandidates
         } else {
             CellMode::ManualCandidates
         };
-        let (digit_value, auto_candidates, manual_candidates, cell_mode) = match cell_state {
-            CellState::Digit(digit) => (
-                DigitValueCell(Some(digit)),
-                AutoCandidates(Set::NONE),
-                ManualCandidates(Set::NONE),
-                CellMode::Digit,
-            ),
-            CellState::Candidates(digit_set) => (
-                DigitValueCell(None),
-                AutoCandidates(digit_set),
-                ManualCandidates(Set::NONE),
-                cell_mode,
-            ),
-        };
 
         CellValueBundle {
-            digit_value,
-            auto_candidates,
-            manual_candidates,
+            digit_value: DigitValueCell(board.digit(cell)),
+            auto_candidates: AutoCandidates(board.auto_candidates(cell)),
+            manual_candidates: ManualCandidates(board.manual_candidates(cell)),
+            center_marks: CenterMarks(board.center_marks(cell)),
+            color: CellColor(board.colors(cell)),
             cell_mode,
         }
     }
 }
 
-#[derive(Component, Debug, Deref, DerefMut)]
+#[derive(Component, Debug, PartialEq, Deref, DerefMut)]
 pub struct DigitValueCell(pub Option<Digit>);
 
-#[derive(Component, Debug)]
-pub struct AutoCandidates(pub Set<Digit>);
+#[derive(Component, Debug, PartialEq)]
+pub struct AutoCandidates(pub DigitSet);
 impl CandidatesValue for AutoCandidates {
-    fn candidates_mut(&mut self) -> &mut Set<Digit> {
-        &mut self.0
-    }
-    fn candidates(&self) -> &Set<Digit> {
+    fn candidates(&self) -> &DigitSet {
         &self.0
     }
 }
 
-#[derive(Component, Debug)]
-pub struct ManualCandidates(pub Set<Digit>);
+#[derive(Component, Debug, PartialEq)]
+pub struct ManualCandidates(pub DigitSet);
 
 impl CandidatesValue for ManualCandidates {
-    fn candidates_mut(&mut self) -> &mut Set<Digit> {
-        &mut self.0
-    }
-
-    fn candidates(&self) -> &Set<Digit> {
+    fn candidates(&self) -> &DigitSet {
         &self.0
     }
 }
 
-pub trait CandidatesValue: Component {
-    fn insert(&mut self, digit: Digit) {
-        self.candidates_mut().bitxor_assign(digit);
-    }
+/// 中心标记, 和手动候选数一起显示
+#[derive(Component, Debug, PartialEq)]
+pub struct CenterMarks(pub DigitSet);
+
+/// 格子和候选数上涂的颜色
+#[derive(Component, Debug, PartialEq)]
+pub struct CellColor(pub CellColors);
 
-    fn candidates_mut(&mut self) -> &mut Set<Digit>;
-    fn candidates(&self) -> &Set<Digit>;
+pub trait CandidatesValue: Component {
+    fn candidates(&self) -> &DigitSet;
 }
 
-#[derive(Component, Debug, PartialEq, Eq)]
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CellMode {
     Digit,
     AutoCandidates,
@@ -140,7 +130,7 @@ pub trait CandidateMarker: Component {
 }
 
 /// 冲突红点
-#[derive(Component, Default, Deref, DerefMut)]
+#[derive(Component, Default, PartialEq, Deref, DerefMut)]
 pub struct ConflictCell(pub HashSet<Entity>);
 
 /// 已经揭示的格子
@@ -154,3 +144,7 @@ pub struct CorrectionCell;
 ///  选中的格子
 #[derive(Component)]
 pub struct SelectedCell;
+
+/// 最后选中的格子, 方向键从这里移动. 按住 Ctrl 取消选中之后还留在原处
+#[derive(Component)]
+pub struct CursorCell;
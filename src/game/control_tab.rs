@@ -1,5 +1,16 @@
-use crate::color::{DARK_BLACK, DARK_GRAY, EXTRA_LIGHT_GRAY, GRAY, LIGHT_GRAY, WHITE_COLOR};
-use crate::game::{AutoCandidateMode, CleanCell, NewCandidate, NewDigit, SelectedCell};
+use crate::color::{
+    DARK_BLACK, DARK_GRAY, EXTRA_LIGHT_GRAY, GRAY, LIGHTER_GRAY, LIGHT_GRAY, PALETTE, WHITE_COLOR,
+};
+use crate::game::{
+    board::HighlightedDigit,
+    cell_state::DigitValueCell,
+    grid::{Digit, GridSize},
+    history::MoveHistory,
+    model::PALETTE_SIZE,
+    position::CellPosition,
+    AutoCandidateMode, CleanCell, NewCandidate, NewCenterMark, NewColor, NewDigit, SelectedCell,
+    StartPuzzle, SudokuManager,
+};
 use crate::loading::{FontAssets, TextureAssets};
 use bevy::prelude::*;
 
@@ -14,11 +25,24 @@ pub(crate) fn plugin(app: &mut App) {
             Update,
             (update_auto_candidate_icon,).run_if(resource_changed::<AutoCandidateMode>),
         )
-        .add_observer(update_control_tab);
+        .add_systems(
+            Update,
+            update_remaining.run_if(resource_exists::<SudokuManager>),
+        )
+        .add_observer(update_control_tab)
+        .add_observer(spawn_number_pad);
 }
 
+/// 切换输入的内容
 #[derive(Event)]
-pub struct ToggleTab;
+pub enum ToggleTab {
+    /// 依次切到下一个
+    Next,
+    /// 按住修饰键时临时切到这一个
+    Hold(ControlTab),
+    /// 松开修饰键, 切回按下之前的
+    Release,
+}
 
 #[derive(Component)]
 pub struct ControlDigit;
@@ -26,18 +50,46 @@ pub struct ControlDigit;
 #[derive(Component)]
 pub struct ControlCandidate;
 
-#[derive(Debug, Clone, Eq, PartialEq, Default)]
+#[derive(Component)]
+pub struct ControlCenter;
+
+/// 涂色时按钮上的色块
+#[derive(Component)]
+pub struct ControlSwatch;
+
+/// 输入数字, 候选数 (角标), 中心标记还是涂色
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
 pub enum ControlTab {
     #[default]
     Normal,
     Candidate,
+    Center,
+    Color,
+}
+
+impl ControlTab {
+    const ALL: [ControlTab; 4] = [
+        ControlTab::Normal,
+        ControlTab::Candidate,
+        ControlTab::Center,
+        ControlTab::Color,
+    ];
+
+    fn next(self) -> ControlTab {
+        match self {
+            ControlTab::Normal => ControlTab::Candidate,
+            ControlTab::Candidate => ControlTab::Center,
+            ControlTab::Center => ControlTab::Color,
+            ControlTab::Color => ControlTab::Normal,
+        }
+    }
 }
 
 #[derive(Component)]
 struct ChangeTab(ControlTab);
 
-#[derive(Resource, Debug, Deref, DerefMut, Default)]
-struct SelectedTab(ControlTab);
+#[derive(Resource, Debug, Deref, DerefMut, Default, PartialEq)]
+pub(crate) struct SelectedTab(pub ControlTab);
 
 pub(crate) fn control_board(
     font_assets: &Res<FontAssets>,
@@ -71,69 +123,47 @@ pub(crate) fn control_board(
                 ))
                 .with_children(|builder| {
                     // 切换按钮
-                    builder
-                        .spawn((
-                            Button,
-                            Node {
-                                width: Val::Px(140.0),
-                                height: Val::Px(38.0),
-                                justify_content: JustifyContent::Center,
-                                align_items: AlignItems::Center,
-                                border: UiRect::all(Val::Px(0.0)),
-                                padding: UiRect::axes(Val::Px(6.0), Val::Px(1.0)),
-                                ..Default::default()
-                            },
-                            BackgroundColor(*DARK_BLACK),
-                            ChangeTab(ControlTab::Normal),
-                            BorderRadius::left(Val::Px(3.0)),
-                            // BorderColor(WHITE_COLOR),
-                        ))
-                        .with_child((
-                            Text::new("Normal"),
-                            TextFont {
-                                font: font_assets.franklin_500.clone(),
-                                font_size: 14.0,
-                                ..default()
-                            },
-                            TextColor(WHITE_COLOR),
-                        ))
-                        .observe(
-                            |_trigger: Trigger<Pointer<Click>>, mut commands: Commands| {
-                                commands.trigger(ToggleTab);
-                            },
-                        );
-
-                    builder
-                        .spawn((
-                            Button,
-                            Node {
-                                width: Val::Px(140.0),
-                                height: Val::Px(38.0),
-                                justify_content: JustifyContent::Center,
-                                align_items: AlignItems::Center,
-                                border: UiRect::all(Val::Px(1.0)),
-                                padding: UiRect::axes(Val::Px(6.0), Val::Px(1.0)),
-                                ..Default::default()
-                            },
-                            BackgroundColor(WHITE_COLOR),
-                            ChangeTab(ControlTab::Candidate),
-                            BorderRadius::right(Val::Px(3.0)),
-                            BorderColor(*LIGHT_GRAY),
-                        ))
-                        .with_child((
-                            Text::new("Candidate"),
-                            TextFont {
-                                font: font_assets.franklin_500.clone(),
-                                font_size: 14.0,
-                                ..default()
-                            },
-                            TextColor(*DARK_GRAY),
-                        ))
-                        .observe(
-                            |_trigger: Trigger<Pointer<Click>>, mut commands: Commands| {
-                                commands.trigger(ToggleTab);
-                            },
-                        );
+                    for tab in ControlTab::ALL {
+                        let (label, radius) = match tab {
+                            ControlTab::Normal => ("Normal", BorderRadius::left(Val::Px(3.0))),
+                            ControlTab::Candidate => ("Corner", BorderRadius::ZERO),
+                            ControlTab::Center => ("Center", BorderRadius::ZERO),
+                            ControlTab::Color => ("Color", BorderRadius::right(Val::Px(3.0))),
+                        };
+                        let selected = tab == ControlTab::default();
+                        builder
+                            .spawn((
+                                Button,
+                                Node {
+                                    width: Val::Px(140.0),
+                                    height: Val::Px(38.0),
+                                    justify_content: JustifyContent::Center,
+                                    align_items: AlignItems::Center,
+                                    border: tab_border(tab, ControlTab::default()),
+                                    padding: UiRect::axes(Val::Px(6.0), Val::Px(1.0)),
+                                    ..Default::default()
+                                },
+                                BackgroundColor(if selected { *DARK_BLACK } else { WHITE_COLOR }),
+                                ChangeTab(tab),
+                                radius,
+                                BorderColor(if selected { WHITE_COLOR } else { *LIGHT_GRAY }),
+                            ))
+                            .with_child((
+                                Text::new(label),
+                                TextFont {
+                                    font: font_assets.franklin_500.clone(),
+                                    font_size: 14.0,
+                                    ..default()
+                                },
+                                TextColor(if selected { WHITE_COLOR } else { *DARK_GRAY }),
+                            ))
+                            .observe(
+                                move |_trigger: Trigger<Pointer<Click>>,
+                                      mut selected_tab: ResMut<SelectedTab>| {
+                                    selected_tab.set_if_neq(SelectedTab(tab));
+                                },
+                            );
+                    }
                 });
 
             // 数字键盘
@@ -151,92 +181,19 @@ pub(crate) fn control_board(
                     },
                 ))
                 .with_children(|builder| {
-                    for i in 1..=9 {
-                        builder
-                            .spawn((
-                                Node {
-                                    width: Val::Px(70.0),
-                                    height: Val::Px(70.0),
-                                    border: UiRect::all(Val::Px(1.0)),
-                                    margin: UiRect {
-                                        top: Val::Px(14.0),
-                                        ..default()
-                                    },
-                                    align_items: AlignItems::Center,
-                                    justify_items: JustifyItems::Center,
-                                    align_content: AlignContent::Center,
-                                    justify_content: JustifyContent::Center,
-                                    ..default()
-                                },
-                                BorderRadius::all(Val::Px(3.0)),
-                                BackgroundColor(*EXTRA_LIGHT_GRAY),
-                                BorderColor(*GRAY),
-                                ControlNumber(i),
-                            ))
-                            .observe(mouse_click_control_digit)
-                            .with_children(|builder| {
-                                // 数字格子
-                                builder.spawn((
-                                    Text::new(i.to_string()),
-                                    TextFont {
-                                        font: font_assets.franklin_700.clone(),
-                                        font_size: 32.0,
-                                        ..default()
-                                    },
-                                    TextColor(*DARK_BLACK),
-                                    Visibility::Visible,
-                                    ControlDigit,
-                                ));
-
-                                // 候选格子容器
-                                builder
-                                    .spawn((
-                                        Visibility::Hidden,
-                                        ControlCandidate,
-                                        Node {
-                                            height: Val::Percent(100.0),
-                                            display: Display::Grid,
-                                            aspect_ratio: Some(1.0),
-                                            position_type: PositionType::Absolute,
-                                            grid_template_columns: RepeatedGridTrack::flex(3, 1.0),
-                                            grid_template_rows: RepeatedGridTrack::flex(3, 1.0),
-                                            ..default()
-                                        },
-                                    ))
-                                    .with_children(|builder| {
-                                        // 9个候选数字格子
-                                        for k in 1..=9u8 {
-                                            let visibility = if k == i {
-                                                Visibility::Inherited
-                                            } else {
-                                                Visibility::Hidden
-                                            };
-                                            builder.spawn((
-                                                visibility,
-                                                Text::new(k.to_string()),
-                                                TextFont {
-                                                    font: font_assets.franklin_700.clone(),
-                                                    font_size: 16.0,
-                                                    ..default()
-                                                },
-                                                TextColor(*DARK_BLACK),
-                                                TextLayout::new_with_justify(JustifyText::Center),
-                                                Node {
-                                                    align_items: AlignItems::Center,
-                                                    justify_items: JustifyItems::Center,
-                                                    align_content: AlignContent::Center,
-                                                    justify_content: JustifyContent::Center,
-                                                    margin: UiRect {
-                                                        top: Val::Px(4.),
-                                                        ..default()
-                                                    },
-                                                    ..default()
-                                                },
-                                            ));
-                                        }
-                                    });
-                            });
-                    }
+                    // 数字按钮在开始一局时按棋盘大小生成
+                    builder.spawn((
+                        Node {
+                            width: Val::Percent(100.0),
+                            display: Display::Flex,
+                            flex_direction: FlexDirection::Row,
+                            flex_wrap: FlexWrap::Wrap,
+                            justify_content: JustifyContent::SpaceBetween,
+                            align_content: AlignContent::SpaceBetween,
+                            ..default()
+                        },
+                        NumberPad,
+                    ));
 
                     // 删除按钮
                     builder
@@ -260,8 +217,8 @@ pub(crate) fn control_board(
                             BorderColor(*GRAY),
                         ))
                         .observe(
-                            |_trigger: Trigger<Pointer<Click>>, mut commands: Commands, selected: Single<Entity, With<SelectedCell>>| {
-                                commands.trigger_targets(CleanCell, vec![*selected]);
+                            |_trigger: Trigger<Pointer<Click>>, mut commands: Commands, selected: Query<Entity, With<SelectedCell>>| {
+                                commands.trigger_targets(CleanCell, selected.iter().collect::<Vec<_>>());
                             },
                         )
                         .with_children(|builder| {
@@ -338,13 +295,210 @@ pub(crate) fn control_board(
         });
 }
 
+/// 数字按钮的容器
+#[derive(Component)]
+struct NumberPad;
+
+/// 按这一局的棋盘大小重新生成数字按钮, 16×16 每行放 4 个小一点的按钮
+fn spawn_number_pad(
+    trigger: Trigger<StartPuzzle>,
+    pad: Single<Entity, With<NumberPad>>,
+    font_assets: Res<FontAssets>,
+    selected_tab: Res<SelectedTab>,
+    mut highlighted: ResMut<HighlightedDigit>,
+    mut commands: Commands,
+) {
+    // 上一局的按钮没了, 收不到鼠标离开的事件
+    highlighted.hovered = None;
+    let size = trigger.event().board.size();
+    let (button_size, digit_size, candidate_size) = if size == GridSize::Sixteen {
+        (54.0, 26.0, 10.0)
+    } else {
+        (70.0, 32.0, 16.0)
+    };
+    let visibility = |tab: ControlTab| {
+        if selected_tab.0 == tab {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        }
+    };
+
+    commands
+        .entity(*pad)
+        .despawn_descendants()
+        .with_children(|builder| {
+            for digit in size.digits() {
+                builder
+                    .spawn((
+                        Node {
+                            width: Val::Px(button_size),
+                            height: Val::Px(button_size),
+                            border: UiRect::all(Val::Px(1.0)),
+                            margin: UiRect {
+                                top: Val::Px(14.0),
+                                ..default()
+                            },
+                            align_items: AlignItems::Center,
+                            justify_items: JustifyItems::Center,
+                            align_content: AlignContent::Center,
+                            justify_content: JustifyContent::Center,
+                            ..default()
+                        },
+                        BorderRadius::all(Val::Px(3.0)),
+                        BackgroundColor(*EXTRA_LIGHT_GRAY),
+                        BorderColor(*GRAY),
+                        ControlNumber(digit.get()),
+                    ))
+                    .observe(mouse_click_control_digit)
+                    .observe(
+                        move |_trigger: Trigger<Pointer<Over>>,
+                              mut highlighted: ResMut<HighlightedDigit>| {
+                            highlighted.hovered = Some(digit);
+                        },
+                    )
+                    .observe(
+                        |_trigger: Trigger<Pointer<Out>>,
+                         mut highlighted: ResMut<HighlightedDigit>| {
+                            highlighted.hovered = None;
+                        },
+                    )
+                    .with_children(|builder| {
+                        // 还要填几个
+                        builder.spawn((
+                            Text::default(),
+                            TextFont {
+                                font: font_assets.franklin_500.clone(),
+                                font_size: 11.0,
+                                ..default()
+                            },
+                            TextColor(*GRAY),
+                            Node {
+                                position_type: PositionType::Absolute,
+                                top: Val::Px(2.0),
+                                right: Val::Px(4.0),
+                                ..default()
+                            },
+                            ControlRemaining,
+                        ));
+
+                        // 数字格子
+                        builder.spawn((
+                            Text::new(digit.symbol().to_string()),
+                            TextFont {
+                                font: font_assets.franklin_700.clone(),
+                                font_size: digit_size,
+                                ..default()
+                            },
+                            TextColor(*DARK_BLACK),
+                            visibility(ControlTab::Normal),
+                            ControlDigit,
+                        ));
+
+                        // 候选格子容器, 候选数按宫的形状排列
+                        builder
+                            .spawn((
+                                visibility(ControlTab::Candidate),
+                                ControlCandidate,
+                                Node {
+                                    height: Val::Percent(100.0),
+                                    display: Display::Grid,
+                                    aspect_ratio: Some(1.0),
+                                    position_type: PositionType::Absolute,
+                                    grid_template_columns: RepeatedGridTrack::flex(
+                                        size.box_cols() as u16,
+                                        1.0,
+                                    ),
+                                    grid_template_rows: RepeatedGridTrack::flex(
+                                        size.box_rows() as u16,
+                                        1.0,
+                                    ),
+                                    ..default()
+                                },
+                            ))
+                            .with_children(|builder| {
+                                // 只显示这个按钮自己的候选数字
+                                for other in size.digits() {
+                                    let visibility = if other == digit {
+                                        Visibility::Inherited
+                                    } else {
+                                        Visibility::Hidden
+                                    };
+                                    builder.spawn((
+                                        visibility,
+                                        candidate_text(&font_assets, other, candidate_size),
+                                    ));
+                                }
+                            });
+
+                        // 中心标记, 小字放在按钮中间
+                        builder.spawn((
+                            Text::new(digit.symbol().to_string()),
+                            TextFont {
+                                font: font_assets.franklin_700.clone(),
+                                font_size: candidate_size,
+                                ..default()
+                            },
+                            TextColor(*DARK_BLACK),
+                            visibility(ControlTab::Center),
+                            ControlCenter,
+                        ));
+
+                        // 调色板里的颜色, 16×16 后面的按钮没有颜色
+                        if let Some(&color) = PALETTE.get(digit.get() as usize - 1) {
+                            builder.spawn((
+                                Node {
+                                    width: Val::Percent(60.0),
+                                    height: Val::Percent(60.0),
+                                    ..default()
+                                },
+                                BorderRadius::all(Val::Px(3.0)),
+                                BackgroundColor(color),
+                                visibility(ControlTab::Color),
+                                ControlSwatch,
+                            ));
+                        }
+                    });
+            }
+        });
+}
+
+fn candidate_text(font_assets: &FontAssets, digit: Digit, font_size: f32) -> impl Bundle {
+    (
+        Text::new(digit.symbol().to_string()),
+        TextFont {
+            font: font_assets.franklin_700.clone(),
+            font_size,
+            ..default()
+        },
+        TextColor(*DARK_BLACK),
+        TextLayout::new_with_justify(JustifyText::Center),
+        Node {
+            align_items: AlignItems::Center,
+            justify_items: JustifyItems::Center,
+            align_content: AlignContent::Center,
+            justify_content: JustifyContent::Center,
+            margin: UiRect {
+                top: Val::Px(font_size / 4.0),
+                ..default()
+            },
+            ..default()
+        },
+    )
+}
+
 #[derive(Component)]
 struct AutoCandidateNotCheck;
 
 #[derive(Component)]
 struct AutoCandidateCheck;
 
-fn on_click_auto_candidate(_trigger: Trigger<Pointer<Click>>, mut auto: ResMut<AutoCandidateMode>) {
+fn on_click_auto_candidate(
+    _trigger: Trigger<Pointer<Click>>,
+    mut auto: ResMut<AutoCandidateMode>,
+    mut history: ResMut<MoveHistory>,
+) {
+    history.record();
     auto.0 = !auto.0;
 }
 
@@ -375,43 +529,66 @@ fn update_auto_candidate_icon(
 
 fn show_number(
     selected_tab: Res<SelectedTab>,
-    mut normal_cell: Query<&mut Visibility, (With<ControlDigit>, Without<ControlCandidate>)>,
-    mut candidate: Query<&mut Visibility, (With<ControlCandidate>, Without<ControlDigit>)>,
+    mut q_number: Query<(
+        &mut Visibility,
+        Has<ControlDigit>,
+        Has<ControlCandidate>,
+        Has<ControlCenter>,
+        Has<ControlSwatch>,
+    )>,
 ) {
-    match selected_tab.0 {
-        ControlTab::Normal => {
-            for mut visibility in normal_cell.iter_mut() {
-                *visibility = Visibility::Visible;
-            }
-            for mut visibility in candidate.iter_mut() {
-                *visibility = Visibility::Hidden;
-            }
-        }
-        ControlTab::Candidate => {
-            for mut visibility in normal_cell.iter_mut() {
-                *visibility = Visibility::Hidden;
-            }
-            for mut visibility in candidate.iter_mut() {
-                *visibility = Visibility::Visible;
-            }
-        }
+    for (mut visibility, digit, candidate, center, swatch) in q_number.iter_mut() {
+        let tab = match (digit, candidate, center, swatch) {
+            (true, ..) => ControlTab::Normal,
+            (_, true, ..) => ControlTab::Candidate,
+            (_, _, true, _) => ControlTab::Center,
+            (.., true) => ControlTab::Color,
+            _ => continue,
+        };
+        *visibility = if tab == selected_tab.0 {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
     }
 }
 
+/// 空格键依次切换, 按住修饰键时临时切换, 松开后回到按下之前的
 fn update_control_tab(
-    _ev: Trigger<ToggleTab>,
-    mut l: Local<ControlTab>,
+    trigger: Trigger<ToggleTab>,
+    mut held: Local<Option<ControlTab>>,
     mut selected_tab: ResMut<SelectedTab>,
 ) {
-    match selected_tab.0 {
-        ControlTab::Normal => {
-            selected_tab.0 = ControlTab::Candidate;
-            *l = ControlTab::Normal;
+    match trigger.event() {
+        ToggleTab::Next => {
+            *held = None;
+            selected_tab.0 = selected_tab.0.next();
         }
-        ControlTab::Candidate => {
-            selected_tab.0 = ControlTab::Normal;
-            *l = ControlTab::Candidate;
+        ToggleTab::Hold(tab) => {
+            held.get_or_insert(selected_tab.0);
+            selected_tab.0 = *tab;
         }
+        ToggleTab::Release => {
+            if let Some(tab) = held.take() {
+                selected_tab.0 = tab;
+            }
+        }
+    }
+}
+
+/// 没选中的按钮上下有边框, 两头的按钮外侧有边框, 两个没选中的按钮之间有分隔线
+fn tab_border(tab: ControlTab, selected: ControlTab) -> UiRect {
+    if tab == selected {
+        return UiRect::all(Val::Px(0.0));
+    }
+    let index = tab as usize;
+    let last = ControlTab::ALL.len() - 1;
+    let px = |border: bool| Val::Px(if border { 1.0 } else { 0.0 });
+    UiRect {
+        left: px(index == 0),
+        right: px(index == last || ControlTab::ALL[index + 1] != selected),
+        top: Val::Px(1.0),
+        bottom: Val::Px(1.0),
     }
 }
 
@@ -445,48 +622,93 @@ fn switch_control_tab_ui(
             }
         }
 
-        // normal tab selected
-        if selected_tab.0 == ControlTab::Normal {
-            if change_tab.0 == ControlTab::Normal {
-                node.border = UiRect::all(Val::Px(0.0));
-            } else {
-                node.border = UiRect {
-                    left: Val::Px(0.0),
-                    right: Val::Px(1.0),
-                    top: Val::Px(1.0),
-                    bottom: Val::Px(1.0),
-                }
-            }
-        } else if change_tab.0 == ControlTab::Candidate {
-            node.border = UiRect::all(Val::Px(0.0));
-        } else {
-            node.border = UiRect {
-                left: Val::Px(1.0),
-                right: Val::Px(0.0),
-                top: Val::Px(1.0),
-                bottom: Val::Px(1.0),
-            }
-        }
+        node.border = tab_border(change_tab.0, selected_tab.0);
     }
 }
 
 #[derive(Component)]
 struct ControlNumber(u8);
 
+/// 数字按钮右上角的剩余个数
+#[derive(Component)]
+struct ControlRemaining;
+
+/// 按格子里的数字算出每个数字还要填几个, 全部填对的数字按钮变灰
+fn update_remaining(
+    q_changed: Query<(), Or<(Changed<DigitValueCell>, Added<ControlRemaining>)>>,
+    q_cell: Query<(&CellPosition, &DigitValueCell)>,
+    sudoku_manager: Res<SudokuManager>,
+    q_number: Query<(Entity, &ControlNumber)>,
+    children: Query<&Children>,
+    mut q_text: Query<(&mut TextColor, &mut Text, Has<ControlRemaining>)>,
+) {
+    if q_changed.is_empty() {
+        return;
+    }
+
+    // 每个数字出现的次数一样多, 武士数独重叠的格子也只算一次
+    let digit_count = sudoku_manager.board.size().digit_count() as usize;
+    let total = q_cell.iter().len() / digit_count;
+    let mut placed = vec![0; digit_count + 1];
+    let mut correct = vec![0; digit_count + 1];
+    for (position, digit) in q_cell.iter() {
+        if let Some(digit) = digit.0 {
+            placed[digit.get() as usize] += 1;
+            if sudoku_manager.solution[position.0 as usize] == digit.get() {
+                correct[digit.get() as usize] += 1;
+            }
+        }
+    }
+
+    for (entity, number) in q_number.iter() {
+        let digit = number.0 as usize;
+        let completed = correct[digit] == total;
+        let remaining = total.saturating_sub(placed[digit]);
+        for child in children.iter_descendants(entity) {
+            let Ok((mut text_color, mut text, is_remaining)) = q_text.get_mut(child) else {
+                continue;
+            };
+            if is_remaining {
+                text.0 = if remaining > 0 {
+                    remaining.to_string()
+                } else {
+                    String::new()
+                };
+            } else {
+                let color = if completed {
+                    *LIGHTER_GRAY
+                } else {
+                    *DARK_BLACK
+                };
+                text_color.0 = color;
+            }
+        }
+    }
+}
+
 fn mouse_click_control_digit(
     trigger: Trigger<Pointer<Click>>,
     q_cell: Query<&ControlNumber>,
     mut commands: Commands,
     selected_tab: Res<SelectedTab>,
-    q_selected: Single<Entity, With<SelectedCell>>,
+    q_selected: Query<Entity, With<SelectedCell>>,
 ) {
     if let Ok(cell_value) = q_cell.get(trigger.entity()) {
         match selected_tab.0 {
             ControlTab::Normal => {
-                commands.trigger_targets(NewDigit::new(cell_value.0), vec![*q_selected]);
+                let selected: Vec<Entity> = q_selected.iter().collect();
+                commands.trigger_targets(NewDigit::new(cell_value.0), selected);
             }
             ControlTab::Candidate => {
-                commands.trigger_targets(NewCandidate::new(cell_value.0), vec![*q_selected]);
+                commands.trigger(NewCandidate::new(cell_value.0));
+            }
+            ControlTab::Center => {
+                commands.trigger(NewCenterMark::new(cell_value.0));
+            }
+            ControlTab::Color => {
+                if cell_value.0 <= PALETTE_SIZE {
+                    commands.trigger(NewColor(cell_value.0 - 1));
+                }
             }
         }
     }
@@ -1,6 +1,10 @@
-use crate::color::{DARK_BLACK, WHITE_COLOR};
-use crate::game::{GameTimer, ResetPuzzle, Settings, SudokuManager};
+use crate::color::{ACCENT_BLUE, DARK_BLACK, WHITE_COLOR};
+use crate::game::{
+    hint::{ApplyHint, CurrentHint},
+    Difficulty, GameTimer, GeneratorOptions, InitPuzzle, PuzzleMode, Settings, SudokuManager,
+};
 use crate::loading::{FontAssets, TextureAssets};
+use crate::stats::Statistics;
 use crate::GameState;
 use bevy::prelude::*;
 use bevy::window::WindowFocused;
@@ -14,7 +18,8 @@ pub(super) fn plugin(app: &mut App) {
     .add_observer(on_pause_game)
     .add_observer(on_show_settings)
     .add_observer(on_show_congrats)
-    .add_observer(on_hint);
+    .add_observer(on_hint)
+    .add_observer(on_show_technique);
 }
 
 pub(crate) fn dialog_container(_font_assets: &Res<FontAssets>, builder: &mut ChildBuilder) {
@@ -42,6 +47,7 @@ pub(crate) fn dialog_container(_font_assets: &Res<FontAssets>, builder: &mut Chi
             commands.trigger(PauseGame(false));
             commands.trigger(ShowHint(false));
             commands.trigger(ShowSettings(false));
+            commands.trigger(ShowTechnique(false));
         });
 }
 
@@ -462,6 +468,191 @@ fn on_hint(
     }
 }
 
+#[derive(Component)]
+pub struct TechniqueContainer;
+
+/// 显示 [`CurrentHint`] 中的解题技巧
+#[derive(Event)]
+pub struct ShowTechnique(pub bool);
+
+#[allow(clippy::too_many_arguments)]
+fn on_show_technique(
+    trigger: Trigger<ShowTechnique>,
+    mut time: ResMut<Time<Virtual>>,
+    mut commands: Commands,
+    q_dialog: Single<(Entity, &mut Visibility), With<DialogContainer>>,
+    font_assets: Res<FontAssets>,
+    texture_assets: Res<TextureAssets>,
+    q_technique: Query<Entity, With<TechniqueContainer>>,
+    current_hint: Res<CurrentHint>,
+    mut opened: Local<Opened>,
+) {
+    let (entity, mut visibility) = q_dialog.into_inner();
+    if trigger.event().0 && !opened.0 {
+        let Some(hint) = &current_hint.0 else {
+            return;
+        };
+        opened.0 = true;
+        time.pause();
+        *visibility = Visibility::Visible;
+        commands.entity(entity).with_children(|builder| {
+            spawn_technique(
+                &font_assets,
+                &texture_assets,
+                builder,
+                hint.technique,
+                &hint.explanation,
+                hint.placement.is_some() || !hint.eliminations.is_empty(),
+            );
+        });
+    } else {
+        opened.0 = false;
+        time.unpause();
+        for technique in q_technique.iter() {
+            commands
+                .entity(technique)
+                .insert(FadeOut(Timer::from_seconds(0.2, TimerMode::Once)));
+        }
+    }
+}
+
+/// 提示面板放在右侧, 不挡住棋盘上标出的提示
+fn spawn_technique(
+    font_assets: &Res<FontAssets>,
+    texture_assets: &Res<TextureAssets>,
+    builder: &mut ChildBuilder,
+    technique: &str,
+    explanation: &str,
+    can_apply: bool,
+) {
+    let (mut node, border_radius, box_shadow, background) = dialog_child_body();
+    node.position_type = PositionType::Absolute;
+    node.top = Val::Px(20.0);
+    node.right = Val::Px(0.0);
+    node.width = Val::Px(360.0);
+    node.min_height = Val::Auto;
+    node.padding = UiRect::all(Val::Px(32.0));
+
+    builder
+        .spawn((
+            Name::new("technique-container"),
+            TechniqueContainer,
+            node,
+            border_radius,
+            box_shadow,
+            background,
+        ))
+        .with_children(|builder| {
+            builder
+                .spawn((
+                    ImageNode {
+                        image: texture_assets.close.clone(),
+                        ..default()
+                    },
+                    Node {
+                        position_type: PositionType::Absolute,
+                        margin: UiRect::all(Val::Px(20.0)),
+                        top: Val::Px(0.0),
+                        right: Val::Px(0.0),
+                        height: Val::Px(18.0),
+                        width: Val::Px(18.0),
+                        ..default()
+                    },
+                ))
+                .observe(
+                    |_trigger: Trigger<Pointer<Click>>, mut commands: Commands| {
+                        commands.trigger(ShowTechnique(false));
+                    },
+                );
+
+            builder.spawn((
+                Text::new(technique),
+                TextFont {
+                    font_size: 28.0,
+                    font: font_assets.karnak.clone(),
+                    ..default()
+                },
+                TextColor(*DARK_BLACK),
+            ));
+
+            builder.spawn((
+                Node {
+                    margin: UiRect {
+                        top: Val::Px(18.0),
+                        ..default()
+                    },
+                    ..default()
+                },
+                Text::new(explanation),
+                TextFont {
+                    font_size: 16.0,
+                    font: font_assets.franklin_500.clone(),
+                    ..default()
+                },
+                TextColor(*DARK_BLACK),
+                TextLayout::new_with_justify(JustifyText::Center),
+            ));
+
+            builder
+                .spawn(Node {
+                    display: Display::Flex,
+                    column_gap: Val::Px(12.0),
+                    margin: UiRect {
+                        top: Val::Px(30.0),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|builder| {
+                    if can_apply {
+                        technique_button(font_assets, builder, "Apply", |_, mut commands| {
+                            commands.trigger(ApplyHint);
+                            commands.trigger(ShowTechnique(false));
+                        });
+                    }
+                    technique_button(font_assets, builder, "OK", |_, mut commands| {
+                        commands.trigger(ShowTechnique(false));
+                    });
+                });
+        });
+}
+
+fn technique_button(
+    font_assets: &Res<FontAssets>,
+    builder: &mut ChildBuilder,
+    text: &str,
+    on_click: fn(Trigger<Pointer<Click>>, Commands),
+) {
+    builder
+        .spawn((
+            Name::new("technique-button"),
+            Button,
+            Node {
+                display: Display::Flex,
+                width: Val::Auto,
+                padding: UiRect::horizontal(Val::Px(38.0)),
+                min_height: Val::Px(48.0),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            BorderRadius::all(Val::Px(40.0)),
+            BackgroundColor(*DARK_BLACK),
+        ))
+        .with_children(|builder| {
+            builder.spawn((
+                Text::new(text),
+                TextFont {
+                    font_size: 14.0,
+                    font: font_assets.franklin_500.clone(),
+                    ..default()
+                },
+                TextColor(WHITE_COLOR),
+            ));
+        })
+        .observe(on_click);
+}
+
 #[derive(Event)]
 pub struct ShowSettings(pub bool);
 
@@ -595,6 +786,37 @@ fn spawn_settings(
                             settings.highlight_conflicts = !settings.highlight_conflicts;
                         },
                     );
+                    setting_item(
+                        font_assets,
+                        texture_assets,
+                        builder,
+                        "Highlight row, column and box",
+                        settings.highlight_peers,
+                        |_trigger, mut settings| {
+                            settings.highlight_peers = !settings.highlight_peers;
+                        },
+                    );
+                    setting_item(
+                        font_assets,
+                        texture_assets,
+                        builder,
+                        "Highlight same digits",
+                        settings.highlight_same_digit,
+                        |_trigger, mut settings| {
+                            settings.highlight_same_digit = !settings.highlight_same_digit;
+                        },
+                    );
+                    setting_item(
+                        font_assets,
+                        texture_assets,
+                        builder,
+                        "Highlight same candidates",
+                        settings.highlight_same_candidates,
+                        |_trigger, mut settings| {
+                            settings.highlight_same_candidates =
+                                !settings.highlight_same_candidates;
+                        },
+                    );
                     setting_item(
                         font_assets,
                         texture_assets,
@@ -706,6 +928,7 @@ pub struct ShowCongrats(pub bool);
 #[derive(Component)]
 pub struct CongratsContainer;
 
+#[allow(clippy::too_many_arguments)]
 fn on_show_congrats(
     trigger: Trigger<ShowCongrats>,
     mut commands: Commands,
@@ -714,12 +937,21 @@ fn on_show_congrats(
     texture_assets: Res<TextureAssets>,
     q_congrats: Query<Entity, With<CongratsContainer>>,
     game_timer: Res<GameTimer>,
+    difficulty: Res<Difficulty>,
+    statistics: Res<Statistics>,
 ) {
     let (entity, mut visibility) = q_dialog.into_inner();
     if trigger.event().0 {
         *visibility = Visibility::Visible;
         commands.entity(entity).with_children(|builder| {
-            spawn_congrats(&font_assets, &texture_assets, builder, game_timer.clone());
+            spawn_congrats(
+                &font_assets,
+                &texture_assets,
+                builder,
+                game_timer.clone(),
+                *difficulty,
+                statistics.last_personal_best(),
+            );
         });
     } else {
         for congrats in q_congrats.iter() {
@@ -735,7 +967,14 @@ fn spawn_congrats(
     texture_assets: &Res<TextureAssets>,
     builder: &mut ChildBuilder,
     timer: GameTimer,
+    difficulty: Difficulty,
+    personal_best: bool,
 ) {
+    let article = if difficulty == Difficulty::Easy {
+        "an"
+    } else {
+        "a"
+    };
     builder
         .spawn((
             Name::new("congrats-container"),
@@ -791,7 +1030,10 @@ fn spawn_congrats(
                     height: Val::Px(20.0),
                     ..default()
                 },
-                Text::new(format!("You finished a Easy puzzle in {}", timer)),
+                Text::new(format!(
+                    "You finished {} {} puzzle in {}",
+                    article, difficulty, timer
+                )),
                 TextColor(*DARK_BLACK),
                 TextLayout::default(),
                 TextFont {
@@ -801,6 +1043,26 @@ fn spawn_congrats(
                 },
             ));
 
+            if personal_best {
+                builder.spawn((
+                    Name::new("personal-best"),
+                    Node {
+                        margin: UiRect {
+                            top: Val::Px(12.0),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    Text::new("New personal best!"),
+                    TextColor(*ACCENT_BLUE),
+                    TextFont {
+                        font: font_assets.franklin_700.clone(),
+                        font_size: 16.0,
+                        ..default()
+                    },
+                ));
+            }
+
             builder
                 .spawn((
                     Name::new("replay-button"),
@@ -833,8 +1095,14 @@ fn spawn_congrats(
                     ));
                 })
                 .observe(
-                    |_trigger: Trigger<Pointer<Click>>, mut commands: Commands| {
-                        commands.trigger(ResetPuzzle);
+                    |_trigger: Trigger<Pointer<Click>>,
+                     mut commands: Commands,
+                     sudoku_manager: Res<SudokuManager>,
+                     mut options: ResMut<GeneratorOptions>| {
+                        // 每日谜题已经完成, 再来一局换成随机谜题, 规则和刚完成的这局一样
+                        *options = options.matching(sudoku_manager.board.rules());
+                        commands.insert_resource(PuzzleMode::Random);
+                        commands.trigger(InitPuzzle);
                         commands.trigger(ShowCongrats(false));
                     },
                 );
@@ -0,0 +1,397 @@
+use crate::game::{
+    grid::{cells_line, Digit, DigitSet, GridSize},
+    model::Board,
+};
+use std::fmt::Display;
+use sudoku::Sudoku;
+
+/// 导入谜题失败的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportError {
+    /// 找不到 81 个格子
+    InvalidFormat,
+    NoSolution,
+    MultipleSolutions,
+}
+
+impl Display for ImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportError::InvalidFormat => {
+                write!(
+                    f,
+                    "Expected 81 cells, using 1-9 for digits and . or 0 for blanks"
+                )
+            }
+            ImportError::NoSolution => write!(f, "This puzzle has no solution"),
+            ImportError::MultipleSolutions => write!(f, "This puzzle has more than one solution"),
+        }
+    }
+}
+
+/// 导入的一局: 谜题, 答案, 以及恢复了数字和候选数的棋盘. 只支持 9×9
+#[derive(Clone)]
+pub struct Imported {
+    pub puzzle: Sudoku,
+    pub solution: Sudoku,
+    pub board: Board,
+}
+
+/// 解析谜题文本
+///
+/// 支持 81 个字符的一行 (`.` 或 `0` 表示空格), 带 `|`, `-`, `+` 等分隔符的多行格子,
+/// 以及 HoDoKu / SudokuWiki 的候选数格子. 第一行 81 个字符是题目,
+/// 第二行是当前填的数字. 只有候选数格子时分不清题目和玩家填的数字,
+/// 单个数字的格子都当作玩家填的数字, 用它们求出唯一的答案.
+/// 候选数格子后面可以跟着 `Center marks` 一行和中心标记的格子.
+/// 导出的三部分连在一起可以完整恢复棋盘
+pub fn parse_puzzle(text: &str) -> Result<Imported, ImportError> {
+    let (text, center_marks) = match text.split_once(CENTER_MARKS) {
+        Some((text, center)) => (text, parse_center_marks(center)),
+        None => (text, None),
+    };
+    let lines: Vec<Sudoku> = text
+        .lines()
+        .filter_map(|line| Sudoku::from_str_line(line.trim()).ok())
+        .collect();
+    let marks = parse_pencil_marks(text);
+
+    // 只有候选数格子时没有题目, 单个数字的格子是玩家填的数字
+    let singles = marks.map(|marks| {
+        let mut bytes = [0; 81];
+        for (byte, candidates) in bytes.iter_mut().zip(marks.iter()) {
+            if candidates.len() == 1 {
+                *byte = digits_of(*candidates)[0];
+            }
+        }
+        bytes
+    });
+    let (puzzle, givens) = match (lines.first(), singles) {
+        (Some(puzzle), _) => (Some(*puzzle), Some(*puzzle)),
+        (None, Some(singles)) => (
+            Sudoku::from_bytes(singles).ok(),
+            Sudoku::from_bytes([0; 81]).ok(),
+        ),
+        (None, None) => {
+            let puzzle = grid_line(text).and_then(|line| Sudoku::from_str_line(&line).ok());
+            (puzzle, puzzle)
+        }
+    };
+    let (puzzle, givens) = puzzle.zip(givens).ok_or(ImportError::InvalidFormat)?;
+
+    let solution = match puzzle.solutions_count_up_to(2) {
+        0 => None,
+        1 => puzzle.solution(),
+        _ => return Err(ImportError::MultipleSolutions),
+    }
+    .ok_or(ImportError::NoSolution)?;
+
+    // 有当前数字那一行时, 候选数格子中只有一个数字的空格是候选数而不是填好的数字
+    let values: Vec<Option<u8>> = match (lines.get(1), singles) {
+        (Some(values), _) => values.iter().collect(),
+        (None, Some(singles)) => singles
+            .iter()
+            .map(|&num| (num != 0).then_some(num))
+            .collect(),
+        (None, None) => vec![None; 81],
+    };
+
+    let mut board = Board::new(givens);
+    for (cell, num) in values.into_iter().enumerate() {
+        if let Some(num) = num {
+            board.place(cell as u16, Digit::new(num));
+        }
+    }
+    // 填数字会剔除候选数, 所以候选数最后恢复
+    if let Some(marks) = marks {
+        for (cell, candidates) in marks.into_iter().enumerate() {
+            let cell = cell as u16;
+            if board.digit(cell).is_none() {
+                board.set_candidates(cell, false, candidates);
+                board.set_candidates(cell, true, candidates);
+            }
+        }
+    }
+    if let Some(center_marks) = center_marks {
+        for (cell, marks) in center_marks.into_iter().enumerate() {
+            let cell = cell as u16;
+            if board.digit(cell).is_none() {
+                board.set_center_marks(cell, marks);
+            }
+        }
+    }
+
+    Ok(Imported {
+        puzzle,
+        solution,
+        board,
+    })
+}
+
+/// 从多行格子中取出 81 个格子, 忽略分隔符和空白
+fn grid_line(text: &str) -> Option<String> {
+    let line: String = text
+        .chars()
+        .filter_map(|ch| match ch {
+            '1'..='9' => Some(ch),
+            '.' | '0' | '_' | '*' => Some('.'),
+            _ => None,
+        })
+        .collect();
+    (line.len() == 81).then_some(line)
+}
+
+/// 解析候选数格子: 每行 9 组数字, 用空白或 `|` 隔开, 只有一个数字的是已经填好的格子
+fn parse_pencil_marks(text: &str) -> Option<[DigitSet; 81]> {
+    let tokens = marks_tokens(text, false);
+    if tokens.len() != 81 || tokens.iter().all(|token| token.len() == 1) {
+        return None;
+    }
+    Some(marks_from_tokens(tokens))
+}
+
+/// 解析中心标记的格子, 和候选数格子一样, 没有标记的格子是 `.`
+fn parse_center_marks(text: &str) -> Option<[DigitSet; 81]> {
+    let tokens = marks_tokens(text, true);
+    (tokens.len() == 81).then(|| marks_from_tokens(tokens))
+}
+
+/// 取出每行正好 9 组数字的行里的数字, `allow_blank` 时 `.` 也算一组
+fn marks_tokens(text: &str, allow_blank: bool) -> Vec<&str> {
+    text.lines()
+        .map(|line| {
+            line.split(|ch: char| ch.is_whitespace() || ch == '|')
+                .filter(|token| !token.is_empty())
+                .collect::<Vec<_>>()
+        })
+        .filter(|tokens| {
+            tokens.len() == 9
+                && tokens.iter().all(|token| {
+                    allow_blank && *token == "."
+                        || token.chars().all(|ch| ('1'..='9').contains(&ch))
+                })
+        })
+        .flatten()
+        .collect()
+}
+
+fn marks_from_tokens(tokens: Vec<&str>) -> [DigitSet; 81] {
+    let mut marks = [DigitSet::NONE; 81];
+    for (candidates, token) in marks.iter_mut().zip(tokens) {
+        for ch in token.chars().filter(|&ch| ch != '.') {
+            let digit = Digit::new(ch as u8 - b'0');
+            if !candidates.contains(digit.as_set()) {
+                *candidates ^= digit;
+            }
+        }
+    }
+    marks
+}
+
+fn digits_of(candidates: DigitSet) -> Vec<u8> {
+    candidates.digits().map(Digit::get).collect()
+}
+
+/// 题目, 每格一个字符
+pub fn givens_line(board: &Board) -> String {
+    cells_line(&board.givens())
+}
+
+/// 题目加上玩家填的数字, 每格一个字符
+pub fn values_line(board: &Board) -> String {
+    cells_line(&board.values())
+}
+
+/// 中心标记格子前面的一行
+const CENTER_MARKS: &str = "Center marks";
+
+/// HoDoKu / SudokuWiki 格式的候选数格子, 其他大小的棋盘按宫的形状画分隔线
+///
+/// 手动模式下还没有标记候选数的格子使用自动候选数.
+/// 有中心标记时在后面另画一个中心标记的格子, 没有标记的格子是 `.`
+pub fn pencil_marks(board: &Board, auto: bool) -> String {
+    let size = board.size();
+    // 武士数独空着的位置留空
+    let tokens: Vec<String> = (0..size.cell_count() as u16)
+        .map(|cell| match board.digit(cell) {
+            _ if !size.contains(cell) => String::new(),
+            Some(digit) => digit.symbol().to_string(),
+            None => {
+                let candidates = match board.manual_candidates(cell) {
+                    manual if !auto && !manual.is_empty() => manual,
+                    _ => board.auto_candidates(cell),
+                };
+                candidates.digits().map(Digit::symbol).collect()
+            }
+        })
+        .collect();
+    let mut text = marks_grid(size, &tokens);
+
+    let center_marks = |cell: u16| match board.digit(cell) {
+        None if size.contains(cell) => board.center_marks(cell),
+        _ => DigitSet::NONE,
+    };
+    if (0..size.cell_count() as u16).any(|cell| !center_marks(cell).is_empty()) {
+        let tokens: Vec<String> = (0..size.cell_count() as u16)
+            .map(|cell| match center_marks(cell) {
+                _ if !size.contains(cell) => String::new(),
+                marks if marks.is_empty() => ".".to_string(),
+                marks => marks.digits().map(Digit::symbol).collect(),
+            })
+            .collect();
+        text.push_str(&format!("\n{CENTER_MARKS}\n{}", marks_grid(size, &tokens)));
+    }
+    text
+}
+
+/// 每格一组字符, 按列对齐画成格子
+fn marks_grid(size: GridSize, tokens: &[String]) -> String {
+    let side = size.side() as usize;
+    let (box_rows, box_cols) = (size.box_rows() as usize, size.box_cols() as usize);
+    let widths: Vec<usize> = (0..side)
+        .map(|col| {
+            (0..side)
+                .map(|row| tokens[row * side + col].len())
+                .max()
+                .unwrap_or(1)
+        })
+        .collect();
+    let stacks = side / box_cols;
+    let stack_width = |stack: usize| -> usize {
+        widths[stack * box_cols..(stack + 1) * box_cols]
+            .iter()
+            .sum::<usize>()
+            + box_cols
+            + 1
+    };
+    let border = |left: char, middle: char, right: char| -> String {
+        let dashes: Vec<String> = (0..stacks)
+            .map(|stack| "-".repeat(stack_width(stack)))
+            .collect();
+        format!("{left}{}{right}", dashes.join(&middle.to_string()))
+    };
+
+    let mut grid = vec![border('.', '.', '.')];
+    for row in 0..side {
+        if row > 0 && row % box_rows == 0 {
+            grid.push(border(':', '+', ':'));
+        }
+        let mut line = String::from("|");
+        for stack in 0..stacks {
+            for col in stack * box_cols..(stack + 1) * box_cols {
+                line.push_str(&format!(
+                    " {:<width$}",
+                    tokens[row * side + col],
+                    width = widths[col]
+                ));
+            }
+            line.push_str(" |");
+        }
+        grid.push(line);
+    }
+    grid.push(border('\'', '\'', '\''));
+    grid.join("\n")
+}
+
+#[cfg(test)]
+const PUZZLE: &str =
+    "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79";
+
+#[test]
+fn test_parse_line() {
+    let imported = parse_puzzle(PUZZLE).unwrap();
+    assert_eq!(imported.puzzle.iter().next(), Some(Some(5)));
+    assert_eq!(imported.solution.iter().nth(2), Some(Some(4)));
+
+    let zeros = PUZZLE.replace('.', "0");
+    assert!(parse_puzzle(&format!("{zeros} # comment")).is_ok());
+}
+
+#[test]
+fn test_parse_grid() {
+    let grid = "\
+53. | .7. | ...
+6.. | 195 | ...
+.98 | ... | .6.
+----+-----+----
+8.. | .6. | ..3
+4.. | 8.3 | ..1
+7.. | .2. | ..6
+----+-----+----
+.6. | ... | 28.
+... | 419 | ..5
+... | .8. | .79
+";
+    let puzzle = parse_puzzle(grid).unwrap().puzzle;
+    let expected = parse_puzzle(PUZZLE).unwrap().puzzle;
+    assert_eq!(
+        puzzle.iter().collect::<Vec<_>>(),
+        expected.iter().collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn test_parse_errors() {
+    assert_eq!(parse_puzzle("123").err(), Some(ImportError::InvalidFormat));
+    assert_eq!(
+        parse_puzzle(&".".repeat(81)).err(),
+        Some(ImportError::MultipleSolutions)
+    );
+    // 右上角的格子只能填 9, 但是它下面已经有 9 了
+    let dead_end = format!("12345678.........9{}", ".".repeat(63));
+    assert_eq!(parse_puzzle(&dead_end).err(), Some(ImportError::NoSolution));
+}
+
+#[test]
+fn test_export_round_trip() {
+    let mut board = parse_puzzle(PUZZLE).unwrap().board;
+    board.place(2, Digit::new(4));
+    board.toggle_candidate(3, Digit::new(2), false);
+    board.toggle_candidate(3, Digit::new(6), false);
+    board.toggle_center_mark(5, Digit::new(2));
+    board.toggle_center_mark(5, Digit::new(6));
+
+    let text = format!(
+        "{}\n{}\n{}",
+        givens_line(&board),
+        values_line(&board),
+        pencil_marks(&board, false)
+    );
+    let imported = parse_puzzle(&text).unwrap().board;
+    assert_eq!(givens_line(&imported), PUZZLE);
+    assert_eq!(values_line(&imported), values_line(&board));
+    for cell in 0..81 {
+        if board.digit(cell).is_none() {
+            let expected = match board.manual_candidates(cell) {
+                manual if !manual.is_empty() => manual,
+                _ => board.auto_candidates(cell),
+            };
+            assert_eq!(imported.manual_candidates(cell), expected);
+            assert_eq!(imported.center_marks(cell), board.center_marks(cell));
+        }
+    }
+
+    // 只有候选数格子时, 单个数字的格子都当作玩家填的数字
+    let imported = parse_puzzle(&pencil_marks(&board, true)).unwrap().board;
+    assert!(!imported.is_given(0));
+    assert!(!imported.is_given(2));
+    for cell in 0..81 {
+        if let Some(digit) = board.digit(cell) {
+            assert_eq!(imported.digit(cell), Some(digit));
+        }
+    }
+}
+
+#[test]
+fn test_export_small_board() {
+    let mut givens = vec![0; 16];
+    givens[0] = 1;
+    let rules = crate::game::model::Rules::classic(crate::game::GridSize::Four);
+    let board = Board::with_rules(&givens, rules);
+    assert_eq!(givens_line(&board), format!("1{}", ".".repeat(15)));
+
+    // 4×4 的宫是 2×2, 两条边框加一条分隔线
+    let marks = pencil_marks(&board, true);
+    assert_eq!(marks.lines().count(), 7);
+    assert!(marks.lines().nth(1).unwrap().starts_with("| 1 "));
+}
@@ -0,0 +1,1164 @@
+use crate::game::{
+    grid::{GridSize, NO_REGION},
+    model::{
+        orthogonal_neighbours, sudoku_cells, Board, Cage, Edge, EdgeKind, Line, LineKind, Overlays,
+        Rules, CLASSIC_REGIONS,
+    },
+    position::CellPosition,
+    solver::{count_solutions, first_solution},
+};
+use bevy::prelude::*;
+use chrono::{Datelike, NaiveDate};
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::sync::atomic::{AtomicBool, Ordering};
+use sudoku::{
+    strategy::{Strategy, StrategySolver},
+    Sudoku,
+};
+
+/// 按难度从低到高排列的解题技巧
+pub(crate) const STRATEGIES: [Strategy; 14] = [
+    Strategy::NakedSingles,
+    Strategy::HiddenSingles,
+    Strategy::LockedCandidates,
+    Strategy::NakedPairs,
+    Strategy::HiddenPairs,
+    Strategy::NakedTriples,
+    Strategy::HiddenTriples,
+    Strategy::NakedQuads,
+    Strategy::HiddenQuads,
+    Strategy::XWing,
+    Strategy::Swordfish,
+    Strategy::Jellyfish,
+    Strategy::XyWing,
+    Strategy::XyzWing,
+];
+
+/// 谜题难度
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Difficulty {
+    #[default]
+    Easy,
+    Medium,
+    Hard,
+}
+
+/// 谜题来源
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PuzzleMode {
+    /// 每日谜题, 同一天同一难度在所有机器上都相同
+    #[default]
+    Daily,
+    /// 随机谜题
+    Random,
+}
+
+/// 线索的对称方式
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Symmetry {
+    #[default]
+    None,
+    /// 绕中心旋转 180°
+    Rotational,
+    /// 沿主对角线对称
+    Diagonal,
+    /// 左右镜像
+    Mirror,
+}
+
+impl Symmetry {
+    /// 和这个格子对称的所有格子, 包括它自己
+    fn orbit(self, size: GridSize, cell: usize) -> Vec<usize> {
+        let side = size.side() as usize;
+        let (row, col) = (cell / side, cell % side);
+        let other = match self {
+            Symmetry::None => cell,
+            Symmetry::Rotational => size.cell_count() - 1 - cell,
+            Symmetry::Diagonal => col * side + row,
+            Symmetry::Mirror => row * side + side - 1 - col,
+        };
+        if other == cell {
+            vec![cell]
+        } else {
+            vec![cell, other]
+        }
+    }
+}
+
+impl Display for Symmetry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Symmetry::None => write!(f, "None"),
+            Symmetry::Rotational => write!(f, "Rotational"),
+            Symmetry::Diagonal => write!(f, "Diagonal"),
+            Symmetry::Mirror => write!(f, "Mirror"),
+        }
+    }
+}
+
+/// 规则变体
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Variant {
+    #[default]
+    Classic,
+    /// 杀手数独: 格子分成若干笼子, 笼子里的数字不重复, 加起来等于笼子的和
+    Killer,
+    /// 不规则数独: 九个宫的形状不是 3×3
+    Jigsaw,
+    /// 温度计数独: 温度计上的数字从灯泡开始严格递增
+    Thermo,
+    /// 箭头数独: 箭身上的数字加起来等于圆圈里的数字
+    Arrow,
+    /// Kropki 数独: 白点两边的数字相差 1, 黑点两边的数字是两倍关系
+    Kropki,
+    /// XV 数独: X 两边的数字加起来是 10, V 两边加起来是 5
+    XV,
+}
+
+impl Variant {
+    /// 根据规则判断变体, 导入和继续的对局也能归类
+    pub fn of(rules: &Rules) -> Variant {
+        if !rules.cages.is_empty() {
+            Variant::Killer
+        } else if !rules.has_classic_regions() {
+            Variant::Jigsaw
+        } else if let Some(line) = rules.lines.first() {
+            match line.kind {
+                LineKind::Thermometer => Variant::Thermo,
+                LineKind::Arrow => Variant::Arrow,
+            }
+        } else if rules
+            .edges
+            .iter()
+            .map(|edge| edge.kind)
+            .chain(rules.negative.iter().copied())
+            .any(|kind| matches!(kind, EdgeKind::X | EdgeKind::V))
+        {
+            Variant::XV
+        } else if rules.has_edge_rules() {
+            Variant::Kropki
+        } else {
+            Variant::Classic
+        }
+    }
+
+    /// 这个变体画在格子之间的标记
+    pub fn edge_kinds(self) -> &'static [EdgeKind] {
+        match self {
+            Variant::Kropki => &[EdgeKind::White, EdgeKind::Black],
+            Variant::XV => &[EdgeKind::X, EdgeKind::V],
+            _ => &[],
+        }
+    }
+}
+
+impl Display for Variant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Variant::Classic => write!(f, "Classic"),
+            Variant::Killer => write!(f, "Killer"),
+            Variant::Jigsaw => write!(f, "Jigsaw"),
+            Variant::Thermo => write!(f, "Thermo"),
+            Variant::Arrow => write!(f, "Arrow"),
+            Variant::Kropki => write!(f, "Kropki"),
+            Variant::XV => write!(f, "XV"),
+        }
+    }
+}
+
+/// 随机谜题的生成选项, 每日谜题总是使用默认值
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct GeneratorOptions {
+    /// 棋盘大小, 不是 9×9 时只有经典规则
+    pub size: GridSize,
+    pub variant: Variant,
+    pub symmetry: Symmetry,
+    /// 挖到剩下这么多线索就停止, `None` 表示挖到最少:
+    /// 再去掉任何一组对称的线索解都不唯一. 只用于 9×9 的棋盘
+    pub clues: Option<usize>,
+    /// 叠加的额外约束, 只用于 9×9 的经典数独
+    pub overlays: Overlays,
+    /// 反约束: 没有标记的相邻格子也不满足标记, 只用于 Kropki 和 XV
+    pub negative: bool,
+}
+
+impl GeneratorOptions {
+    /// 生成和这一局同样规则的谜题, 对称方式和线索数保留原来的选项
+    pub fn matching(self, rules: &Rules) -> GeneratorOptions {
+        GeneratorOptions {
+            size: rules.size,
+            variant: Variant::of(rules),
+            overlays: rules.overlays,
+            negative: !rules.negative.is_empty(),
+            ..self
+        }
+    }
+}
+
+impl Difficulty {
+    /// 根据解题所需最难技巧在 [`STRATEGIES`] 中的位置划分难度
+    fn from_strategy_index(index: usize) -> Difficulty {
+        match index {
+            // naked / hidden singles
+            0..=1 => Difficulty::Easy,
+            // locked candidates, pairs and triples
+            2..=6 => Difficulty::Medium,
+            // quads, fish and wings
+            _ => Difficulty::Hard,
+        }
+    }
+}
+
+impl Display for Difficulty {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Difficulty::Easy => write!(f, "Easy"),
+            Difficulty::Medium => write!(f, "Medium"),
+            Difficulty::Hard => write!(f, "Hard"),
+        }
+    }
+}
+
+/// 评估谜题难度
+///
+/// 逐步放开更难的技巧，第一次能解出时用到的最难技巧决定难度。
+/// 只靠这些技巧解不出的谜题返回 `None`
+pub fn grade(sudoku: Sudoku) -> Option<Difficulty> {
+    let solver = StrategySolver::from_sudoku(sudoku);
+    (0..STRATEGIES.len())
+        .find(|&index| solver.clone().solve(&STRATEGIES[..=index]).is_ok())
+        .map(Difficulty::from_strategy_index)
+}
+
+/// 按选项生成一局, 返回 (答案, 带题目和变体规则的棋盘)
+///
+/// 生成在后台进行, `cancel` 被设置后尽快停下并返回 `None`
+pub fn generate_board(
+    difficulty: Difficulty,
+    options: GeneratorOptions,
+    cancel: &AtomicBool,
+) -> Option<(Vec<u8>, Board)> {
+    let rng = &mut rand::thread_rng();
+    if options.size != GridSize::Nine {
+        return generate_sized(difficulty, options, rng, cancel);
+    }
+    match options.variant {
+        Variant::Classic if !options.overlays.is_empty() => {
+            generate_overlays(difficulty, options, rng, cancel)
+        }
+        Variant::Classic => {
+            let (puzzle, solution) = generate_with_rng(difficulty, options, rng, cancel)?;
+            Some((sudoku_cells(solution), Board::new(puzzle)))
+        }
+        Variant::Killer => generate_killer(difficulty, options, rng, cancel),
+        Variant::Jigsaw => generate_jigsaw(difficulty, options, rng, cancel),
+        Variant::Thermo => generate_lines(LineKind::Thermometer, difficulty, options, rng, cancel),
+        Variant::Arrow => generate_lines(LineKind::Arrow, difficulty, options, rng, cancel),
+        Variant::Kropki | Variant::XV => generate_edges(difficulty, options, rng, cancel),
+    }
+}
+
+/// 生成指定日期和难度的每日谜题, 返回 (谜题, 答案)
+pub fn generate_daily(date: NaiveDate, difficulty: Difficulty) -> (Sudoku, Sudoku) {
+    let mut rng = ChaCha8Rng::seed_from_u64(daily_seed(date, difficulty));
+    generate_with_rng(
+        difficulty,
+        GeneratorOptions::default(),
+        &mut rng,
+        &AtomicBool::new(false),
+    )
+    .expect("daily puzzles are never cancelled")
+}
+
+/// 由日历日期和难度得到随机数种子
+///
+/// 不使用 `Hash`, 保证不同平台和编译器版本得到相同的种子
+fn daily_seed(date: NaiveDate, difficulty: Difficulty) -> u64 {
+    let day = date.num_days_from_ce() as u64;
+    day * 4 + difficulty as u64
+}
+
+fn generate_with_rng<R: Rng>(
+    difficulty: Difficulty,
+    options: GeneratorOptions,
+    rng: &mut R,
+    cancel: &AtomicBool,
+) -> Option<(Sudoku, Sudoku)> {
+    loop {
+        let solution = random_solution(GridSize::Nine, rng);
+        // 取消之后每个洞都当作解不唯一, 很快就挖完了
+        let puzzle = dig_holes(&solution, options, rng, |puzzle| {
+            !is_cancelled(cancel) && has_unique_solution(puzzle)
+        });
+        if is_cancelled(cancel) {
+            return None;
+        }
+        let Some(sudoku) = to_sudoku(&puzzle) else {
+            continue;
+        };
+        if grade(sudoku) != Some(difficulty) {
+            continue;
+        }
+        if let Some(solution) = to_sudoku(&solution) {
+            return Some((sudoku, solution));
+        }
+    }
+}
+
+fn is_cancelled(cancel: &AtomicBool) -> bool {
+    cancel.load(Ordering::Relaxed)
+}
+
+/// 生成变体谜题: `layout` 给出完整的解和规则, 然后按变体规则挖空
+///
+/// 变体没有对应的技巧评级, 只保证在变体规则下解唯一,
+/// 难度决定默认保留多少数字, `None` 表示不保留多余的数字
+fn generate_variant<R: Rng>(
+    options: GeneratorOptions,
+    clues: Option<usize>,
+    rng: &mut R,
+    cancel: &AtomicBool,
+    mut layout: impl FnMut(&mut R) -> Option<(Vec<u8>, Rules)>,
+) -> Option<(Vec<u8>, Board)> {
+    let options = GeneratorOptions {
+        clues: options.clues.or(clues),
+        ..options
+    };
+
+    loop {
+        if is_cancelled(cancel) {
+            return None;
+        }
+        let Some((solution, rules)) = layout(rng) else {
+            continue;
+        };
+        let unique =
+            |puzzle: &[u8]| !is_cancelled(cancel) && count_solutions(puzzle, &rules, 2) == 1;
+        let puzzle = dig_holes(&solution, options, rng, unique);
+        if is_cancelled(cancel) {
+            return None;
+        }
+        return Some((solution, Board::with_rules(&puzzle, rules)));
+    }
+}
+
+/// 生成 4×4、6×6、16×16 的经典数独或者武士数独
+///
+/// 解题技巧只认识 9×9, 所以这些大小不评级, 没有选线索数时难度决定保留多少数字.
+/// 16×16 和武士数独挖到最少太慢, 困难也保留一部分数字
+fn generate_sized<R: Rng>(
+    difficulty: Difficulty,
+    options: GeneratorOptions,
+    rng: &mut R,
+    cancel: &AtomicBool,
+) -> Option<(Vec<u8>, Board)> {
+    let size = options.size;
+    let cells = size.cells().count();
+    let clues = match (size, difficulty) {
+        (GridSize::Sixteen, Difficulty::Easy) => Some(150),
+        (GridSize::Sixteen, Difficulty::Medium) => Some(130),
+        (GridSize::Sixteen, Difficulty::Hard) => Some(115),
+        (GridSize::Samurai, Difficulty::Hard) => Some(110),
+        (_, Difficulty::Easy) => Some(cells * 5 / 8),
+        (_, Difficulty::Medium) => Some(cells / 2),
+        (_, Difficulty::Hard) => None,
+    };
+    generate_variant(options, clues, rng, cancel, |rng| {
+        Some((random_solution(size, rng), Rules::classic(size)))
+    })
+}
+
+/// 生成带额外约束的经典数独, 解题技巧不认识这些约束, 难度决定保留多少数字
+fn generate_overlays<R: Rng>(
+    difficulty: Difficulty,
+    options: GeneratorOptions,
+    rng: &mut R,
+    cancel: &AtomicBool,
+) -> Option<(Vec<u8>, Board)> {
+    let clues = match difficulty {
+        Difficulty::Easy => Some(32),
+        Difficulty::Medium => Some(26),
+        Difficulty::Hard => None,
+    };
+    let rules = Rules {
+        overlays: options.overlays,
+        ..default()
+    };
+    generate_variant(options, clues, rng, cancel, |rng| {
+        let solution = random_solution_with_overlays(&rules, rng)?;
+        Some((solution, rules.clone()))
+    })
+}
+
+/// 生成杀手数独, 难度越高笼子越大, 给出的数字越少
+fn generate_killer<R: Rng>(
+    difficulty: Difficulty,
+    options: GeneratorOptions,
+    rng: &mut R,
+    cancel: &AtomicBool,
+) -> Option<(Vec<u8>, Board)> {
+    let (max_size, clues) = match difficulty {
+        Difficulty::Easy => (3, Some(24)),
+        Difficulty::Medium => (4, Some(12)),
+        Difficulty::Hard => (5, None),
+    };
+    generate_variant(options, clues, rng, cancel, |rng| {
+        let solution = random_solution(GridSize::Nine, rng);
+        let cages = random_cages(&solution, max_size, rng);
+        Some((solution, Rules { cages, ..default() }))
+    })
+}
+
+/// 生成不规则数独, 每一局的宫都是随机的
+fn generate_jigsaw<R: Rng>(
+    difficulty: Difficulty,
+    options: GeneratorOptions,
+    rng: &mut R,
+    cancel: &AtomicBool,
+) -> Option<(Vec<u8>, Board)> {
+    let clues = match difficulty {
+        Difficulty::Easy => Some(36),
+        Difficulty::Medium => Some(30),
+        Difficulty::Hard => None,
+    };
+    generate_variant(options, clues, rng, cancel, |rng| {
+        let rules = Rules {
+            regions: random_regions(rng).to_vec(),
+            ..default()
+        };
+        let solution = random_solution_with_rules(&rules, rng)?;
+        Some((solution, rules))
+    })
+}
+
+/// 生成温度计或者箭头数独, 线条按随机的解来画, 难度越高给出的数字越少
+fn generate_lines<R: Rng>(
+    kind: LineKind,
+    difficulty: Difficulty,
+    options: GeneratorOptions,
+    rng: &mut R,
+    cancel: &AtomicBool,
+) -> Option<(Vec<u8>, Board)> {
+    let clues = match difficulty {
+        Difficulty::Easy => Some(30),
+        Difficulty::Medium => Some(24),
+        Difficulty::Hard => None,
+    };
+    generate_variant(options, clues, rng, cancel, |rng| {
+        let solution = random_solution(GridSize::Nine, rng);
+        let lines = random_lines(kind, &solution, 10, rng);
+        Some((solution, Rules { lines, ..default() }))
+    })
+}
+
+/// 按完整的解随机画最多 `count` 条线, 线之间不共用格子
+///
+/// 温度计每一步走到数字更大的相邻格子, 走到随机的长度为止;
+/// 箭头从圆圈出发, 箭身的数字正好凑够圆圈里的数字才保留
+fn random_lines<R: Rng>(kind: LineKind, solution: &[u8], count: usize, rng: &mut R) -> Vec<Line> {
+    let mut on_line = [false; 81];
+    let mut cells: Vec<usize> = (0..81).collect();
+    cells.shuffle(rng);
+
+    let mut lines = vec![];
+    for start in cells {
+        if lines.len() == count {
+            break;
+        }
+        if on_line[start] {
+            continue;
+        }
+        let length = rng.gen_range(3..=6);
+        let mut remaining = solution[start];
+        let mut path = vec![start];
+        loop {
+            let last = path[path.len() - 1];
+            let next: Vec<usize> = adjacent_cells(last)
+                .into_iter()
+                .filter(|&other| {
+                    !on_line[other]
+                        && !path.contains(&other)
+                        && match kind {
+                            LineKind::Thermometer => solution[other] > solution[last],
+                            LineKind::Arrow => solution[other] <= remaining,
+                        }
+                })
+                .collect();
+            let Some(&next) = next.choose(rng) else {
+                break;
+            };
+            path.push(next);
+            remaining = remaining.saturating_sub(solution[next]);
+            if (kind == LineKind::Thermometer && path.len() == length)
+                || (kind == LineKind::Arrow && remaining == 0)
+            {
+                break;
+            }
+        }
+        if path.len() < 3 || (kind == LineKind::Arrow && remaining != 0) {
+            continue;
+        }
+        for &cell in &path {
+            on_line[cell] = true;
+        }
+        lines.push(Line {
+            kind,
+            path: path
+                .into_iter()
+                .map(|cell| CellPosition::new(cell as u16, GridSize::Nine))
+                .collect(),
+        });
+    }
+    lines
+}
+
+/// 生成 Kropki 或者 XV 数独, 标记按随机的解来画, 难度越高给出的数字越少
+fn generate_edges<R: Rng>(
+    difficulty: Difficulty,
+    options: GeneratorOptions,
+    rng: &mut R,
+    cancel: &AtomicBool,
+) -> Option<(Vec<u8>, Board)> {
+    let clues = match difficulty {
+        Difficulty::Easy => Some(30),
+        Difficulty::Medium => Some(24),
+        Difficulty::Hard => None,
+    };
+    let kinds = options.variant.edge_kinds();
+    generate_variant(options, clues, rng, cancel, |rng| {
+        let solution = random_solution(GridSize::Nine, rng);
+        let rules = Rules {
+            edges: random_edges(kinds, &solution, options.negative, rng),
+            negative: if options.negative {
+                kinds.to_vec()
+            } else {
+                vec![]
+            },
+            ..default()
+        };
+        Some((solution, rules))
+    })
+}
+
+/// 在满足标记的相邻格子之间画标记, 两种标记都满足时随机选一种
+///
+/// 有反约束时所有满足的地方都要画上, 否则随机去掉四分之一
+fn random_edges<R: Rng>(
+    kinds: &[EdgeKind],
+    solution: &[u8],
+    negative: bool,
+    rng: &mut R,
+) -> Vec<Edge> {
+    let mut edges = vec![];
+    for cell in GridSize::Nine.cells() {
+        for other in orthogonal_neighbours(GridSize::Nine, cell) {
+            if other < cell || (!negative && rng.gen_bool(0.25)) {
+                continue;
+            }
+            let (a, b) = (solution[cell as usize], solution[other as usize]);
+            let holds: Vec<EdgeKind> = kinds
+                .iter()
+                .copied()
+                .filter(|kind| kind.holds(a, b))
+                .collect();
+            if let Some(&kind) = holds.choose(rng) {
+                edges.push(Edge {
+                    kind,
+                    cells: [cell, other].map(|cell| CellPosition::new(cell, GridSize::Nine)),
+                });
+            }
+        }
+    }
+    edges
+}
+
+/// 随机的不规则宫
+///
+/// 从九个 3×3 宫出发, 反复在两个相邻的宫之间交换边上的格子, 交换后每个宫仍然要连通
+fn random_regions<R: Rng>(rng: &mut R) -> [u8; 81] {
+    let mut regions = CLASSIC_REGIONS;
+    let mut swaps = 0;
+    while swaps < 40 {
+        let cell = rng.gen_range(0..81);
+        let Some(&other) = neighbours(cell).choose(rng) else {
+            continue;
+        };
+        let (from, to) = (regions[cell], regions[other]);
+        if from == to {
+            continue;
+        }
+        // 从另一个宫里换一个挨着这个宫的格子过来, 保持每个宫九个格子
+        let back: Vec<usize> = (0..81)
+            .filter(|&back| {
+                back != other
+                    && regions[back] == to
+                    && neighbours(back).iter().any(|&next| regions[next] == from)
+            })
+            .collect();
+        let Some(&back) = back.choose(rng) else {
+            continue;
+        };
+        regions[cell] = to;
+        regions[back] = from;
+        if is_connected(&regions, from) && is_connected(&regions, to) {
+            swaps += 1;
+        } else {
+            regions[cell] = from;
+            regions[back] = to;
+        }
+    }
+    regions
+}
+
+/// 宫里的格子上下左右连成一片
+fn is_connected(regions: &[u8; 81], region: u8) -> bool {
+    let cells: Vec<usize> = (0..81).filter(|&cell| regions[cell] == region).collect();
+    let mut seen = vec![cells[0]];
+    let mut index = 0;
+    while index < seen.len() {
+        for next in neighbours(seen[index]) {
+            if regions[next] == region && !seen.contains(&next) {
+                seen.push(next);
+            }
+        }
+        index += 1;
+    }
+    seen.len() == cells.len()
+}
+
+/// 按规则随机生成一个完整的解: 先随机放几个不冲突的数字, 再交给解题器补全
+fn random_solution_with_rules<R: Rng>(rules: &Rules, rng: &mut R) -> Option<Vec<u8>> {
+    let mut grid = vec![0; 81];
+    let mut cells: Vec<usize> = (0..81).collect();
+    cells.shuffle(rng);
+    for &cell in cells.iter().take(11) {
+        let allowed: Vec<u8> = (1..=9)
+            .filter(|&num| {
+                !(0..81).any(|other| {
+                    grid[other] == num
+                        && (other / 9 == cell / 9
+                            || other % 9 == cell % 9
+                            || rules.regions[other] == rules.regions[cell])
+                })
+            })
+            .collect();
+        grid[cell] = *allowed.choose(rng)?;
+    }
+    first_solution(&Board::with_rules(&grid, rules.clone()))
+}
+
+/// 按额外约束随机生成一个完整的解
+///
+/// 约束多的时候随机放的数字几乎都无解, 所以先找第一个解, 再随机换数字、旋转或翻转棋盘,
+/// 这些变换不会破坏宫、对角线、Windoku、马步和王步的约束
+fn random_solution_with_overlays<R: Rng>(rules: &Rules, rng: &mut R) -> Option<Vec<u8>> {
+    let solution = first_solution(&Board::with_rules(&[0; 81], rules.clone()))?;
+    let mut digits: Vec<u8> = (1..=9).collect();
+    digits.shuffle(rng);
+    let (transpose, flip_rows, flip_cols) = rng.gen();
+    Some(
+        (0..81)
+            .map(|cell| {
+                let (mut row, mut col) = (cell / 9, cell % 9);
+                if transpose {
+                    (row, col) = (col, row);
+                }
+                if flip_rows {
+                    row = 8 - row;
+                }
+                if flip_cols {
+                    col = 8 - col;
+                }
+                digits[solution[row * 9 + col] as usize - 1]
+            })
+            .collect(),
+    )
+}
+
+/// 把完整的解随机分成笼子, 笼子由上下左右相连的格子组成, 里面的数字不重复
+fn random_cages<R: Rng>(solution: &[u8], max_size: usize, rng: &mut R) -> Vec<Cage> {
+    let mut in_cage = [false; 81];
+    let mut cells: Vec<usize> = (0..81).collect();
+    cells.shuffle(rng);
+
+    let mut cages = vec![];
+    for start in cells {
+        if in_cage[start] {
+            continue;
+        }
+        let size = rng.gen_range(2..=max_size);
+        let mut members = vec![start];
+        in_cage[start] = true;
+        while members.len() < size {
+            let next: Vec<usize> = members
+                .iter()
+                .flat_map(|&cell| neighbours(cell))
+                .filter(|&other| {
+                    !in_cage[other]
+                        && members
+                            .iter()
+                            .all(|&cell| solution[cell] != solution[other])
+                })
+                .collect();
+            let Some(&next) = next.choose(rng) else {
+                break;
+            };
+            in_cage[next] = true;
+            members.push(next);
+        }
+        members.sort_unstable();
+        cages.push(Cage {
+            sum: members.iter().map(|&cell| solution[cell]).sum(),
+            cells: members.into_iter().map(|cell| cell as u16).collect(),
+        });
+    }
+    cages
+}
+
+/// 上下左右相邻的格子
+fn neighbours(cell: usize) -> Vec<usize> {
+    let (row, col) = (cell / 9, cell % 9);
+    let mut cells = vec![];
+    if row > 0 {
+        cells.push(cell - 9);
+    }
+    if row < 8 {
+        cells.push(cell + 9);
+    }
+    if col > 0 {
+        cells.push(cell - 1);
+    }
+    if col < 8 {
+        cells.push(cell + 1);
+    }
+    cells
+}
+
+/// 上下左右以及斜着相邻的格子
+fn adjacent_cells(cell: usize) -> Vec<usize> {
+    let (row, col) = (cell / 9, cell % 9);
+    (0..81)
+        .filter(|&other| {
+            other != cell && (other / 9).abs_diff(row) <= 1 && (other % 9).abs_diff(col) <= 1
+        })
+        .collect()
+}
+
+/// 随机生成一个完整的解
+///
+/// 按随机顺序回溯填数, 每次先填可选数字最少的格子, 所有合法的解都可能出现.
+/// 同一个随机数生成器状态总是得到同一个解
+fn random_solution<R: Rng>(size: GridSize, rng: &mut R) -> Vec<u8> {
+    let digit_count = size.digit_count() as usize;
+    let regions = size.regions();
+    let houses = (0..size.cell_count() as u16)
+        .map(|cell| size.houses_of(cell, &regions))
+        .collect();
+    let regions = regions
+        .iter()
+        .filter(|&&region| region != NO_REGION)
+        .max()
+        .map_or(0, |&region| region as usize + 1);
+    let mut fill = RandomFill {
+        digit_count,
+        cells: size.cells().map(usize::from).collect(),
+        houses,
+        grid: vec![0; size.cell_count()],
+        used: vec![0; size.line_count() + regions],
+    };
+    assert!(fill.fill(rng), "an empty grid always has a solution");
+    fill.grid
+}
+
+/// 回溯填数的状态, `used` 依次是每一行, 每一列, 每一宫已经用过的数字
+///
+/// 武士数独共用的格子同时在两个棋盘的行和列里
+struct RandomFill {
+    digit_count: usize,
+    cells: Vec<usize>,
+    houses: Vec<Vec<usize>>,
+    grid: Vec<u8>,
+    used: Vec<u32>,
+}
+
+impl RandomFill {
+    /// 格子还能填的数字, 第 `n - 1` 位表示数字 `n`
+    fn allowed(&self, cell: usize) -> u32 {
+        let used = self.houses[cell]
+            .iter()
+            .fold(0, |used, &house| used | self.used[house]);
+        !used & ((1 << self.digit_count) - 1)
+    }
+
+    fn place(&mut self, cell: usize, num: u8) {
+        self.grid[cell] = num;
+        for &house in &self.houses[cell] {
+            self.used[house] |= 1 << (num - 1);
+        }
+    }
+
+    fn remove(&mut self, cell: usize) {
+        let num = std::mem::take(&mut self.grid[cell]);
+        for &house in &self.houses[cell] {
+            self.used[house] &= !(1 << (num - 1));
+        }
+    }
+
+    fn fill<R: Rng>(&mut self, rng: &mut R) -> bool {
+        let Some(cell) = self
+            .cells
+            .iter()
+            .copied()
+            .filter(|&cell| self.grid[cell] == 0)
+            .min_by_key(|&cell| self.allowed(cell).count_ones())
+        else {
+            return true;
+        };
+        let allowed = self.allowed(cell);
+        let mut nums: Vec<u8> = (1..=self.digit_count as u8)
+            .filter(|num| allowed & (1 << (num - 1)) != 0)
+            .collect();
+        nums.shuffle(rng);
+        for num in nums {
+            self.place(cell, num);
+            if self.fill(rng) {
+                return true;
+            }
+            self.remove(cell);
+        }
+        false
+    }
+}
+
+/// 按随机顺序挖空, 只要解仍然唯一就保留空格
+///
+/// 对称的格子一起挖, 线索数达到目标后停止. `unique` 检查挖完之后解是否唯一
+fn dig_holes<R: Rng>(
+    solution: &[u8],
+    options: GeneratorOptions,
+    rng: &mut R,
+    unique: impl Fn(&[u8]) -> bool,
+) -> Vec<u8> {
+    let mut puzzle = solution.to_vec();
+    let mut clues = solution.iter().filter(|&&num| num != 0).count();
+    let mut cells: Vec<usize> = (0..solution.len()).collect();
+    cells.shuffle(rng);
+
+    for cell in cells {
+        if options.clues.is_some_and(|target| clues <= target) {
+            break;
+        }
+        if puzzle[cell] == 0 {
+            continue;
+        }
+        let orbit = options.symmetry.orbit(options.size, cell);
+        for &other in &orbit {
+            puzzle[other] = 0;
+        }
+        if unique(&puzzle) {
+            clues -= orbit.len();
+        } else {
+            for &other in &orbit {
+                puzzle[other] = solution[other];
+            }
+        }
+    }
+    puzzle
+}
+
+fn has_unique_solution(puzzle: &[u8]) -> bool {
+    to_sudoku(puzzle).is_some_and(|sudoku| sudoku.solution().is_some())
+}
+
+fn to_sudoku(cells: &[u8]) -> Option<Sudoku> {
+    Sudoku::from_bytes(cells.try_into().ok()?).ok()
+}
+
+#[cfg(test)]
+fn cells(sudoku: Sudoku) -> Vec<Option<u8>> {
+    sudoku.iter().collect()
+}
+
+#[test]
+fn test_daily_same_date() {
+    let date = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+    let (first, first_solution) = generate_daily(date, Difficulty::Easy);
+    let (second, second_solution) = generate_daily(date, Difficulty::Easy);
+
+    assert_eq!(cells(first).len(), 81);
+    assert_eq!(cells(first), cells(second));
+    assert_eq!(cells(first_solution), cells(second_solution));
+}
+
+#[test]
+fn test_daily_same_date_medium() {
+    let date = NaiveDate::from_ymd_opt(2025, 6, 15).unwrap();
+    let (first, _) = generate_daily(date, Difficulty::Medium);
+    let (second, _) = generate_daily(date, Difficulty::Medium);
+
+    assert_eq!(cells(first), cells(second));
+    assert_eq!(grade(first), Some(Difficulty::Medium));
+}
+
+#[test]
+fn test_daily_other_date() {
+    let (first, _) = generate_daily(
+        NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(),
+        Difficulty::Easy,
+    );
+    let (second, _) = generate_daily(
+        NaiveDate::from_ymd_opt(2025, 1, 2).unwrap(),
+        Difficulty::Easy,
+    );
+
+    assert_ne!(cells(first), cells(second));
+}
+
+/// 每日谜题的生成方式或者种子变了, 玩家同一天看到的谜题就不一样了
+#[test]
+fn test_daily_golden() {
+    let date = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+    let (puzzle, solution) = generate_daily(date, Difficulty::Easy);
+    assert_eq!(
+        puzzle.to_str_line().to_string(),
+        ".4...31..63.....52........8.51......4....7.....64....35....9.2....1..4...9..387.."
+    );
+    assert_eq!(
+        solution.to_str_line().to_string(),
+        "845293167637814952129576348351982674482367591976451283513749826768125439294638715"
+    );
+}
+
+#[test]
+fn test_symmetric_holes() {
+    let mut rng = ChaCha8Rng::seed_from_u64(7);
+    let solution = random_solution(GridSize::Nine, &mut rng);
+    for symmetry in [Symmetry::Rotational, Symmetry::Diagonal, Symmetry::Mirror] {
+        let options = GeneratorOptions {
+            symmetry,
+            ..default()
+        };
+        let puzzle = dig_holes(&solution, options, &mut rng, has_unique_solution);
+        assert!(has_unique_solution(&puzzle));
+        for cell in 0..81 {
+            for other in symmetry.orbit(GridSize::Nine, cell) {
+                assert_eq!(puzzle[cell] == 0, puzzle[other] == 0);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_target_clues() {
+    let mut rng = ChaCha8Rng::seed_from_u64(7);
+    let solution = random_solution(GridSize::Nine, &mut rng);
+    let options = GeneratorOptions {
+        clues: Some(40),
+        ..default()
+    };
+    let puzzle = dig_holes(&solution, options, &mut rng, has_unique_solution);
+    assert_eq!(puzzle.iter().filter(|&&digit| digit != 0).count(), 40);
+    assert!(has_unique_solution(&puzzle));
+}
+
+#[test]
+fn test_random_cages() {
+    let mut rng = ChaCha8Rng::seed_from_u64(7);
+    let solution = random_solution(GridSize::Nine, &mut rng);
+    let cages = random_cages(&solution, 4, &mut rng);
+
+    let mut covered = [0; 81];
+    for cage in &cages {
+        assert!(cage.cells.len() <= 4);
+        let digits: Vec<u8> = cage
+            .cells
+            .iter()
+            .map(|&cell| solution[cell as usize])
+            .collect();
+        assert_eq!(digits.iter().sum::<u8>(), cage.sum);
+        for (index, digit) in digits.iter().enumerate() {
+            assert!(!digits[index + 1..].contains(digit));
+        }
+        for &cell in &cage.cells {
+            covered[cell as usize] += 1;
+        }
+    }
+    assert!(covered.iter().all(|&count| count == 1));
+}
+
+#[test]
+fn test_random_lines() {
+    let mut rng = ChaCha8Rng::seed_from_u64(7);
+    let solution = random_solution(GridSize::Nine, &mut rng);
+    for kind in [LineKind::Thermometer, LineKind::Arrow] {
+        let lines = random_lines(kind, &solution, 10, &mut rng);
+        assert!(!lines.is_empty());
+        let rules = Rules { lines, ..default() };
+        assert!(rules.is_valid());
+        assert!(Board::with_rules(&solution, rules).is_solved());
+    }
+
+    let options = GeneratorOptions {
+        variant: Variant::Arrow,
+        ..default()
+    };
+    let (solution, board) = generate_lines(
+        LineKind::Arrow,
+        Difficulty::Easy,
+        options,
+        &mut rng,
+        &AtomicBool::new(false),
+    )
+    .unwrap();
+    assert!(Board::with_rules(&solution, board.rules().clone()).is_solved());
+    assert_eq!(count_solutions(&board.values(), board.rules(), 2), 1);
+}
+
+#[test]
+fn test_random_edges() {
+    let mut rng = ChaCha8Rng::seed_from_u64(7);
+    let solution = random_solution(GridSize::Nine, &mut rng);
+    for variant in [Variant::Kropki, Variant::XV] {
+        let kinds = variant.edge_kinds();
+        let rules = Rules {
+            edges: random_edges(kinds, &solution, true, &mut rng),
+            negative: kinds.to_vec(),
+            ..default()
+        };
+        assert!(rules.is_valid());
+        assert!(Board::with_rules(&solution, rules).is_solved());
+    }
+
+    let options = GeneratorOptions {
+        variant: Variant::Kropki,
+        negative: true,
+        ..default()
+    };
+    let (solution, board) =
+        generate_edges(Difficulty::Easy, options, &mut rng, &AtomicBool::new(false)).unwrap();
+    assert!(!board.rules().negative.is_empty());
+    assert!(Board::with_rules(&solution, board.rules().clone()).is_solved());
+    assert_eq!(count_solutions(&board.values(), board.rules(), 2), 1);
+}
+
+#[test]
+fn test_random_regions() {
+    let mut rng = ChaCha8Rng::seed_from_u64(7);
+    let regions = random_regions(&mut rng);
+    assert_ne!(regions, CLASSIC_REGIONS);
+    for region in 0..9 {
+        assert_eq!(regions.iter().filter(|&&r| r == region).count(), 9);
+        assert!(is_connected(&regions, region));
+    }
+}
+
+#[test]
+fn test_other_sizes() {
+    let mut rng = ChaCha8Rng::seed_from_u64(7);
+    for size in [
+        GridSize::Four,
+        GridSize::Six,
+        GridSize::Sixteen,
+        GridSize::Samurai,
+    ] {
+        let options = GeneratorOptions { size, ..default() };
+        let (solution, board) = generate_sized(
+            Difficulty::Medium,
+            options,
+            &mut rng,
+            &AtomicBool::new(false),
+        )
+        .unwrap();
+        assert!(Board::with_rules(&solution, Rules::classic(size)).is_solved());
+        assert_eq!(count_solutions(&board.values(), board.rules(), 2), 1);
+        let clues = size.cells().filter(|&cell| board.is_given(cell)).count();
+        assert!(clues <= size.cells().count() / 2 || size == GridSize::Sixteen);
+    }
+}
+
+#[test]
+fn test_overlays() {
+    use crate::game::model::Overlay;
+
+    let mut overlays = Overlays::NONE;
+    overlays.toggle(Overlay::Diagonals);
+    overlays.toggle(Overlay::AntiKing);
+    let options = GeneratorOptions {
+        overlays,
+        ..default()
+    };
+    let mut rng = ChaCha8Rng::seed_from_u64(7);
+    let (solution, board) =
+        generate_overlays(Difficulty::Easy, options, &mut rng, &AtomicBool::new(false)).unwrap();
+    assert!(Board::with_rules(&solution, board.rules().clone()).is_solved());
+    assert_eq!(count_solutions(&board.values(), board.rules(), 2), 1);
+}
+
+#[test]
+fn test_matching_options() {
+    use crate::game::model::Overlay;
+
+    let mut overlays = Overlays::NONE;
+    overlays.toggle(Overlay::Diagonals);
+    let cancel = AtomicBool::new(false);
+    for options in [
+        GeneratorOptions::default(),
+        GeneratorOptions {
+            size: GridSize::Six,
+            ..default()
+        },
+        GeneratorOptions {
+            variant: Variant::Killer,
+            ..default()
+        },
+        GeneratorOptions {
+            variant: Variant::XV,
+            negative: true,
+            ..default()
+        },
+        GeneratorOptions {
+            overlays,
+            ..default()
+        },
+    ] {
+        let (_, board) = generate_board(Difficulty::Easy, options, &cancel).unwrap();
+        assert_eq!(GeneratorOptions::default().matching(board.rules()), options);
+    }
+    // 每日谜题用默认规则, 之前在设置里选的变体不能带到下一局
+    let stale = GeneratorOptions {
+        size: GridSize::Four,
+        variant: Variant::Thermo,
+        ..default()
+    };
+    assert_eq!(
+        stale.matching(&Rules::classic(GridSize::Nine)),
+        GeneratorOptions::default()
+    );
+}
+
+#[test]
+fn test_daily_seed() {
+    let date = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+    assert_ne!(
+        daily_seed(date, Difficulty::Easy),
+        daily_seed(date, Difficulty::Hard)
+    );
+    assert_ne!(
+        daily_seed(date, Difficulty::Hard),
+        daily_seed(date.succ_opt().unwrap(), Difficulty::Easy)
+    );
+}
+
+#[test]
+fn test_cancelled() {
+    let cancel = AtomicBool::new(true);
+    assert!(generate_board(Difficulty::Hard, GeneratorOptions::default(), &cancel).is_none());
+    let options = GeneratorOptions {
+        variant: Variant::Killer,
+        ..default()
+    };
+    assert!(generate_board(Difficulty::Hard, options, &cancel).is_none());
+}
@@ -0,0 +1,360 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::ops::{BitAnd, BitOr, BitXorAssign};
+
+/// 棋盘大小, 宫是 `box_rows` 行 `box_cols` 列
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GridSize {
+    /// 2×2 宫, 给小朋友玩
+    Four,
+    /// 2×3 宫
+    Six,
+    #[default]
+    Nine,
+    /// 4×4 宫, 10 到 16 用字母 A 到 G 表示
+    Sixteen,
+    /// 武士数独: 五个 9×9 的棋盘摆在 21×21 的格子上,
+    /// 四个角上的棋盘各和中间的棋盘共用一个宫, 其他位置是空着的
+    Samurai,
+}
+
+/// 武士数独五个棋盘左上角的 (行, 列): 左上, 右上, 中间, 左下, 右下
+pub const SAMURAI_GRIDS: [(u8, u8); 5] = [(0, 0), (0, 12), (6, 6), (12, 0), (12, 12)];
+
+/// 空着的位置所在的宫
+pub const NO_REGION: u8 = u8::MAX;
+
+impl GridSize {
+    /// 每行的格子数, 武士数独是 21, 其他大小也是最大的数字
+    pub fn side(self) -> u8 {
+        match self {
+            GridSize::Four => 4,
+            GridSize::Six => 6,
+            GridSize::Nine => 9,
+            GridSize::Sixteen => 16,
+            GridSize::Samurai => 21,
+        }
+    }
+
+    /// 最大的数字, 也是每个宫的格子数
+    pub fn digit_count(self) -> u8 {
+        match self {
+            GridSize::Samurai => 9,
+            size => size.side(),
+        }
+    }
+
+    pub fn box_rows(self) -> u8 {
+        match self {
+            GridSize::Four | GridSize::Six => 2,
+            GridSize::Nine | GridSize::Samurai => 3,
+            GridSize::Sixteen => 4,
+        }
+    }
+
+    pub fn box_cols(self) -> u8 {
+        self.digit_count() / self.box_rows()
+    }
+
+    /// 格子总数, 包括武士数独空着的位置. 16×16 有 256 个格子, 所以不是 `u8`
+    pub fn cell_count(self) -> usize {
+        self.side() as usize * self.side() as usize
+    }
+
+    /// 所有格子的编号, 不包括武士数独空着的位置
+    pub fn cells(self) -> impl Iterator<Item = u16> {
+        (0..self.cell_count() as u16).filter(move |&cell| self.contains(cell))
+    }
+
+    /// 这个位置是棋盘上的格子
+    pub fn contains(self, cell: u16) -> bool {
+        (cell as usize) < self.cell_count() && self.grids_of(cell).next().is_some()
+    }
+
+    /// 格子所在的 9×9 棋盘, 武士数独共用的格子在两个棋盘里, 其他大小只有一个棋盘
+    pub fn grids_of(self, cell: u16) -> impl Iterator<Item = usize> {
+        let side = self.side() as u16;
+        let (row, col) = (cell / side, cell % side);
+        let grids: &[(u8, u8)] = match self {
+            GridSize::Samurai => &SAMURAI_GRIDS,
+            _ => &[(0, 0)],
+        };
+        let size = self.digit_count() as u16;
+        grids
+            .iter()
+            .enumerate()
+            .filter(move |(_, &(top, left))| {
+                let (top, left) = (top as u16, left as u16);
+                (top..top + size).contains(&row) && (left..left + size).contains(&col)
+            })
+            .map(|(grid, _)| grid)
+    }
+
+    /// 两个格子在同一个棋盘里, 只有这时同一行或者同一列才算
+    pub fn shares_grid(self, a: u16, b: u16) -> bool {
+        self != GridSize::Samurai
+            || self
+                .grids_of(a)
+                .any(|grid| self.grids_of(b).any(|other| other == grid))
+    }
+
+    /// 格子所在的行和列, 编号在所有棋盘里唯一. 武士数独共用的格子在两个棋盘里各有一行一列,
+    /// 编号都小于 [`GridSize::line_count`]
+    pub fn lines_of(self, cell: u16) -> Vec<usize> {
+        let side = self.side() as usize;
+        let (row, col) = (cell as usize / side, cell as usize % side);
+        if self != GridSize::Samurai {
+            return vec![row, side + col];
+        }
+        self.grids_of(cell)
+            .flat_map(|grid| {
+                let (top, left) = SAMURAI_GRIDS[grid];
+                [
+                    grid * 9 + row - top as usize,
+                    45 + grid * 9 + col - left as usize,
+                ]
+            })
+            .collect()
+    }
+
+    /// 格子所在的行、列和宫, 宫的编号排在所有行和列后面. 空着的位置不在任何行、列和宫里
+    pub fn houses_of(self, cell: u16, regions: &[u8]) -> Vec<usize> {
+        if !self.contains(cell) {
+            return vec![];
+        }
+        let mut houses = self.lines_of(cell);
+        houses.push(self.line_count() + regions[cell as usize] as usize);
+        houses
+    }
+
+    /// 所有棋盘的行数加列数
+    pub fn line_count(self) -> usize {
+        match self {
+            GridSize::Samurai => 90,
+            size => size.side() as usize * 2,
+        }
+    }
+
+    /// 所有可以填的数字
+    pub fn digits(self) -> impl Iterator<Item = Digit> {
+        (1..=self.digit_count()).map(Digit::new)
+    }
+
+    /// 每个格子所在的宫, 宫从左到右、从上到下编号, 空着的位置是 [`NO_REGION`]
+    pub fn regions(self) -> Vec<u8> {
+        let (side, box_rows, box_cols) = (self.side(), self.box_rows(), self.box_cols());
+        (0..self.cell_count() as u16)
+            .map(|cell| {
+                if !self.contains(cell) {
+                    return NO_REGION;
+                }
+                let (row, col) = ((cell / side as u16) as u8, (cell % side as u16) as u8);
+                row / box_rows * (side / box_cols) + col / box_cols
+            })
+            .collect()
+    }
+}
+
+impl Display for GridSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GridSize::Samurai => write!(f, "Samurai"),
+            size => write!(f, "{0}×{0}", size.side()),
+        }
+    }
+}
+
+/// 格子里的数字, 1 到 16
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Digit(u8);
+
+impl Digit {
+    pub fn new(num: u8) -> Digit {
+        assert!((1..=16).contains(&num), "digit out of range: {num}");
+        Digit(num)
+    }
+
+    /// 符号转成数字: `1`-`9`, 以及大小写的 `A`-`G`
+    pub fn from_symbol(symbol: char) -> Option<Digit> {
+        let num = match symbol.to_ascii_uppercase() {
+            ch @ '1'..='9' => ch as u8 - b'0',
+            ch @ 'A'..='G' => ch as u8 - b'A' + 10,
+            _ => return None,
+        };
+        Some(Digit(num))
+    }
+
+    pub fn get(self) -> u8 {
+        self.0
+    }
+
+    pub fn as_set(self) -> DigitSet {
+        DigitSet(1 << self.0)
+    }
+
+    /// 显示用的符号, 大于 9 的数字用字母
+    pub fn symbol(self) -> char {
+        match self.0 {
+            num @ 1..=9 => char::from(b'0' + num),
+            num => char::from(b'A' + num - 10),
+        }
+    }
+}
+
+impl Display for Digit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.symbol())
+    }
+}
+
+/// 一组数字, 第 n 位表示数字 n
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct DigitSet(u32);
+
+impl DigitSet {
+    pub const NONE: DigitSet = DigitSet(0);
+
+    /// 包含 `other` 的所有数字
+    pub fn contains(self, other: DigitSet) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn remove(&mut self, other: DigitSet) {
+        self.0 &= !other.0;
+    }
+
+    pub fn len(self) -> u32 {
+        self.0.count_ones()
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// 从小到大的数字
+    pub fn digits(self) -> impl Iterator<Item = Digit> {
+        (1..=16u8)
+            .filter(move |num| self.0 & (1 << num) != 0)
+            .map(Digit)
+    }
+}
+
+impl BitAnd for DigitSet {
+    type Output = DigitSet;
+
+    fn bitand(self, other: DigitSet) -> DigitSet {
+        DigitSet(self.0 & other.0)
+    }
+}
+
+impl BitOr for DigitSet {
+    type Output = DigitSet;
+
+    fn bitor(self, other: DigitSet) -> DigitSet {
+        DigitSet(self.0 | other.0)
+    }
+}
+
+/// 切换一个数字
+impl BitXorAssign<Digit> for DigitSet {
+    fn bitxor_assign(&mut self, digit: Digit) {
+        self.0 ^= 1 << digit.0;
+    }
+}
+
+/// 格子转成一行文字, 每格一个符号, `.` 表示空格
+pub fn cells_line(cells: &[u8]) -> String {
+    cells
+        .iter()
+        .map(|&num| match num {
+            0 => '.',
+            num => Digit::new(num).symbol(),
+        })
+        .collect()
+}
+
+/// 解析 [`cells_line`] 的结果, 数字不能超过棋盘大小
+pub fn parse_cells_line(line: &str, size: GridSize) -> Option<Vec<u8>> {
+    let cells: Vec<u8> = line
+        .chars()
+        .map(|ch| match ch {
+            '.' => Some(0),
+            ch => Digit::from_symbol(ch)
+                .map(Digit::get)
+                .filter(|&num| num <= size.digit_count()),
+        })
+        .collect::<Option<_>>()?;
+    (cells.len() == size.cell_count()).then_some(cells)
+}
+
+#[test]
+fn test_grid_sizes() {
+    assert_eq!(GridSize::Six.box_cols(), 3);
+    assert_eq!(GridSize::Sixteen.cells().last(), Some(255));
+
+    let regions = GridSize::Six.regions();
+    assert_eq!(&regions[..12], &[0, 0, 0, 1, 1, 1, 0, 0, 0, 1, 1, 1]);
+    assert_eq!(regions[35], 5);
+    for region in 0..16 {
+        let count = GridSize::Sixteen
+            .regions()
+            .into_iter()
+            .filter(|&r| r == region)
+            .count();
+        assert_eq!(count, 16);
+    }
+}
+
+#[test]
+fn test_samurai() {
+    let samurai = GridSize::Samurai;
+    assert_eq!(samurai.cells().count(), 369);
+    assert_eq!(samurai.digits().count(), 9);
+    assert_eq!(samurai.box_cols(), 3);
+    // 第 0 行第 9 列在左上和右上两个棋盘中间, 是空着的
+    assert!(!samurai.contains(9));
+    // 第 6 行第 6 列是左上棋盘和中间棋盘共用的宫
+    let shared = 6 * 21 + 6;
+    assert_eq!(samurai.grids_of(shared).collect::<Vec<_>>(), vec![0, 2]);
+    assert_eq!(samurai.lines_of(shared).len(), 4);
+    // 同一行的两个格子在不同的棋盘里, 不算同一行
+    assert!(!samurai.shares_grid(0, 20));
+    assert!(samurai.shares_grid(0, shared));
+
+    let regions = samurai.regions();
+    assert_eq!(regions[9], NO_REGION);
+    for region in [0, 8, 24, 48] {
+        assert_eq!(regions.iter().filter(|&&r| r == region).count(), 9);
+    }
+}
+
+#[test]
+fn test_symbols() {
+    assert_eq!(Digit::new(7).symbol(), '7');
+    assert_eq!(Digit::new(16).symbol(), 'G');
+    assert_eq!(Digit::from_symbol('b'), Some(Digit::new(11)));
+    assert_eq!(Digit::from_symbol('0'), None);
+
+    let line = cells_line(&[1, 0, 10, 16]);
+    assert_eq!(line, "1.AG");
+    assert_eq!(parse_cells_line("1.3.", GridSize::Four), None);
+    assert_eq!(
+        parse_cells_line(&"1.3.".repeat(4), GridSize::Four).map(|cells| cells[2]),
+        Some(3)
+    );
+}
+
+#[test]
+fn test_digit_set() {
+    let mut set = DigitSet::NONE;
+    set ^= Digit::new(3);
+    set ^= Digit::new(16);
+    assert_eq!(set.len(), 2);
+    assert!(set.contains(Digit::new(16).as_set()));
+    assert_eq!(
+        set.digits().collect::<Vec<_>>(),
+        vec![Digit::new(3), Digit::new(16)]
+    );
+    set.remove(Digit::new(3).as_set());
+    assert_eq!(set, Digit::new(16).as_set());
+}
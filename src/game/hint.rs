@@ -0,0 +1,981 @@
+use crate::game::{
+    board::{HintCandidates, HintCell},
+    cell_state::{AutoCandidates, CellMode, DigitValueCell, ManualCandidates},
+    dialog::ShowTechnique,
+    generator::STRATEGIES,
+    grid::{Digit as PlayerDigit, GridSize},
+    history::MoveHistory,
+    model::Rules,
+    position::CellPosition,
+    FindHint, InitPuzzle, NewDigit, SelectCell, SudokuManager,
+};
+use bevy::prelude::*;
+use sudoku::{
+    bitset::Set,
+    board::{CellState, Digit},
+    strategy::{deduction::Deduction, Strategy, StrategySolver},
+};
+
+pub(crate) fn plugin(app: &mut App) {
+    app.init_resource::<CurrentHint>()
+        .add_observer(find_hint)
+        .add_observer(on_apply_hint)
+        .add_observer(on_close_technique)
+        .add_observer(clear_hint_on_init);
+}
+
+/// 执行当前提示: 填入数字或删除候选数
+#[derive(Event)]
+pub struct ApplyHint;
+
+/// 玩家眼中的一个格子
+#[derive(Clone, Copy)]
+pub struct PlayerCell {
+    pub digit: Option<Digit>,
+    /// 玩家标记的候选数, 为空表示还没有标记
+    pub candidates: Set<Digit>,
+}
+
+/// 一条提示
+#[derive(Clone, Debug)]
+pub struct Hint {
+    pub technique: &'static str,
+    pub explanation: String,
+    /// 提示指向的格子
+    pub cell: Option<u8>,
+    /// 可以填入的数字
+    pub placement: Option<(u8, Digit)>,
+    /// 可以删除的候选数
+    pub eliminations: Vec<(u8, Digit)>,
+    /// 推理时查看的宫
+    pub house: Vec<u8>,
+    /// 构成这一技巧的候选数
+    pub pattern: Vec<(u8, Digit)>,
+}
+
+impl Hint {
+    /// 指出了格子或者下一步, "No Hint" 不算用了一次提示
+    fn is_found(&self) -> bool {
+        self.cell.is_some() || self.placement.is_some() || !self.eliminations.is_empty()
+    }
+}
+
+/// 最近一次找到的提示
+#[derive(Resource, Default)]
+pub struct CurrentHint(pub Option<Hint>);
+
+/// 按技巧从易到难找出下一步
+///
+/// 先检查填错的数字和漏掉正确答案的候选数, 否则这些技巧得到的结论没有意义.
+/// 解题技巧只认识 3×3 宫, 不规则数独只检查错误
+pub fn next_hint(cells: &[PlayerCell; 81], solution: &[u8], rules: &Rules) -> Hint {
+    for (index, cell) in cells.iter().enumerate() {
+        if let Some(digit) = cell.digit {
+            if digit.get() != solution[index] {
+                return Hint {
+                    technique: "Mistake",
+                    explanation: format!(
+                        "The {} in {} is not correct.",
+                        digit.get(),
+                        cell_name(index as u8)
+                    ),
+                    cell: Some(index as u8),
+                    placement: None,
+                    eliminations: vec![],
+                    house: vec![],
+                    pattern: vec![],
+                };
+            }
+        }
+    }
+
+    let grid = grid_state(cells, rules);
+    for (index, cell_state) in grid.iter().enumerate() {
+        if let CellState::Candidates(candidates) = cell_state {
+            if !candidates.contains(Digit::new(solution[index]).as_set()) {
+                return Hint {
+                    technique: "Missing Candidate",
+                    explanation: format!(
+                        "The candidates of {} no longer include the correct digit. Check the candidates you removed there.",
+                        cell_name(index as u8)
+                    ),
+                    cell: Some(index as u8),
+                    placement: None,
+                    eliminations: vec![],
+                    house: vec![],
+                    pattern: vec![],
+                };
+            }
+        }
+    }
+
+    let solver = StrategySolver::from_grid_state(grid);
+    for strategy in STRATEGIES.iter().filter(|_| rules.has_classic_regions()) {
+        let deductions = match solver.clone().solve(std::slice::from_ref(strategy)) {
+            Ok((_, deductions)) | Err((_, deductions)) => deductions,
+        };
+        let Some(deduction) = deductions.get(0) else {
+            continue;
+        };
+        if let Some(hint) = describe(strategy, deduction, &grid) {
+            return hint;
+        }
+    }
+
+    Hint {
+        technique: "No Hint",
+        explanation: "No further step can be found with the techniques this game knows."
+            .to_string(),
+        cell: None,
+        placement: None,
+        eliminations: vec![],
+        house: vec![],
+        pattern: vec![],
+    }
+}
+
+/// 玩家的数字和候选数转成解题器的输入
+///
+/// 没有标记候选数的格子按同行、同列、同宫以及额外约束已有的数字推算候选数
+fn grid_state(cells: &[PlayerCell; 81], rules: &Rules) -> [CellState; 81] {
+    let mut grid = [CellState::Candidates(Set::NONE); 81];
+    for (index, cell) in cells.iter().enumerate() {
+        if let Some(digit) = cell.digit {
+            grid[index] = CellState::Digit(digit);
+            continue;
+        }
+
+        let mut possible = Set::NONE;
+        for num in 1..=9 {
+            let seen = cells.iter().enumerate().any(|(other, other_cell)| {
+                other != index
+                    && other_cell.digit == Some(Digit::new(num))
+                    && (other / 9 == index / 9
+                        || other % 9 == index % 9
+                        || rules.regions[other] == rules.regions[index]
+                        || rules
+                            .overlays
+                            .sees(GridSize::Nine, other as u16, index as u16))
+            });
+            if !seen {
+                possible ^= Digit::new(num);
+            }
+        }
+
+        grid[index] = if cell.candidates.is_empty() {
+            CellState::Candidates(possible)
+        } else {
+            CellState::Candidates(possible & cell.candidates)
+        };
+    }
+    grid
+}
+
+fn describe(
+    strategy: &Strategy,
+    deduction: Deduction<&[sudoku::board::Candidate]>,
+    grid: &[CellState; 81],
+) -> Option<Hint> {
+    let technique = technique_name(strategy);
+    match deduction {
+        Deduction::NakedSingles(candidate) => {
+            let cell = candidate.cell.get();
+            Some(Hint {
+                technique,
+                explanation: format!(
+                    "{} has only one candidate left, so it must be {}.",
+                    cell_name(cell),
+                    candidate.digit.get()
+                ),
+                cell: Some(cell),
+                placement: Some((cell, candidate.digit)),
+                eliminations: vec![],
+                house: vec![],
+                pattern: vec![],
+            })
+        }
+        Deduction::HiddenSingles(candidate, _) => {
+            let cell = candidate.cell.get();
+            let house = hidden_single_house(grid, cell, candidate.digit);
+            Some(Hint {
+                technique,
+                explanation: format!(
+                    "{} is the only place in its {} where {} can go.",
+                    cell_name(cell),
+                    house_name(house),
+                    candidate.digit.get()
+                ),
+                cell: Some(cell),
+                placement: Some((cell, candidate.digit)),
+                eliminations: vec![],
+                house: house_cells(house),
+                pattern: vec![],
+            })
+        }
+        Deduction::LockedCandidates { conflicts, .. }
+        | Deduction::Subsets { conflicts, .. }
+        | Deduction::BasicFish { conflicts, .. }
+        | Deduction::Wing { conflicts, .. } => {
+            let eliminations: Vec<(u8, Digit)> = conflicts
+                .iter()
+                .map(|candidate| (candidate.cell.get(), candidate.digit))
+                .collect();
+            let first = eliminations.first()?;
+            let (house, pattern) =
+                elimination_pattern(strategy, grid, &eliminations).unwrap_or_default();
+            Some(Hint {
+                technique,
+                explanation: format!(
+                    "{} Remove {}.",
+                    technique_reason(strategy),
+                    eliminations
+                        .iter()
+                        .map(|(cell, digit)| format!("{} from {}", digit.get(), cell_name(*cell)))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+                cell: Some(first.0),
+                placement: None,
+                eliminations,
+                house,
+                pattern,
+            })
+        }
+        _ => None,
+    }
+}
+
+fn technique_name(strategy: &Strategy) -> &'static str {
+    match strategy {
+        Strategy::NakedSingles => "Naked Single",
+        Strategy::HiddenSingles => "Hidden Single",
+        Strategy::LockedCandidates => "Locked Candidates",
+        Strategy::NakedPairs => "Naked Pair",
+        Strategy::HiddenPairs => "Hidden Pair",
+        Strategy::NakedTriples => "Naked Triple",
+        Strategy::HiddenTriples => "Hidden Triple",
+        Strategy::NakedQuads => "Naked Quad",
+        Strategy::HiddenQuads => "Hidden Quad",
+        Strategy::XWing => "X-Wing",
+        Strategy::Swordfish => "Swordfish",
+        Strategy::Jellyfish => "Jellyfish",
+        Strategy::XyWing => "XY-Wing",
+        Strategy::XyzWing => "XYZ-Wing",
+        _ => "Logic",
+    }
+}
+
+/// 删除候选数的理由
+fn technique_reason(strategy: &Strategy) -> &'static str {
+    match strategy {
+        Strategy::LockedCandidates => {
+            "All places for this digit in one house also lie in a second house, so it cannot appear anywhere else in the second house."
+        }
+        Strategy::NakedPairs | Strategy::NakedTriples | Strategy::NakedQuads => {
+            "These cells of one house share the same few candidates between them, so those digits cannot appear in the other cells of the house."
+        }
+        Strategy::HiddenPairs | Strategy::HiddenTriples | Strategy::HiddenQuads => {
+            "These digits can only go in the same few cells of one house, so those cells cannot hold any other digit."
+        }
+        Strategy::XWing | Strategy::Swordfish | Strategy::Jellyfish => {
+            "In several rows this digit is limited to the same columns (or the other way round), so it cannot appear elsewhere in those columns."
+        }
+        Strategy::XyWing | Strategy::XyzWing => {
+            "A pivot cell and its pincers force this digit into one of the pincers, so cells that see all of them cannot hold it."
+        }
+        _ => "",
+    }
+}
+
+/// 宫的编号: 0..9 是行, 9..18 是列, 18..27 是宫格
+fn house_cells(house: u8) -> Vec<u8> {
+    (0..81u8)
+        .filter(|&cell| {
+            let position = CellPosition::new(cell.into(), GridSize::Nine);
+            match house / 9 {
+                0 => position.row() == house % 9,
+                1 => position.col() == house % 9,
+                _ => position.block() == house % 9,
+            }
+        })
+        .collect()
+}
+
+fn house_name(house: u8) -> &'static str {
+    match house / 9 {
+        0 => "row",
+        1 => "column",
+        _ => "box",
+    }
+}
+
+fn candidates_of(grid: &[CellState; 81], cell: u8) -> Set<Digit> {
+    match grid[cell as usize] {
+        CellState::Digit(_) => Set::NONE,
+        CellState::Candidates(candidates) => candidates,
+    }
+}
+
+fn has_candidate(grid: &[CellState; 81], cell: u8, digit: Digit) -> bool {
+    candidates_of(grid, cell).contains(digit.as_set())
+}
+
+fn digits_of(candidates: Set<Digit>) -> Vec<Digit> {
+    (1..=9)
+        .map(Digit::new)
+        .filter(|digit| candidates.contains(digit.as_set()))
+        .collect()
+}
+
+fn sees(cell: u8, other: u8) -> bool {
+    cell != other
+        && CellPosition::new(cell.into(), GridSize::Nine)
+            .in_range(&CellPosition::new(other.into(), GridSize::Nine))
+}
+
+/// 从 `items` 中取出 `size` 个元素的所有组合
+fn combinations(items: &[u8], size: usize) -> Vec<Vec<u8>> {
+    if size == 0 {
+        return vec![vec![]];
+    }
+    let mut result = vec![];
+    for (index, &item) in items.iter().enumerate() {
+        for mut rest in combinations(&items[index + 1..], size - 1) {
+            rest.insert(0, item);
+            result.push(rest);
+        }
+    }
+    result
+}
+
+/// 隐性唯一数所在的宫, 行优先
+fn hidden_single_house(grid: &[CellState; 81], cell: u8, digit: Digit) -> u8 {
+    (0..27)
+        .find(|&house| {
+            let cells = house_cells(house);
+            cells.contains(&cell)
+                && cells
+                    .iter()
+                    .all(|&other| other == cell || !has_candidate(grid, other, digit))
+        })
+        .unwrap_or(18 + CellPosition::new(cell.into(), GridSize::Nine).block())
+}
+
+/// 还原删除候选数的技巧用到的宫和候选数
+///
+/// 解题器只告诉我们删掉哪些候选数, 这里按技巧重新找出构成它的格子
+fn elimination_pattern(
+    strategy: &Strategy,
+    grid: &[CellState; 81],
+    eliminations: &[(u8, Digit)],
+) -> Option<(Vec<u8>, Vec<(u8, Digit)>)> {
+    let mut removed: Vec<u8> = eliminations.iter().map(|(cell, _)| *cell).collect();
+    removed.sort();
+    removed.dedup();
+
+    match strategy {
+        Strategy::LockedCandidates => locked_pattern(grid, eliminations[0].1, &removed),
+        Strategy::NakedPairs => naked_pattern(grid, eliminations, &removed, 2),
+        Strategy::NakedTriples => naked_pattern(grid, eliminations, &removed, 3),
+        Strategy::NakedQuads => naked_pattern(grid, eliminations, &removed, 4),
+        Strategy::HiddenPairs => hidden_pattern(grid, eliminations, &removed, 2),
+        Strategy::HiddenTriples => hidden_pattern(grid, eliminations, &removed, 3),
+        Strategy::HiddenQuads => hidden_pattern(grid, eliminations, &removed, 4),
+        Strategy::XWing => fish_pattern(grid, eliminations[0].1, &removed, 2),
+        Strategy::Swordfish => fish_pattern(grid, eliminations[0].1, &removed, 3),
+        Strategy::Jellyfish => fish_pattern(grid, eliminations[0].1, &removed, 4),
+        Strategy::XyWing => wing_pattern(grid, eliminations[0].1, &removed, false),
+        Strategy::XyzWing => wing_pattern(grid, eliminations[0].1, &removed, true),
+        _ => None,
+    }
+}
+
+/// 一个宫里 `digit` 的位置全部落在另一个宫里
+fn locked_pattern(
+    grid: &[CellState; 81],
+    digit: Digit,
+    removed: &[u8],
+) -> Option<(Vec<u8>, Vec<(u8, Digit)>)> {
+    for house in 0..27 {
+        let cells = house_cells(house);
+        if !removed.iter().all(|cell| cells.contains(cell)) {
+            continue;
+        }
+        let locked: Vec<u8> = cells
+            .iter()
+            .copied()
+            .filter(|cell| !removed.contains(cell) && has_candidate(grid, *cell, digit))
+            .collect();
+        if locked.is_empty() {
+            continue;
+        }
+        for other in 0..27 {
+            // 行列和宫格交叉
+            if (house / 9 == 2) == (other / 9 == 2) {
+                continue;
+            }
+            let other_cells = house_cells(other);
+            let confined = other_cells
+                .iter()
+                .filter(|cell| has_candidate(grid, **cell, digit))
+                .all(|cell| locked.contains(cell));
+            if confined && locked.iter().all(|cell| other_cells.contains(cell)) {
+                let pattern = locked.iter().map(|cell| (*cell, digit)).collect();
+                return Some((other_cells, pattern));
+            }
+        }
+    }
+    None
+}
+
+/// 一个宫里 `size` 个格子只有 `size` 个候选数
+fn naked_pattern(
+    grid: &[CellState; 81],
+    eliminations: &[(u8, Digit)],
+    removed: &[u8],
+    size: usize,
+) -> Option<(Vec<u8>, Vec<(u8, Digit)>)> {
+    let digits = eliminations
+        .iter()
+        .fold(Set::NONE, |digits, (_, digit)| digits | digit.as_set());
+    for house in 0..27 {
+        let cells = house_cells(house);
+        if !removed.iter().all(|cell| cells.contains(cell)) {
+            continue;
+        }
+        let options: Vec<u8> = cells
+            .iter()
+            .copied()
+            .filter(|cell| {
+                let candidates = candidates_of(grid, *cell);
+                !removed.contains(cell)
+                    && !candidates.is_empty()
+                    && candidates.len() as usize <= size
+            })
+            .collect();
+        for subset in combinations(&options, size) {
+            let union = subset
+                .iter()
+                .fold(Set::NONE, |union, cell| union | candidates_of(grid, *cell));
+            if union.len() as usize == size && union & digits == digits {
+                let pattern = subset
+                    .iter()
+                    .flat_map(|cell| {
+                        digits_of(candidates_of(grid, *cell))
+                            .into_iter()
+                            .map(move |digit| (*cell, digit))
+                    })
+                    .collect();
+                return Some((cells, pattern));
+            }
+        }
+    }
+    None
+}
+
+/// 一个宫里 `size` 个数字只能放在同样的 `size` 个格子
+fn hidden_pattern(
+    grid: &[CellState; 81],
+    eliminations: &[(u8, Digit)],
+    removed: &[u8],
+    size: usize,
+) -> Option<(Vec<u8>, Vec<(u8, Digit)>)> {
+    for house in 0..27 {
+        let cells = house_cells(house);
+        if !removed.iter().all(|cell| cells.contains(cell)) {
+            continue;
+        }
+        let options: Vec<u8> = (1..=9)
+            .filter(|&num| {
+                cells
+                    .iter()
+                    .any(|cell| has_candidate(grid, *cell, Digit::new(num)))
+            })
+            .collect();
+        for subset in combinations(&options, size) {
+            let digits = subset
+                .iter()
+                .fold(Set::NONE, |digits, num| digits | Digit::new(*num).as_set());
+            let spots: Vec<u8> = cells
+                .iter()
+                .copied()
+                .filter(|cell| !(candidates_of(grid, *cell) & digits).is_empty())
+                .collect();
+            let matches = spots.len() == size
+                && removed.iter().all(|cell| spots.contains(cell))
+                && eliminations
+                    .iter()
+                    .all(|(_, digit)| !digits.contains(digit.as_set()));
+            if matches {
+                let pattern = spots
+                    .iter()
+                    .flat_map(|cell| {
+                        digits_of(candidates_of(grid, *cell) & digits)
+                            .into_iter()
+                            .map(move |digit| (*cell, digit))
+                    })
+                    .collect();
+                return Some((cells, pattern));
+            }
+        }
+    }
+    None
+}
+
+/// `size` 行里 `digit` 只出现在同样的 `size` 列, 或者反过来
+fn fish_pattern(
+    grid: &[CellState; 81],
+    digit: Digit,
+    removed: &[u8],
+    size: usize,
+) -> Option<(Vec<u8>, Vec<(u8, Digit)>)> {
+    for rows_as_base in [true, false] {
+        let line = |cell: u8| {
+            let position = CellPosition::new(cell.into(), GridSize::Nine);
+            if rows_as_base {
+                position.row()
+            } else {
+                position.col()
+            }
+        };
+        let cross = |cell: u8| {
+            let position = CellPosition::new(cell.into(), GridSize::Nine);
+            if rows_as_base {
+                position.col()
+            } else {
+                position.row()
+            }
+        };
+
+        let options: Vec<u8> = (0..9)
+            .filter(|&index| {
+                let count = (0..81u8)
+                    .filter(|&cell| line(cell) == index && has_candidate(grid, cell, digit))
+                    .count();
+                (1..=size).contains(&count)
+            })
+            .collect();
+        for base in combinations(&options, size) {
+            let base_cells: Vec<u8> = (0..81u8)
+                .filter(|&cell| base.contains(&line(cell)) && has_candidate(grid, cell, digit))
+                .collect();
+            let mut covers: Vec<u8> = base_cells.iter().map(|cell| cross(*cell)).collect();
+            covers.sort();
+            covers.dedup();
+            if covers.len() != size {
+                continue;
+            }
+            let would_remove: Vec<u8> = (0..81u8)
+                .filter(|&cell| {
+                    covers.contains(&cross(cell))
+                        && !base.contains(&line(cell))
+                        && has_candidate(grid, cell, digit)
+                })
+                .collect();
+            if would_remove == removed {
+                let house = (0..81u8)
+                    .filter(|&cell| base.contains(&line(cell)))
+                    .collect();
+                let pattern = base_cells.iter().map(|cell| (*cell, digit)).collect();
+                return Some((house, pattern));
+            }
+        }
+    }
+    None
+}
+
+/// 枢纽格和两个钳子格, 能同时看到两个钳子格 (XYZ-Wing 还要看到枢纽格) 的格子不能是 `digit`
+fn wing_pattern(
+    grid: &[CellState; 81],
+    digit: Digit,
+    removed: &[u8],
+    xyz: bool,
+) -> Option<(Vec<u8>, Vec<(u8, Digit)>)> {
+    let z = digit.as_set();
+    for hinge in 0..81u8 {
+        let hinge_candidates = candidates_of(grid, hinge);
+        let hinge_size = if xyz { 3 } else { 2 };
+        if hinge_candidates.len() as usize != hinge_size || hinge_candidates.contains(z) != xyz {
+            continue;
+        }
+        let pincers: Vec<u8> = (0..81u8)
+            .filter(|&cell| {
+                let candidates = candidates_of(grid, cell);
+                sees(hinge, cell) && candidates.len() == 2 && candidates.contains(z)
+            })
+            .collect();
+        for pair in combinations(&pincers, 2) {
+            let (first, second) = (pair[0], pair[1]);
+            let first_candidates = candidates_of(grid, first);
+            let second_candidates = candidates_of(grid, second);
+            let expected = if xyz {
+                hinge_candidates
+            } else {
+                hinge_candidates | z
+            };
+            let matches = first_candidates | second_candidates == expected
+                && first_candidates & second_candidates == z
+                && removed.iter().all(|&cell| {
+                    sees(cell, first) && sees(cell, second) && (!xyz || sees(cell, hinge))
+                });
+            if matches {
+                let pattern = [hinge, first, second]
+                    .iter()
+                    .flat_map(|cell| {
+                        digits_of(candidates_of(grid, *cell))
+                            .into_iter()
+                            .map(move |digit| (*cell, digit))
+                    })
+                    .collect();
+                return Some((vec![], pattern));
+            }
+        }
+    }
+    None
+}
+
+/// 格子的名字, 例如 "row 3, column 5"
+fn cell_name(cell: u8) -> String {
+    let position = CellPosition::new(cell.into(), GridSize::Nine);
+    format!("row {}, column {}", position.row() + 1, position.col() + 1)
+}
+
+fn find_hint(
+    _trigger: Trigger<FindHint>,
+    q_cell: Query<(
+        Entity,
+        &CellPosition,
+        &DigitValueCell,
+        &ManualCandidates,
+        &AutoCandidates,
+        &CellMode,
+    )>,
+    q_overlay: Query<Entity, Or<(With<HintCell>, With<HintCandidates>)>>,
+    mut sudoku_manager: ResMut<SudokuManager>,
+    mut current_hint: ResMut<CurrentHint>,
+    mut commands: Commands,
+) {
+    if sudoku_manager.is_solved {
+        return;
+    }
+
+    // 解题技巧只认识 9×9 的棋盘
+    if sudoku_manager.board.size() != GridSize::Nine {
+        clear_overlay(&mut commands, &q_overlay);
+        current_hint.0 = Some(Hint {
+            technique: "No Hint",
+            explanation: "Hints are only available on 9×9 boards.".to_string(),
+            cell: None,
+            placement: None,
+            eliminations: vec![],
+            house: vec![],
+            pattern: vec![],
+        });
+        commands.trigger(ShowTechnique(true));
+        return;
+    }
+
+    let mut cells = [PlayerCell {
+        digit: None,
+        candidates: Set::NONE,
+    }; 81];
+    let mut entities = [Entity::PLACEHOLDER; 81];
+    for (entity, position, digit, manual, auto, cell_mode) in q_cell.iter() {
+        let candidates = match cell_mode {
+            CellMode::AutoCandidates => auto.0,
+            _ => manual.0,
+        };
+        cells[position.0 as usize] = PlayerCell {
+            digit: digit.0.map(|digit| Digit::new(digit.get())),
+            candidates: candidates.digits().fold(Set::NONE, |set, digit| {
+                set | Digit::new(digit.get()).as_set()
+            }),
+        };
+        entities[position.0 as usize] = entity;
+    }
+
+    let hint = next_hint(
+        &cells,
+        &sudoku_manager.solution,
+        sudoku_manager.board.rules(),
+    );
+    if hint.is_found() {
+        sudoku_manager.hints_used += 1;
+    }
+    if let Some(cell) = hint.cell {
+        commands.trigger_targets(SelectCell::Only, entities[cell as usize]);
+    }
+
+    clear_overlay(&mut commands, &q_overlay);
+    show_overlay(&mut commands, &entities, &hint);
+    current_hint.0 = Some(hint);
+    commands.trigger(ShowTechnique(true));
+}
+
+/// 在棋盘上标出提示用到的宫、格子和候选数
+fn show_overlay(commands: &mut Commands, entities: &[Entity; 81], hint: &Hint) {
+    let mut cells: [Option<HintCell>; 81] = [None; 81];
+    let mut candidates = [HintCandidates::default(); 81];
+
+    for &cell in hint.house.iter() {
+        cells[cell as usize] = Some(HintCell::House);
+    }
+    for &(cell, digit) in hint.pattern.iter() {
+        cells[cell as usize] = Some(HintCell::Pattern);
+        candidates[cell as usize].pattern ^= player_digit(digit);
+    }
+    for &(cell, digit) in hint.eliminations.iter() {
+        let digit = player_digit(digit);
+        if !candidates[cell as usize].eliminate.contains(digit.as_set()) {
+            candidates[cell as usize].eliminate ^= digit;
+        }
+    }
+    if let Some((cell, digit)) = hint.placement {
+        cells[cell as usize] = Some(HintCell::Pattern);
+        candidates[cell as usize].place = Some(player_digit(digit));
+    }
+
+    for (index, entity) in entities.iter().enumerate() {
+        if let Some(hint_cell) = cells[index] {
+            commands.entity(*entity).insert(hint_cell);
+        }
+        if !candidates[index].is_empty() {
+            commands.entity(*entity).insert(candidates[index]);
+        }
+    }
+}
+
+/// 解题器的数字转成棋盘上的数字
+fn player_digit(digit: Digit) -> PlayerDigit {
+    PlayerDigit::new(digit.get())
+}
+
+fn clear_overlay(
+    commands: &mut Commands,
+    q_overlay: &Query<Entity, Or<(With<HintCell>, With<HintCandidates>)>>,
+) {
+    for entity in q_overlay.iter() {
+        commands
+            .entity(entity)
+            .remove::<HintCell>()
+            .remove::<HintCandidates>();
+    }
+}
+
+fn on_close_technique(
+    trigger: Trigger<ShowTechnique>,
+    q_overlay: Query<Entity, Or<(With<HintCell>, With<HintCandidates>)>>,
+    mut commands: Commands,
+) {
+    if !trigger.event().0 {
+        clear_overlay(&mut commands, &q_overlay);
+    }
+}
+
+fn clear_hint_on_init(
+    _trigger: Trigger<InitPuzzle>,
+    q_overlay: Query<Entity, Or<(With<HintCell>, With<HintCandidates>)>>,
+    mut current_hint: ResMut<CurrentHint>,
+    mut commands: Commands,
+) {
+    current_hint.0 = None;
+    clear_overlay(&mut commands, &q_overlay);
+}
+
+fn on_apply_hint(
+    _trigger: Trigger<ApplyHint>,
+    current_hint: Res<CurrentHint>,
+    q_cell: Query<(Entity, &CellPosition, &CellMode)>,
+    mut sudoku_manager: ResMut<SudokuManager>,
+    mut history: ResMut<MoveHistory>,
+    mut commands: Commands,
+) {
+    let Some(hint) = &current_hint.0 else {
+        return;
+    };
+
+    if let Some((cell, digit)) = hint.placement {
+        if let Some((entity, ..)) = q_cell
+            .iter()
+            .find(|(_, position, _)| position.0 == u16::from(cell))
+        {
+            commands.trigger_targets(NewDigit(player_digit(digit)), vec![entity]);
+        }
+    }
+
+    if hint.eliminations.is_empty() {
+        return;
+    }
+    history.record();
+    let board = &mut sudoku_manager.board;
+    for (_, position, cell_mode) in q_cell.iter() {
+        let cell = position.0;
+        if !hint
+            .eliminations
+            .iter()
+            .any(|(other, _)| u16::from(*other) == cell)
+        {
+            continue;
+        }
+
+        // 手动模式下还没有标记候选数时, 先把自动候选数抄过来
+        if *cell_mode == CellMode::ManualCandidates && board.manual_candidates(cell).is_empty() {
+            board.set_candidates(cell, false, board.auto_candidates(cell));
+        }
+        for (_, digit) in hint
+            .eliminations
+            .iter()
+            .filter(|(other, _)| u16::from(*other) == cell)
+        {
+            board.eliminate(cell, player_digit(*digit));
+        }
+    }
+}
+
+#[cfg(test)]
+fn player_cells(line: &str) -> [PlayerCell; 81] {
+    let mut cells = [PlayerCell {
+        digit: None,
+        candidates: Set::NONE,
+    }; 81];
+    for (cell, ch) in cells.iter_mut().zip(line.chars()) {
+        cell.digit = ch
+            .to_digit(10)
+            .filter(|&num| num > 0)
+            .map(|num| Digit::new(num as u8));
+    }
+    cells
+}
+
+#[cfg(test)]
+const SOLVED: &str =
+    "534678912672195348198342567859761423426853791713924856961537284287419635345286179";
+
+#[test]
+fn test_hint_naked_single() {
+    let solution: Vec<u8> = SOLVED.bytes().map(|byte| byte - b'0').collect();
+    let mut line = SOLVED.to_string();
+    line.replace_range(0..1, ".");
+
+    let hint = next_hint(&player_cells(&line), &solution, &Rules::default());
+    assert_eq!(hint.technique, "Naked Single");
+    assert_eq!(
+        hint.placement.map(|(cell, digit)| (cell, digit.get())),
+        Some((0, 5))
+    );
+}
+
+#[test]
+fn test_hint_mistake() {
+    let solution: Vec<u8> = SOLVED.bytes().map(|byte| byte - b'0').collect();
+    let mut line = SOLVED.to_string();
+    line.replace_range(0..2, "3.");
+
+    let hint = next_hint(&player_cells(&line), &solution, &Rules::default());
+    assert_eq!(hint.technique, "Mistake");
+    assert_eq!(hint.cell, Some(0));
+    assert!(hint.is_found());
+
+    let hint = next_hint(&player_cells(SOLVED), &solution, &Rules::default());
+    assert_eq!(hint.technique, "No Hint");
+    assert!(!hint.is_found());
+}
+
+#[test]
+fn test_combinations() {
+    assert_eq!(
+        combinations(&[1, 2, 3], 2),
+        vec![vec![1, 2], vec![1, 3], vec![2, 3]]
+    );
+    assert_eq!(combinations(&[1, 2], 3), Vec::<Vec<u8>>::new());
+    assert_eq!(house_cells(0), (0..9).collect::<Vec<u8>>());
+    assert_eq!(
+        house_cells(9),
+        (0..9).map(|row| row * 9).collect::<Vec<u8>>()
+    );
+}
+
+/// 只有列出的格子是空格, 其他格子都已经填好
+#[cfg(test)]
+fn candidate_grid(cells: &[(u8, &[u8])]) -> [CellState; 81] {
+    let mut grid = [CellState::Digit(Digit::new(1)); 81];
+    for &(cell, nums) in cells {
+        grid[cell as usize] = CellState::Candidates(
+            nums.iter()
+                .fold(Set::NONE, |set, &num| set | Digit::new(num).as_set()),
+        );
+    }
+    grid
+}
+
+#[cfg(test)]
+fn pairs(pairs: &[(u8, u8)]) -> Vec<(u8, Digit)> {
+    pairs
+        .iter()
+        .map(|&(cell, num)| (cell, Digit::new(num)))
+        .collect()
+}
+
+#[test]
+fn test_pattern_naked_pair() {
+    // 第一行前两格只能是 1 和 2, 同一行的其他格子不能再有 1 和 2
+    let grid = candidate_grid(&[(0, &[1, 2]), (1, &[1, 2]), (2, &[1, 2, 3]), (3, &[2, 4])]);
+    let eliminations = pairs(&[(2, 1), (2, 2), (3, 2)]);
+    let (house, pattern) =
+        elimination_pattern(&Strategy::NakedPairs, &grid, &eliminations).unwrap();
+    assert_eq!(house, (0..9).collect::<Vec<u8>>());
+    assert_eq!(pattern, pairs(&[(0, 1), (0, 2), (1, 1), (1, 2)]));
+}
+
+#[test]
+fn test_pattern_hidden_pair() {
+    // 第一行的 8 和 9 只能放在前两格, 这两格的其他候选数可以删掉
+    let grid = candidate_grid(&[(0, &[1, 8, 9]), (1, &[2, 8, 9]), (2, &[1, 2])]);
+    let eliminations = pairs(&[(0, 1), (1, 2)]);
+    let (house, pattern) =
+        elimination_pattern(&Strategy::HiddenPairs, &grid, &eliminations).unwrap();
+    assert_eq!(house, (0..9).collect::<Vec<u8>>());
+    assert_eq!(pattern, pairs(&[(0, 8), (0, 9), (1, 8), (1, 9)]));
+}
+
+#[test]
+fn test_pattern_pointing_pair() {
+    // 左上宫的 5 只在第一行, 第一行其他宫的 5 可以删掉
+    let grid = candidate_grid(&[(0, &[4, 5]), (1, &[5, 6]), (5, &[5, 7]), (7, &[3, 5])]);
+    let eliminations = pairs(&[(5, 5), (7, 5)]);
+    let (house, pattern) =
+        elimination_pattern(&Strategy::LockedCandidates, &grid, &eliminations).unwrap();
+    assert_eq!(house, vec![0, 1, 2, 9, 10, 11, 18, 19, 20]);
+    assert_eq!(pattern, pairs(&[(0, 5), (1, 5)]));
+}
+
+#[test]
+fn test_pattern_x_wing() {
+    // 第 2 行和第 5 行的 7 都只在第 3 列和第 7 列
+    let grid = candidate_grid(&[
+        (11, &[1, 7]),
+        (15, &[2, 7]),
+        (24, &[3, 7]),
+        (38, &[4, 7]),
+        (42, &[5, 7]),
+        (65, &[6, 7]),
+    ]);
+    let eliminations = pairs(&[(24, 7), (65, 7)]);
+    let (house, pattern) = elimination_pattern(&Strategy::XWing, &grid, &eliminations).unwrap();
+    assert_eq!(house, (9..18).chain(36..45).collect::<Vec<u8>>());
+    assert_eq!(pattern, pairs(&[(11, 7), (15, 7), (38, 7), (42, 7)]));
+}
+
+#[test]
+fn test_pattern_xy_wing() {
+    // 枢纽格 1/2, 钳子格 1/3 和 2/3, 同时看到两个钳子格的格子不能是 3
+    let grid = candidate_grid(&[(0, &[1, 2]), (4, &[1, 3]), (36, &[2, 3]), (40, &[3, 5])]);
+    let eliminations = pairs(&[(40, 3)]);
+    let (house, pattern) = elimination_pattern(&Strategy::XyWing, &grid, &eliminations).unwrap();
+    assert!(house.is_empty());
+    assert_eq!(
+        pattern,
+        pairs(&[(0, 1), (0, 2), (4, 1), (4, 3), (36, 2), (36, 3)])
+    );
+    assert!(elimination_pattern(&Strategy::XyzWing, &grid, &eliminations).is_none());
+}
@@ -0,0 +1,396 @@
+use crate::game::{
+    cell_state::{CellMode, CorrectionCell, RevealedCell},
+    model::Board,
+    position::CellPosition,
+    AutoCandidateMode, CleanCell, ClearColors, InitPuzzle, NewCandidate, NewCandidateColor,
+    NewCenterMark, NewColor, NewDigit, ResetPuzzle, RevealCell, SudokuManager,
+};
+use crate::GameState;
+use bevy::prelude::*;
+use std::collections::VecDeque;
+
+/// 最多能撤销的步数, 每一步都存着整个棋盘, 超过后丢掉最早的一步
+const MAX_UNDO: usize = 200;
+
+pub(crate) fn plugin(app: &mut App) {
+    app.init_resource::<MoveHistory>()
+        .add_systems(
+            Last,
+            sync_history
+                .run_if(in_state(GameState::Playing))
+                .run_if(snapshot_changed),
+        )
+        .add_observer(record_move::<NewDigit>)
+        .add_observer(record_move::<NewCandidate>)
+        .add_observer(record_move::<NewCenterMark>)
+        .add_observer(record_move::<NewColor>)
+        .add_observer(record_move::<NewCandidateColor>)
+        .add_observer(record_move::<ClearColors>)
+        .add_observer(record_move::<CleanCell>)
+        .add_observer(record_move::<RevealCell>)
+        .add_observer(clear_history::<InitPuzzle>)
+        .add_observer(clear_history::<ResetPuzzle>)
+        .add_observer(on_undo)
+        .add_observer(on_redo);
+}
+
+#[derive(Event)]
+pub struct Undo;
+
+#[derive(Event)]
+pub struct Redo;
+
+/// 单个格子界面状态的快照, 数字和候选数在棋盘模型里
+#[derive(Clone, PartialEq)]
+struct CellSnapshot {
+    entity: Entity,
+    cell_mode: CellMode,
+    correction: bool,
+    revealed: bool,
+}
+
+/// 整个棋盘的快照
+#[derive(Clone, PartialEq)]
+struct BoardSnapshot {
+    board: Board,
+    cells: Vec<CellSnapshot>,
+    auto_mode: bool,
+}
+
+/// 操作历史
+///
+/// 每一步操作之前的棋盘快照放进撤销栈, 撤销时把当前棋盘放进重做栈
+#[derive(Resource, Default)]
+pub struct MoveHistory {
+    undo: VecDeque<BoardSnapshot>,
+    redo: Vec<BoardSnapshot>,
+    /// 上一帧结束时的棋盘
+    current: Option<BoardSnapshot>,
+    /// 本帧已经记录过一步操作
+    pending: bool,
+}
+
+impl MoveHistory {
+    /// 在修改棋盘之前调用, 同一帧内的多次修改合并成一步
+    pub fn record(&mut self) {
+        if self.pending {
+            return;
+        }
+        if let Some(current) = self.current.clone() {
+            self.push_undo(current);
+            self.pending = true;
+        }
+    }
+
+    fn push_undo(&mut self, snapshot: BoardSnapshot) {
+        if self.undo.len() == MAX_UNDO {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(snapshot);
+    }
+
+    fn clear(&mut self) {
+        *self = MoveHistory::default();
+    }
+}
+
+fn record_move<E: Event>(_trigger: Trigger<E>, mut history: ResMut<MoveHistory>) {
+    history.record();
+}
+
+fn clear_history<E: Event>(_trigger: Trigger<E>, mut history: ResMut<MoveHistory>) {
+    history.clear();
+}
+
+/// 这一帧记录了一步操作, 或者棋盘、自动候选模式、格子的界面状态有变化
+fn snapshot_changed(
+    history: Res<MoveHistory>,
+    sudoku_manager: Option<Res<SudokuManager>>,
+    auto_mode: Res<AutoCandidateMode>,
+    q_cell: Query<
+        (),
+        Or<(
+            Changed<CellMode>,
+            Changed<CorrectionCell>,
+            Changed<RevealedCell>,
+        )>,
+    >,
+    removed_correction: RemovedComponents<CorrectionCell>,
+    removed_revealed: RemovedComponents<RevealedCell>,
+) -> bool {
+    history.pending
+        || history.current.is_none()
+        || sudoku_manager.is_some_and(|sudoku_manager| sudoku_manager.is_changed())
+        || auto_mode.is_changed()
+        || !q_cell.is_empty()
+        || !removed_correction.is_empty()
+        || !removed_revealed.is_empty()
+}
+
+/// 有变化的帧结束时同步当前棋盘, 没有变化时不用复制棋盘
+fn sync_history(
+    mut history: ResMut<MoveHistory>,
+    q_cell: Query<(
+        Entity,
+        &CellPosition,
+        &CellMode,
+        Has<CorrectionCell>,
+        Has<RevealedCell>,
+    )>,
+    sudoku_manager: Option<Res<SudokuManager>>,
+    auto_mode: Res<AutoCandidateMode>,
+) {
+    let Some(sudoku_manager) = sudoku_manager else {
+        return;
+    };
+    let cells = q_cell
+        .iter()
+        .sort_by::<&CellPosition>(|t1, t2| t1.0.cmp(&t2.0))
+        .map(
+            |(entity, _, cell_mode, correction, revealed)| CellSnapshot {
+                entity,
+                cell_mode: *cell_mode,
+                correction,
+                revealed,
+            },
+        )
+        .collect();
+    let snapshot = BoardSnapshot {
+        board: sudoku_manager.board.clone(),
+        cells,
+        auto_mode: auto_mode.0,
+    };
+
+    let pending = history.pending;
+    history.pending = false;
+    if history.current.as_ref() != Some(&snapshot) {
+        if pending {
+            history.redo.clear();
+        }
+        history.current = Some(snapshot);
+    } else if pending {
+        // 这一步什么也没改变
+        history.undo.pop_back();
+    }
+}
+
+fn on_undo(
+    _trigger: Trigger<Undo>,
+    mut history: ResMut<MoveHistory>,
+    sudoku_manager: ResMut<SudokuManager>,
+    q_cell: Query<CellQuery>,
+    auto_mode: ResMut<AutoCandidateMode>,
+    commands: Commands,
+) {
+    if sudoku_manager.is_solved {
+        return;
+    }
+    let Some(current) = history.current.clone() else {
+        return;
+    };
+    let Some(previous) = history.undo.pop_back() else {
+        return;
+    };
+    history.redo.push(current);
+    restore(&previous, sudoku_manager, q_cell, auto_mode, commands);
+    history.current = Some(previous);
+}
+
+fn on_redo(
+    _trigger: Trigger<Redo>,
+    mut history: ResMut<MoveHistory>,
+    sudoku_manager: ResMut<SudokuManager>,
+    q_cell: Query<CellQuery>,
+    auto_mode: ResMut<AutoCandidateMode>,
+    commands: Commands,
+) {
+    if sudoku_manager.is_solved {
+        return;
+    }
+    let Some(current) = history.current.clone() else {
+        return;
+    };
+    let Some(next) = history.redo.pop() else {
+        return;
+    };
+    history.push_undo(current);
+    restore(&next, sudoku_manager, q_cell, auto_mode, commands);
+    history.current = Some(next);
+}
+
+type CellQuery = (
+    &'static mut CellMode,
+    Has<CorrectionCell>,
+    Has<RevealedCell>,
+);
+
+/// 把棋盘恢复到快照的状态
+fn restore(
+    snapshot: &BoardSnapshot,
+    mut sudoku_manager: ResMut<SudokuManager>,
+    mut q_cell: Query<CellQuery>,
+    mut auto_mode: ResMut<AutoCandidateMode>,
+    mut commands: Commands,
+) {
+    sudoku_manager.board = snapshot.board.clone();
+
+    for cell in snapshot.cells.iter() {
+        let Ok((mut cell_mode, has_correction, has_revealed)) = q_cell.get_mut(cell.entity) else {
+            continue;
+        };
+        cell_mode.set_if_neq(cell.cell_mode);
+
+        if cell.correction && !has_correction {
+            commands.entity(cell.entity).insert(CorrectionCell);
+        } else if !cell.correction && has_correction {
+            commands.entity(cell.entity).remove::<CorrectionCell>();
+        }
+
+        if cell.revealed && !has_revealed {
+            commands.entity(cell.entity).insert(RevealedCell);
+        } else if !cell.revealed && has_revealed {
+            commands.entity(cell.entity).remove::<RevealedCell>();
+        }
+    }
+
+    if auto_mode.0 != snapshot.auto_mode {
+        auto_mode.0 = snapshot.auto_mode;
+    }
+}
+
+#[cfg(test)]
+const SOLVED: &str =
+    "534678912672195348198342567859761423426853791713924856961537284287419635345286179";
+
+/// 只带数字输入、检查、撤销和同步系统的最小 App, 返回 App 和按位置排列的格子
+#[cfg(test)]
+fn test_app(board: Board) -> (App, Vec<Entity>) {
+    use crate::game::{
+        cell_state::{CellValueBundle, FixedCell},
+        on_check_cell, on_new_digit, sync_board, Settings,
+    };
+
+    let mut app = App::new();
+    app.init_resource::<MoveHistory>()
+        .init_resource::<AutoCandidateMode>()
+        .insert_resource(Settings {
+            check_guesses_when_entered: true,
+            ..default()
+        })
+        .add_systems(
+            PostUpdate,
+            sync_board.run_if(resource_changed::<SudokuManager>),
+        )
+        .add_systems(Last, sync_history.run_if(snapshot_changed))
+        .add_observer(record_move::<NewDigit>)
+        .add_observer(on_new_digit)
+        .add_observer(on_check_cell)
+        .add_observer(on_undo)
+        .add_observer(on_redo);
+
+    let size = board.size();
+    let cells = size
+        .cells()
+        .map(|cell| {
+            let mut entity = app.world_mut().spawn((
+                CellPosition::new(cell, size),
+                CellValueBundle::from_board(&board, cell, true),
+            ));
+            if board.is_given(cell) {
+                entity.insert(FixedCell);
+            }
+            entity.id()
+        })
+        .collect();
+    app.insert_resource(SudokuManager {
+        solution: SOLVED.bytes().map(|byte| byte - b'0').collect(),
+        board,
+        is_solved: false,
+        hints_used: 0,
+        revealed_cells: 0,
+        fully_revealed: false,
+    });
+    app.update();
+    (app, cells)
+}
+
+#[cfg(test)]
+fn board_without(cells: &[usize]) -> Board {
+    let mut line = SOLVED.to_string();
+    for &cell in cells {
+        line.replace_range(cell..cell + 1, ".");
+    }
+    Board::new(sudoku::Sudoku::from_str_line(&line).unwrap())
+}
+
+#[test]
+fn test_undo_conflicting_digit() {
+    use crate::game::{
+        cell_state::{AutoCandidates, ConflictCell, ManualCandidates},
+        grid::Digit,
+    };
+
+    let mut board = board_without(&[0, 1]);
+    board.toggle_candidate(1, Digit::new(3), false);
+    let original = board.clone();
+    let (mut app, cells) = test_app(board);
+
+    // 第 0 格应该是 5, 填 3 和第一列最下面的 3 冲突, 还会踢掉第 1 格的候选数 3
+    app.world_mut()
+        .trigger_targets(NewDigit::new(3), vec![cells[0]]);
+    app.update();
+    let world = app.world();
+    assert!(world.get::<ConflictCell>(cells[0]).is_some());
+    assert!(world.get::<ConflictCell>(cells[72]).is_some());
+    assert!(world.get::<CorrectionCell>(cells[0]).is_some());
+    assert!(world.get::<AutoCandidates>(cells[1]).unwrap().0.is_empty());
+    assert!(world
+        .get::<ManualCandidates>(cells[1])
+        .unwrap()
+        .0
+        .is_empty());
+
+    app.world_mut().trigger(Undo);
+    app.update();
+    let world = app.world();
+    assert_eq!(world.resource::<SudokuManager>().board, original);
+    assert!(world.get::<ConflictCell>(cells[0]).is_none());
+    assert!(world.get::<ConflictCell>(cells[72]).is_none());
+    assert!(world.get::<CorrectionCell>(cells[0]).is_none());
+    assert_eq!(
+        *world.get::<CellMode>(cells[0]).unwrap(),
+        CellMode::AutoCandidates
+    );
+    assert_eq!(
+        world.get::<AutoCandidates>(cells[1]).unwrap().0,
+        Digit::new(3).as_set()
+    );
+    assert_eq!(
+        world.get::<ManualCandidates>(cells[1]).unwrap().0,
+        Digit::new(3).as_set()
+    );
+
+    // 重做回到填错的状态
+    app.world_mut().trigger(Redo);
+    app.update();
+    let world = app.world();
+    assert!(world.get::<ConflictCell>(cells[0]).is_some());
+    assert!(world.get::<CorrectionCell>(cells[0]).is_some());
+}
+
+#[test]
+fn test_undo_limit() {
+    let mut history = MoveHistory {
+        current: Some(BoardSnapshot {
+            board: board_without(&[0]),
+            cells: Vec::new(),
+            auto_mode: false,
+        }),
+        ..default()
+    };
+    for _ in 0..MAX_UNDO + 10 {
+        history.record();
+        history.pending = false;
+    }
+    assert_eq!(history.undo.len(), MAX_UNDO);
+}
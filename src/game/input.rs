@@ -1,77 +1,117 @@
 use crate::game::{
-    control_tab::ToggleTab, CleanCell, MoveSelectCell, NewCandidate, NewDigit, SelectedCell,
+    control_tab::{ControlTab, SelectedTab, ToggleTab},
+    history::{Redo, Undo},
+    model::PALETTE_SIZE,
+    position::CellPosition,
+    CleanCell, MoveSelectCell, NewCandidate, NewCenterMark, NewColor, NewDigit, SelectedCell,
 };
 use bevy::prelude::*;
 
+/// 每个数字对应的按键, 16×16 的 10 到 16 用字母键 A 到 G
+const DIGIT_KEYS: [&[KeyCode]; 16] = [
+    &[KeyCode::Digit1, KeyCode::Numpad1],
+    &[KeyCode::Digit2, KeyCode::Numpad2],
+    &[KeyCode::Digit3, KeyCode::Numpad3],
+    &[KeyCode::Digit4, KeyCode::Numpad4],
+    &[KeyCode::Digit5, KeyCode::Numpad5],
+    &[KeyCode::Digit6, KeyCode::Numpad6],
+    &[KeyCode::Digit7, KeyCode::Numpad7],
+    &[KeyCode::Digit8, KeyCode::Numpad8],
+    &[KeyCode::Digit9, KeyCode::Numpad9],
+    &[KeyCode::KeyA],
+    &[KeyCode::KeyB],
+    &[KeyCode::KeyC],
+    &[KeyCode::KeyD],
+    &[KeyCode::KeyE],
+    &[KeyCode::KeyF],
+    &[KeyCode::KeyG],
+];
+
+/// 数字键按选中的标签输入, 按住 Alt 输入候选数, 按住 Ctrl 输入中心标记
 pub(crate) fn keyboard_input(
     mut commands: Commands,
     keyboard_input: Res<ButtonInput<KeyCode>>,
-    q_selected: Query<Entity, With<SelectedCell>>,
+    q_selected: Query<(Entity, &CellPosition), With<SelectedCell>>,
+    selected_tab: Res<SelectedTab>,
 ) {
-    let q_selected = match q_selected.iter().next() {
-        Some(e) => e,
-        None => return,
+    let Some((_, cell_position)) = q_selected.iter().next() else {
+        return;
     };
+    // 选中多个格子时一起修改, 同一帧里的修改撤销时是一步
+    let selected: Vec<Entity> = q_selected.iter().map(|(entity, _)| entity).collect();
 
     if keyboard_input.just_pressed(KeyCode::Delete) {
-        commands.trigger_targets(CleanCell, vec![q_selected]);
+        commands.trigger_targets(CleanCell, selected);
         return;
     }
 
     if keyboard_input.just_pressed(KeyCode::Space) {
-        commands.trigger(ToggleTab);
+        commands.trigger(ToggleTab::Next);
         return;
     }
-    if keyboard_input.any_just_pressed([KeyCode::AltLeft, KeyCode::AltRight]) {
-        commands.trigger(ToggleTab);
+    let alt_keys = [KeyCode::AltLeft, KeyCode::AltRight];
+    let ctrl_keys = [KeyCode::ControlLeft, KeyCode::ControlRight];
+    if keyboard_input.any_just_pressed(alt_keys) {
+        commands.trigger(ToggleTab::Hold(ControlTab::Candidate));
         return;
     }
-
-    if keyboard_input.any_just_released([KeyCode::AltLeft, KeyCode::AltRight]) {
-        commands.trigger(ToggleTab);
+    if keyboard_input.any_just_pressed(ctrl_keys) {
+        commands.trigger(ToggleTab::Hold(ControlTab::Center));
         return;
     }
 
-    let press_1 = keyboard_input.any_just_pressed([KeyCode::Digit1, KeyCode::Numpad1]);
-    let press_2 = keyboard_input.any_just_pressed([KeyCode::Digit2, KeyCode::Numpad2]);
-    let press_3 = keyboard_input.any_just_pressed([KeyCode::Digit3, KeyCode::Numpad3]);
-    let press_4 = keyboard_input.any_just_pressed([KeyCode::Digit4, KeyCode::Numpad4]);
-    let press_5 = keyboard_input.any_just_pressed([KeyCode::Digit5, KeyCode::Numpad5]);
-    let press_6 = keyboard_input.any_just_pressed([KeyCode::Digit6, KeyCode::Numpad6]);
-    let press_7 = keyboard_input.any_just_pressed([KeyCode::Digit7, KeyCode::Numpad7]);
-    let press_8 = keyboard_input.any_just_pressed([KeyCode::Digit8, KeyCode::Numpad8]);
-    let press_9 = keyboard_input.any_just_pressed([KeyCode::Digit9, KeyCode::Numpad9]);
-
-    let alt = keyboard_input.any_pressed([KeyCode::AltLeft, KeyCode::AltRight]);
+    if keyboard_input.any_just_released(alt_keys.into_iter().chain(ctrl_keys)) {
+        commands.trigger(ToggleTab::Release);
+        return;
+    }
 
-    let num = if press_1 {
-        Some(1)
-    } else if press_2 {
-        Some(2)
-    } else if press_3 {
-        Some(3)
-    } else if press_4 {
-        Some(4)
-    } else if press_5 {
-        Some(5)
-    } else if press_6 {
-        Some(6)
-    } else if press_7 {
-        Some(7)
-    } else if press_8 {
-        Some(8)
-    } else if press_9 {
-        Some(9)
+    let tab = if keyboard_input.any_pressed(alt_keys) {
+        ControlTab::Candidate
+    } else if keyboard_input.any_pressed(ctrl_keys) {
+        ControlTab::Center
     } else {
-        None
+        selected_tab.0
     };
 
+    // 数字不能超过棋盘的边长, 武士数独是 9
+    let digit_count = cell_position.size().digit_count() as usize;
+    let num = DIGIT_KEYS[..digit_count]
+        .iter()
+        .position(|keys| keyboard_input.any_just_pressed(keys.iter().copied()))
+        .map(|index| index as u8 + 1);
+
     if let Some(num) = num {
-        if alt {
-            commands.trigger_targets(NewCandidate::new(num), vec![q_selected]);
+        match tab {
+            ControlTab::Normal => commands.trigger_targets(NewDigit::new(num), selected),
+            ControlTab::Candidate => commands.trigger(NewCandidate::new(num)),
+            ControlTab::Center => commands.trigger(NewCenterMark::new(num)),
+            ControlTab::Color if num <= PALETTE_SIZE => commands.trigger(NewColor(num - 1)),
+            ControlTab::Color => {}
+        }
+    }
+}
+
+/// Ctrl+Z 撤销, Ctrl+Shift+Z / Ctrl+Y 重做
+pub(crate) fn keyboard_history(mut commands: Commands, keyboard_input: Res<ButtonInput<KeyCode>>) {
+    let ctrl = keyboard_input.any_pressed([
+        KeyCode::ControlLeft,
+        KeyCode::ControlRight,
+        KeyCode::SuperLeft,
+        KeyCode::SuperRight,
+    ]);
+    if !ctrl {
+        return;
+    }
+
+    let shift = keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    if keyboard_input.just_pressed(KeyCode::KeyZ) {
+        if shift {
+            commands.trigger(Redo);
         } else {
-            commands.trigger_targets(NewDigit::new(num), vec![q_selected]);
+            commands.trigger(Undo);
         }
+    } else if keyboard_input.just_pressed(KeyCode::KeyY) {
+        commands.trigger(Redo);
     }
 }
 
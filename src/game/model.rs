@@ -0,0 +1,1329 @@
+use crate::game::{
+    grid::{Digit, DigitSet, GridSize},
+    position::CellPosition,
+};
+use serde::{Deserialize, Serialize};
+use std::{cmp::Ordering, fmt::Display};
+use sudoku::Sudoku;
+
+/// 经典数独每个格子所在的 3×3 宫
+#[rustfmt::skip]
+pub const CLASSIC_REGIONS: [u8; 81] = [
+    0, 0, 0, 1, 1, 1, 2, 2, 2,
+    0, 0, 0, 1, 1, 1, 2, 2, 2,
+    0, 0, 0, 1, 1, 1, 2, 2, 2,
+    3, 3, 3, 4, 4, 4, 5, 5, 5,
+    3, 3, 3, 4, 4, 4, 5, 5, 5,
+    3, 3, 3, 4, 4, 4, 5, 5, 5,
+    6, 6, 6, 7, 7, 7, 8, 8, 8,
+    6, 6, 6, 7, 7, 7, 8, 8, 8,
+    6, 6, 6, 7, 7, 7, 8, 8, 8,
+];
+
+/// 杀手数独的笼子: 笼子里的数字不能重复, 加起来等于 `sum`
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cage {
+    pub cells: Vec<u16>,
+    pub sum: u8,
+}
+
+/// 可以叠加在经典规则上的额外约束
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Overlay {
+    /// 两条主对角线上的数字也不能重复
+    Diagonals,
+    /// 四个额外的 3×3 宫, 只有 9×9 的棋盘有
+    Windoku,
+    /// 隔一个马步的两个格子数字不同
+    AntiKnight,
+    /// 相邻的两个格子数字不同, 斜着相邻也算
+    AntiKing,
+}
+
+impl Overlay {
+    pub const ALL: [Overlay; 4] = [
+        Overlay::Diagonals,
+        Overlay::Windoku,
+        Overlay::AntiKnight,
+        Overlay::AntiKing,
+    ];
+
+    /// 这个约束下, 两个不同的格子不能填相同的数字
+    pub fn sees(self, size: GridSize, a: u16, b: u16) -> bool {
+        let side = size.side() as u16;
+        let (row_a, col_a, row_b, col_b) = (a / side, a % side, b / side, b % side);
+        let (rows, cols) = (row_a.abs_diff(row_b), col_a.abs_diff(col_b));
+        match self {
+            Overlay::Diagonals => {
+                (row_a == col_a && row_b == col_b)
+                    || (row_a + col_a == side - 1 && row_b + col_b == side - 1)
+            }
+            Overlay::Windoku => {
+                size == GridSize::Nine
+                    && windoku_box(a).is_some()
+                    && windoku_box(a) == windoku_box(b)
+            }
+            Overlay::AntiKnight => (rows, cols) == (1, 2) || (rows, cols) == (2, 1),
+            Overlay::AntiKing => rows <= 1 && cols <= 1,
+        }
+    }
+}
+
+impl Display for Overlay {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Overlay::Diagonals => write!(f, "Diagonal"),
+            Overlay::Windoku => write!(f, "Windoku"),
+            Overlay::AntiKnight => write!(f, "Anti-Knight"),
+            Overlay::AntiKing => write!(f, "Anti-King"),
+        }
+    }
+}
+
+/// 9×9 棋盘上格子所在的 Windoku 额外宫: 第 2-4 行或第 6-8 行, 同时在第 2-4 列或第 6-8 列
+pub fn windoku_box(cell: u16) -> Option<u16> {
+    let window = |line: u16| match line {
+        1..=3 => Some(0),
+        5..=7 => Some(1),
+        _ => None,
+    };
+    Some(window(cell / 9)? * 2 + window(cell % 9)?)
+}
+
+/// 一组额外约束, 第 n 位表示 `Overlay::ALL[n]`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Overlays(u8);
+
+impl Overlays {
+    pub const NONE: Overlays = Overlays(0);
+
+    pub fn contains(self, overlay: Overlay) -> bool {
+        self.0 & (1 << overlay as u8) != 0
+    }
+
+    pub fn toggle(&mut self, overlay: Overlay) {
+        self.0 ^= 1 << overlay as u8;
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn iter(self) -> impl Iterator<Item = Overlay> {
+        Overlay::ALL
+            .into_iter()
+            .filter(move |&overlay| self.contains(overlay))
+    }
+
+    /// 这些约束能同时满足
+    ///
+    /// 马步和王步一起用时只剩四种基本的解, 都不满足对角线和 Windoku;
+    /// 对角线, Windoku 和马步也没有共同的解
+    pub fn is_solvable(self) -> bool {
+        let all = |overlays: &[Overlay]| overlays.iter().all(|&overlay| self.contains(overlay));
+        !(all(&[Overlay::AntiKnight, Overlay::AntiKing])
+            && (self.contains(Overlay::Diagonals) || self.contains(Overlay::Windoku)))
+            && !all(&[Overlay::Diagonals, Overlay::Windoku, Overlay::AntiKnight])
+    }
+
+    /// 任何一个约束下两个格子不能填相同的数字
+    pub fn sees(self, size: GridSize, a: u16, b: u16) -> bool {
+        self.iter().any(|overlay| overlay.sees(size, a, b))
+    }
+}
+
+impl Display for Overlays {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names: Vec<String> = self.iter().map(|overlay| overlay.to_string()).collect();
+        write!(f, "{}", names.join(" + "))
+    }
+}
+
+/// 线条约束的种类
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum LineKind {
+    /// 温度计: 从灯泡开始数字严格递增
+    Thermometer,
+    /// 箭头: 箭身上的数字加起来等于圆圈里的数字, 箭身上的数字可以重复
+    Arrow,
+}
+
+/// 画在棋盘上的线条约束
+///
+/// `path` 从温度计的灯泡或者箭头的圆圈开始, 前后两个格子上下左右或者斜着相邻
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Line {
+    pub kind: LineKind,
+    pub path: Vec<CellPosition>,
+}
+
+impl Line {
+    pub fn contains(&self, cell: u16) -> bool {
+        self.path.iter().any(|position| position.0 == cell)
+    }
+
+    /// 线上的格子都在这个大小的棋盘上, 首尾相连并且不重复, 温度计不比棋盘的边长
+    fn is_valid(&self, size: GridSize) -> bool {
+        self.path.len() >= 2
+            && (self.kind == LineKind::Arrow || self.path.len() <= size.side() as usize)
+            && self.path.iter().all(|position| {
+                position.size() == size && (position.0 as usize) < size.cell_count()
+            })
+            && self.path.windows(2).all(|pair| {
+                let rows = pair[0].row().abs_diff(pair[1].row());
+                let cols = pair[0].col().abs_diff(pair[1].col());
+                rows <= 1 && cols <= 1 && rows + cols > 0
+            })
+            && self
+                .path
+                .iter()
+                .enumerate()
+                .all(|(index, position)| !self.path[index + 1..].contains(position))
+    }
+
+    /// 其他格子的数字不变时, `cell` 填 `num` 能不能满足这条线
+    ///
+    /// `value` 给出格子现在的数字, 0 表示空格. 温度计上相隔 n 格的两个数字至少差 n,
+    /// 箭身上的每个空格至少是 1, 圆圈空着时最大是棋盘的边长
+    pub fn allows(&self, side: u8, cell: u16, num: u8, value: impl Fn(u16) -> u8) -> bool {
+        let Some(index) = self.path.iter().position(|position| position.0 == cell) else {
+            return true;
+        };
+        let value = |position: &CellPosition| {
+            if position.0 == cell {
+                num
+            } else {
+                value(position.0)
+            }
+        };
+        match self.kind {
+            LineKind::Thermometer => {
+                let above = self.path.len() - 1 - index;
+                num as usize > index
+                    && num as usize + above <= side as usize
+                    && self.path.iter().enumerate().all(|(other, position)| {
+                        thermometer_order(index, num, other, value(position))
+                    })
+            }
+            LineKind::Arrow => {
+                let circle = value(&self.path[0]);
+                let (sum, empty) = self.path[1..]
+                    .iter()
+                    .fold((0, 0), |(sum, empty), position| match value(position) {
+                        0 => (sum, empty + 1),
+                        num => (sum + num as usize, empty),
+                    });
+                match circle {
+                    0 => sum + empty <= side as usize,
+                    circle => {
+                        sum + empty <= circle as usize && (empty > 0 || sum == circle as usize)
+                    }
+                }
+            }
+        }
+    }
+
+    /// 和 `cell` 的数字一起违反这条线的格子, 包括 `cell` 自己
+    ///
+    /// 温度计只算和它顺序不对的格子, 箭头的和不对时箭头上填了数字的格子都算
+    pub fn conflicts_of(&self, side: u8, cell: u16, value: impl Fn(u16) -> u8) -> Vec<u16> {
+        let num = value(cell);
+        let Some(index) = self.path.iter().position(|position| position.0 == cell) else {
+            return vec![];
+        };
+        if num == 0 {
+            return vec![];
+        }
+        let mut conflicts: Vec<u16> = match self.kind {
+            LineKind::Thermometer => self
+                .path
+                .iter()
+                .enumerate()
+                .filter(|&(other, position)| {
+                    !thermometer_order(index, num, other, value(position.0))
+                })
+                .map(|(_, position)| position.0)
+                .collect(),
+            LineKind::Arrow if self.allows(side, cell, num, &value) => vec![],
+            LineKind::Arrow => self
+                .path
+                .iter()
+                .map(|position| position.0)
+                .filter(|&other| value(other) != 0)
+                .collect(),
+        };
+        if !conflicts.is_empty() && !conflicts.contains(&cell) {
+            conflicts.push(cell);
+        }
+        conflicts
+    }
+}
+
+/// 温度计第 `index` 格的 `num` 和第 `other` 格的 `value` 顺序正确, 0 表示空格
+fn thermometer_order(index: usize, num: u8, other: usize, value: u8) -> bool {
+    let (num, value) = (num as usize, value as usize);
+    value == 0
+        || match other.cmp(&index) {
+            Ordering::Less => value + (index - other) <= num,
+            Ordering::Equal => true,
+            Ordering::Greater => num + (other - index) <= value,
+        }
+}
+
+/// 相邻两个格子之间边上的标记
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum EdgeKind {
+    /// 白点: 两个数字相差 1
+    White,
+    /// 黑点: 一个数字是另一个的两倍
+    Black,
+    /// X: 两个数字加起来是 10
+    X,
+    /// V: 两个数字加起来是 5
+    V,
+}
+
+impl EdgeKind {
+    /// 这对数字满足标记
+    pub fn holds(self, a: u8, b: u8) -> bool {
+        match self {
+            EdgeKind::White => a.abs_diff(b) == 1,
+            EdgeKind::Black => a == b * 2 || b == a * 2,
+            EdgeKind::X => a + b == 10,
+            EdgeKind::V => a + b == 5,
+        }
+    }
+}
+
+/// 画在两个上下或者左右相邻的格子之间的标记, `cells` 从小到大排列
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Edge {
+    pub kind: EdgeKind,
+    pub cells: [CellPosition; 2],
+}
+
+impl Edge {
+    fn is_valid(&self, size: GridSize) -> bool {
+        let [a, b] = self.cells;
+        a.size() == size
+            && b.size() == size
+            && a.0 < b.0
+            && (b.0 as usize) < size.cell_count()
+            && orthogonal_neighbours(size, a.0).contains(&b.0)
+    }
+}
+
+/// 上下左右相邻的格子
+pub fn orthogonal_neighbours(size: GridSize, cell: u16) -> Vec<u16> {
+    let side = size.side() as u16;
+    let (row, col) = (cell / side, cell % side);
+    let mut cells = vec![];
+    if row > 0 {
+        cells.push(cell - side);
+    }
+    if col > 0 {
+        cells.push(cell - 1);
+    }
+    if col < side - 1 {
+        cells.push(cell + 1);
+    }
+    if row < side - 1 {
+        cells.push(cell + side);
+    }
+    cells
+}
+
+/// 一局的规则: 宫的划分以及变体的额外约束
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Rules {
+    /// 棋盘大小, 旧存档没有这个字段, 都是 9×9
+    pub size: GridSize,
+    /// 每个格子所在的宫, 不规则数独的宫不是 3×3
+    pub regions: Vec<u8>,
+    /// 杀手数独的笼子, 只有 9×9 的棋盘有笼子
+    pub cages: Vec<Cage>,
+    /// 叠加的额外约束, 只能用在经典宫上
+    pub overlays: Overlays,
+    /// 温度计和箭头
+    pub lines: Vec<Line>,
+    /// Kropki 的黑白点以及 X、V
+    pub edges: Vec<Edge>,
+    /// 反约束: 没有画这些标记的相邻格子也不能满足这些标记
+    pub negative: Vec<EdgeKind>,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules::classic(GridSize::Nine)
+    }
+}
+
+impl Rules {
+    /// 这个大小的棋盘, 宫是规则的矩形
+    pub fn classic(size: GridSize) -> Rules {
+        Rules {
+            size,
+            regions: size.regions(),
+            cages: vec![],
+            overlays: Overlays::NONE,
+            lines: vec![],
+            edges: vec![],
+            negative: vec![],
+        }
+    }
+
+    /// 宫是规则的矩形, 只认识经典宫的解题技巧才能使用
+    pub fn has_classic_regions(&self) -> bool {
+        self.regions == self.size.regions()
+    }
+
+    /// 宫的划分有效: 每个宫的格子数等于棋盘的边长, 笼子和线条合法,
+    /// 额外约束只叠加在 9×9 的经典宫上. 武士数独只有经典规则
+    pub fn is_valid(&self) -> bool {
+        if self.size == GridSize::Samurai {
+            return *self == Rules::classic(GridSize::Samurai);
+        }
+        let side = self.size.side();
+        self.regions.len() == self.size.cell_count()
+            && (0..side).all(|region| {
+                self.regions.iter().filter(|&&r| r == region).count() == side as usize
+            })
+            && (self.cages.is_empty() || (self.size == GridSize::Nine && self.cages_are_valid()))
+            && (self.overlays.is_empty()
+                || (self.size == GridSize::Nine
+                    && self.cages.is_empty()
+                    && self.has_classic_regions()))
+            && self.overlays.is_solvable()
+            && self.lines.iter().all(|line| line.is_valid(self.size))
+            && self.edges.iter().enumerate().all(|(index, edge)| {
+                edge.is_valid(self.size)
+                    && self.edges[index + 1..]
+                        .iter()
+                        .all(|other| other.cells != edge.cells)
+            })
+    }
+
+    /// 相邻格子之间有标记或者反约束, 相邻格子的数字互相限制
+    pub fn has_edge_rules(&self) -> bool {
+        !self.edges.is_empty() || !self.negative.is_empty()
+    }
+
+    /// 两个相邻格子之间的标记
+    pub fn edge_between(&self, a: u16, b: u16) -> Option<EdgeKind> {
+        let cells = (a.min(b), a.max(b));
+        self.edges
+            .iter()
+            .find(|edge| (edge.cells[0].0, edge.cells[1].0) == cells)
+            .map(|edge| edge.kind)
+    }
+
+    /// 相邻的两个格子分别填 `num_a` 和 `num_b` 时满足边上的标记, 没有标记时满足反约束
+    pub fn edge_allows(&self, a: u16, num_a: u8, b: u16, num_b: u8) -> bool {
+        match self.edge_between(a, b) {
+            Some(kind) => kind.holds(num_a, num_b),
+            None => !self.negative.iter().any(|&kind| kind.holds(num_a, num_b)),
+        }
+    }
+
+    /// 笼子互不重叠, 正好盖满整个棋盘, 每个笼子的和都能用不重复的数字凑出来
+    fn cages_are_valid(&self) -> bool {
+        let mut covered = [false; 81];
+        for cage in &self.cages {
+            if cage.cells.is_empty()
+                || sum_combinations(cage.sum as i32, cage.cells.len()).is_empty()
+            {
+                return false;
+            }
+            for &cell in &cage.cells {
+                if cell >= 81 || std::mem::replace(&mut covered[cell as usize], true) {
+                    return false;
+                }
+            }
+        }
+        covered.iter().all(|&covered| covered)
+    }
+}
+
+/// 调色板里颜色的个数
+pub const PALETTE_SIZE: u8 = 9;
+
+/// 格子和候选数上涂的颜色, 用调色板里的序号表示, 找链和着色时用
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CellColors {
+    pub cell: Option<u8>,
+    /// 第 n - 1 个是候选数 n 的颜色
+    pub candidates: [Option<u8>; 16],
+}
+
+impl CellColors {
+    pub fn candidate(&self, digit: Digit) -> Option<u8> {
+        self.candidates[digit.get() as usize - 1]
+    }
+
+    pub fn set_candidate(&mut self, digit: Digit, color: Option<u8>) {
+        self.candidates[digit.get() as usize - 1] = color;
+    }
+}
+
+/// 棋盘模型, 不依赖 Bevy
+///
+/// 保存题目数字, 玩家填的数字, 两套候选数, 中心标记和涂的颜色, 所有规则都在这里实现,
+/// ECS 里的格子组件只是它的镜像
+#[derive(Clone, Debug, PartialEq)]
+pub struct Board {
+    givens: Vec<Option<Digit>>,
+    digits: Vec<Option<Digit>>,
+    manual_candidates: Vec<DigitSet>,
+    auto_candidates: Vec<DigitSet>,
+    /// 中心标记, 和手动候选数 (角标) 分开记录
+    center_marks: Vec<DigitSet>,
+    colors: Vec<CellColors>,
+    rules: Rules,
+}
+
+impl Board {
+    /// 从谜题创建棋盘, 自动候选数是每个空格不和已有数字冲突的数字
+    pub fn new(puzzle: Sudoku) -> Board {
+        Board::with_rules(&sudoku_cells(puzzle), Rules::default())
+    }
+
+    /// 按规则创建棋盘, `givens` 里 0 表示空格, 自动候选数也要满足变体的规则
+    pub fn with_rules(givens: &[u8], rules: Rules) -> Board {
+        assert_eq!(givens.len(), rules.size.cell_count());
+        let givens = givens
+            .iter()
+            .map(|&num| (num != 0).then(|| Digit::new(num)))
+            .collect();
+        Board::from_givens(givens, rules)
+    }
+
+    fn from_givens(givens: Vec<Option<Digit>>, rules: Rules) -> Board {
+        let count = givens.len();
+        let mut board = Board {
+            digits: givens.clone(),
+            givens,
+            manual_candidates: vec![DigitSet::NONE; count],
+            auto_candidates: vec![DigitSet::NONE; count],
+            center_marks: vec![DigitSet::NONE; count],
+            colors: vec![CellColors::default(); count],
+            rules,
+        };
+        for cell in board.size().cells() {
+            if board.digits[cell as usize].is_none() {
+                board.auto_candidates[cell as usize] = board.possible(cell);
+            }
+        }
+        board
+    }
+
+    /// 擦掉所有填入的数字和候选数, 回到刚开始的样子
+    pub fn reset(&mut self) {
+        let givens = std::mem::take(&mut self.givens);
+        *self = Board::from_givens(givens, std::mem::take(&mut self.rules));
+    }
+
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    pub fn size(&self) -> GridSize {
+        self.rules.size
+    }
+
+    pub fn cage_of(&self, cell: u16) -> Option<&Cage> {
+        self.rules
+            .cages
+            .iter()
+            .find(|cage| cage.cells.contains(&cell))
+    }
+
+    /// 经过这个格子的线条
+    pub fn lines_of(&self, cell: u16) -> impl Iterator<Item = &Line> {
+        self.rules
+            .lines
+            .iter()
+            .filter(move |line| line.contains(cell))
+    }
+
+    pub fn edges(&self) -> &[Edge] {
+        &self.rules.edges
+    }
+
+    /// 格子所在的宫
+    pub fn region(&self, cell: u16) -> u8 {
+        self.rules.regions[cell as usize]
+    }
+
+    /// 同一行, 同一列或者同一宫, 不规则数独按这一局的宫
+    ///
+    /// 武士数独的行和列只在一个棋盘里, 共用的格子在两个棋盘里都要检查
+    pub fn in_house(&self, a: u16, b: u16) -> bool {
+        let size = self.size();
+        let side = size.side() as u16;
+        (a / side == b / side || a % side == b % side) && size.shares_grid(a, b)
+            || self.region(a) == self.region(b)
+    }
+
+    /// 两个格子的数字不能相同: 同一行, 同一列, 同一宫, 同一个笼子或者额外约束
+    pub fn sees(&self, a: u16, b: u16) -> bool {
+        self.in_house(a, b)
+            || self.cage_of(a).is_some_and(|cage| cage.cells.contains(&b))
+            || self.rules.overlays.sees(self.size(), a, b)
+    }
+
+    /// 题目, 0 表示空格
+    pub fn givens(&self) -> Vec<u8> {
+        self.givens
+            .iter()
+            .map(|digit| digit.map_or(0, Digit::get))
+            .collect()
+    }
+
+    /// 题目加上填的数字, 0 表示空格
+    pub fn values(&self) -> Vec<u8> {
+        self.digits
+            .iter()
+            .map(|digit| digit.map_or(0, Digit::get))
+            .collect()
+    }
+
+    pub fn is_given(&self, cell: u16) -> bool {
+        self.givens[cell as usize].is_some()
+    }
+
+    pub fn digit(&self, cell: u16) -> Option<Digit> {
+        self.digits[cell as usize]
+    }
+
+    pub fn manual_candidates(&self, cell: u16) -> DigitSet {
+        self.manual_candidates[cell as usize]
+    }
+
+    pub fn auto_candidates(&self, cell: u16) -> DigitSet {
+        self.auto_candidates[cell as usize]
+    }
+
+    pub fn center_marks(&self, cell: u16) -> DigitSet {
+        self.center_marks[cell as usize]
+    }
+
+    pub fn colors(&self, cell: u16) -> CellColors {
+        self.colors[cell as usize]
+    }
+
+    fn candidates_mut(&mut self, cell: u16, auto: bool) -> &mut DigitSet {
+        if auto {
+            &mut self.auto_candidates[cell as usize]
+        } else {
+            &mut self.manual_candidates[cell as usize]
+        }
+    }
+
+    /// 填入数字, 并从同行同列同宫以及额外约束相关格子的候选数和中心标记中剔除这个数字
+    ///
+    /// 题目给出的格子不能修改, 返回 `false`
+    pub fn place(&mut self, cell: u16, digit: Digit) -> bool {
+        if self.is_given(cell) {
+            return false;
+        }
+        self.digits[cell as usize] = Some(digit);
+        for other in self.size().cells() {
+            if other != cell && self.sees(cell, other) {
+                self.eliminate(other, digit);
+            }
+        }
+        // 笼子剩下的和变小了, 其他空格能凑的数字也跟着变少
+        if let Some(cage) = self.cage_of(cell) {
+            for other in cage.cells.clone() {
+                if self.digit(other).is_none() {
+                    let sums = self.cage_digits(other);
+                    let auto = &mut self.auto_candidates[other as usize];
+                    *auto = *auto & sums;
+                }
+            }
+        }
+        // 相邻的空格要和这个数字满足边上的标记
+        if self.rules.has_edge_rules() {
+            for other in orthogonal_neighbours(self.size(), cell) {
+                if self.digit(other).is_none() {
+                    let digits = self.edge_digits(other);
+                    let auto = &mut self.auto_candidates[other as usize];
+                    *auto = *auto & digits;
+                }
+            }
+        }
+        // 同一条线上的空格也受这个数字限制
+        let on_lines: Vec<u16> = self
+            .lines_of(cell)
+            .flat_map(|line| line.path.iter().map(|position| position.0))
+            .collect();
+        for other in on_lines {
+            if self.digit(other).is_none() {
+                let digits = self.line_digits(other);
+                let auto = &mut self.auto_candidates[other as usize];
+                *auto = *auto & digits;
+            }
+        }
+        true
+    }
+
+    /// 擦掉填入的数字, 返回原来的数字
+    pub fn clear(&mut self, cell: u16) -> Option<Digit> {
+        if self.is_given(cell) {
+            return None;
+        }
+        self.digits[cell as usize].take()
+    }
+
+    pub fn clear_candidates(&mut self, cell: u16, auto: bool) {
+        if !self.is_given(cell) {
+            *self.candidates_mut(cell, auto) = DigitSet::NONE;
+        }
+    }
+
+    pub fn set_candidates(&mut self, cell: u16, auto: bool, candidates: DigitSet) {
+        if !self.is_given(cell) {
+            *self.candidates_mut(cell, auto) = candidates;
+        }
+    }
+
+    pub fn set_center_marks(&mut self, cell: u16, marks: DigitSet) {
+        if !self.is_given(cell) {
+            self.center_marks[cell as usize] = marks;
+        }
+    }
+
+    /// 切换一个候选数, 格子里已经有数字时先擦掉
+    pub fn toggle_candidate(&mut self, cell: u16, digit: Digit, auto: bool) -> bool {
+        if self.is_given(cell) {
+            return false;
+        }
+        self.digits[cell as usize] = None;
+        *self.candidates_mut(cell, auto) ^= digit;
+        true
+    }
+
+    /// 切换一个中心标记, 格子里已经有数字时先擦掉
+    pub fn toggle_center_mark(&mut self, cell: u16, digit: Digit) -> bool {
+        if self.is_given(cell) {
+            return false;
+        }
+        self.digits[cell as usize] = None;
+        self.center_marks[cell as usize] ^= digit;
+        true
+    }
+
+    /// 题目给出的格子也可以涂颜色
+    pub fn set_colors(&mut self, cell: u16, colors: CellColors) {
+        self.colors[cell as usize] = colors;
+    }
+
+    /// 擦掉所有格子和候选数上的颜色
+    pub fn clear_colors(&mut self) {
+        self.colors.fill(CellColors::default());
+    }
+
+    /// 从两套候选数和中心标记中删除一个数字
+    pub fn eliminate(&mut self, cell: u16, digit: Digit) {
+        self.manual_candidates[cell as usize].remove(digit.as_set());
+        self.auto_candidates[cell as usize].remove(digit.as_set());
+        self.center_marks[cell as usize].remove(digit.as_set());
+    }
+
+    /// 和这个格子数字相同的其他格子
+    ///
+    /// 笼子里填的数字加起来超过了和, 或者填满了却不等于和时, 笼子里填了数字的格子都算冲突.
+    /// 温度计上顺序不对的格子, 和不对的箭头上填了数字的格子,
+    /// 以及和这个格子不满足边上标记或者反约束的相邻格子也算冲突
+    pub fn conflicts_of(&self, cell: u16) -> Vec<u16> {
+        let Some(digit) = self.digit(cell) else {
+            return vec![];
+        };
+        let mut conflicts: Vec<u16> = self
+            .size()
+            .cells()
+            .filter(|&other| {
+                other != cell && self.sees(cell, other) && self.digit(other) == Some(digit)
+            })
+            .collect();
+        if let Some(cage) = self.cage_of(cell) {
+            let filled: Vec<u16> = cage
+                .cells
+                .iter()
+                .copied()
+                .filter(|&other| self.digit(other).is_some())
+                .collect();
+            let total: u8 = filled
+                .iter()
+                .filter_map(|&other| self.digit(other))
+                .map(|digit| digit.get())
+                .sum();
+            if total > cage.sum || (filled.len() == cage.cells.len() && total != cage.sum) {
+                conflicts.extend(filled);
+            }
+        }
+        let side = self.size().digit_count();
+        for line in self.lines_of(cell) {
+            conflicts.extend(line.conflicts_of(side, cell, |other| self.value(other)));
+        }
+        if self.rules.has_edge_rules() {
+            for other in orthogonal_neighbours(self.size(), cell) {
+                let value = self.value(other);
+                if value != 0 && !self.rules.edge_allows(cell, digit.get(), other, value) {
+                    conflicts.extend([cell, other]);
+                }
+            }
+        }
+        conflicts.sort_unstable();
+        conflicts.dedup();
+        conflicts
+    }
+
+    /// 格子的数字, 0 表示空格
+    fn value(&self, cell: u16) -> u8 {
+        self.digit(cell).map_or(0, Digit::get)
+    }
+
+    /// 所有格子都填满并且没有冲突
+    pub fn is_solved(&self) -> bool {
+        self.size()
+            .cells()
+            .all(|cell| self.digit(cell).is_some() && self.conflicts_of(cell).is_empty())
+    }
+
+    /// 不和其他格子数字冲突, 并且能凑出笼子的和的数字
+    fn possible(&self, cell: u16) -> DigitSet {
+        let mut possible = DigitSet::NONE;
+        for digit in self.size().digits() {
+            if !self.size().cells().any(|other| {
+                other != cell && self.sees(cell, other) && self.digit(other) == Some(digit)
+            }) {
+                possible ^= digit;
+            }
+        }
+        if self.cage_of(cell).is_some() {
+            possible = possible & self.cage_digits(cell);
+        }
+        if self.lines_of(cell).next().is_some() {
+            possible = possible & self.line_digits(cell);
+        }
+        if self.rules.has_edge_rules() {
+            possible = possible & self.edge_digits(cell);
+        }
+        possible
+    }
+
+    /// 相邻格子的数字不变时, 这个格子可以填的数字
+    ///
+    /// 相邻的格子已经填了数字时要和它满足标记或者反约束;
+    /// 还空着时, 标记的另一边要有数字能配上, 相邻的格子同行或者同列, 数字不能相同
+    fn edge_digits(&self, cell: u16) -> DigitSet {
+        let size = self.size();
+        let mut digits = DigitSet::NONE;
+        for digit in size.digits() {
+            let num = digit.get();
+            let fits = orthogonal_neighbours(size, cell).into_iter().all(|other| {
+                match (self.value(other), self.rules.edge_between(cell, other)) {
+                    (0, Some(kind)) => size
+                        .digits()
+                        .any(|partner| partner != digit && kind.holds(num, partner.get())),
+                    (0, None) => true,
+                    (value, _) => self.rules.edge_allows(cell, num, other, value),
+                }
+            });
+            if fits {
+                digits ^= digit;
+            }
+        }
+        digits
+    }
+
+    /// 线上其他数字不变时, 这个格子可以填的数字
+    fn line_digits(&self, cell: u16) -> DigitSet {
+        let side = self.size().digit_count();
+        let mut digits = DigitSet::NONE;
+        for digit in self.size().digits() {
+            if self
+                .lines_of(cell)
+                .all(|line| line.allows(side, cell, digit.get(), |other| self.value(other)))
+            {
+                digits ^= digit;
+            }
+        }
+        digits
+    }
+
+    /// 笼子里其他数字不变时, 这个格子可以填的数字
+    ///
+    /// 剩下的空格要用不重复的数字凑出剩下的和, 这个格子的数字必须出现在某一种组合里
+    fn cage_digits(&self, cell: u16) -> DigitSet {
+        let Some(cage) = self.cage_of(cell) else {
+            return DigitSet::NONE;
+        };
+        let mut used = 0u16;
+        let mut remaining = cage.sum as i32;
+        let mut empty = 0;
+        for &other in &cage.cells {
+            match self.digit(other).filter(|_| other != cell) {
+                Some(digit) => {
+                    used |= 1 << digit.get();
+                    remaining -= digit.get() as i32;
+                }
+                None => empty += 1,
+            }
+        }
+
+        let mut digits = DigitSet::NONE;
+        for combination in sum_combinations(remaining, empty) {
+            if combination & used != 0 {
+                continue;
+            }
+            for num in 1..=9u8 {
+                let digit = Digit::new(num);
+                if combination & (1 << num) != 0 && !digits.contains(digit.as_set()) {
+                    digits ^= digit;
+                }
+            }
+        }
+        digits
+    }
+}
+
+/// 用 `count` 个不重复的 1-9 凑出 `sum` 的所有组合, 第 n 位表示数字 n
+pub fn sum_combinations(sum: i32, count: usize) -> Vec<u16> {
+    (0u16..512)
+        .map(|mask| mask << 1)
+        .filter(|mask| {
+            mask.count_ones() as usize == count
+                && (1..=9).filter(|num| mask & (1 << num) != 0).sum::<i32>() == sum
+        })
+        .collect()
+}
+
+/// 谜题的格子, 0 表示空格
+pub fn sudoku_cells(sudoku: Sudoku) -> Vec<u8> {
+    sudoku.iter().map(|num| num.unwrap_or(0)).collect()
+}
+
+#[cfg(test)]
+const SOLVED: &str =
+    "534678912672195348198342567859761423426853791713924856961537284287419635345286179";
+
+#[cfg(test)]
+fn board_without(cells: &[usize]) -> Board {
+    let mut line = SOLVED.to_string();
+    for &cell in cells {
+        line.replace_range(cell..cell + 1, ".");
+    }
+    Board::new(Sudoku::from_str_line(&line).unwrap())
+}
+
+#[test]
+fn test_new_board() {
+    let board = board_without(&[0, 1]);
+    assert!(board.is_given(2));
+    assert!(!board.is_given(0));
+    assert_eq!(board.auto_candidates(0), Digit::new(5).as_set());
+    assert_eq!(board.auto_candidates(1), Digit::new(3).as_set());
+    assert!(!board.is_solved());
+}
+
+#[test]
+fn test_place_kicks_candidates() {
+    let mut board = board_without(&[0, 1, 9]);
+    board.toggle_candidate(1, Digit::new(5), false);
+    board.toggle_candidate(9, Digit::new(5), false);
+    board.toggle_center_mark(9, Digit::new(5));
+    assert!(board.place(0, Digit::new(5)));
+    assert!(board.manual_candidates(1).is_empty());
+    assert!(board.manual_candidates(9).is_empty());
+    assert!(board.center_marks(9).is_empty());
+    assert!(!board.auto_candidates(1).contains(Digit::new(5).as_set()));
+
+    // 题目给出的数字不能修改
+    assert!(!board.place(2, Digit::new(1)));
+    assert_eq!(board.clear(2), None);
+    assert_eq!(board.digit(2), Some(Digit::new(4)));
+}
+
+#[test]
+fn test_conflicts() {
+    let mut board = board_without(&[0, 1]);
+    board.place(0, Digit::new(3));
+    board.place(1, Digit::new(3));
+    // 第 0 格的 3 还和第一列最下面的 3 冲突
+    assert_eq!(board.conflicts_of(0), vec![1, 72]);
+    assert_eq!(board.conflicts_of(1), vec![0]);
+    assert!(!board.is_solved());
+
+    board.clear(0);
+    assert!(board.conflicts_of(1).is_empty());
+    board.place(0, Digit::new(5));
+    assert!(board.is_solved());
+}
+
+#[test]
+fn test_toggle_candidate() {
+    let mut board = board_without(&[0]);
+    board.place(0, Digit::new(5));
+    assert!(board.toggle_candidate(0, Digit::new(1), false));
+    assert_eq!(board.digit(0), None);
+    assert_eq!(board.manual_candidates(0), Digit::new(1).as_set());
+    board.toggle_candidate(0, Digit::new(1), false);
+    assert!(board.manual_candidates(0).is_empty());
+
+    // 中心标记和角标分开记录
+    board.place(0, Digit::new(5));
+    assert!(board.toggle_center_mark(0, Digit::new(2)));
+    assert_eq!(board.digit(0), None);
+    assert_eq!(board.center_marks(0), Digit::new(2).as_set());
+    assert!(board.manual_candidates(0).is_empty());
+    assert!(!board.toggle_center_mark(2, Digit::new(2)));
+}
+
+#[test]
+fn test_cages() {
+    assert_eq!(sum_combinations(7, 2).len(), 3);
+    assert_eq!(sum_combinations(45, 9).len(), 1);
+
+    let givens = board_without(&[0, 1]).givens;
+    let cage = Cage {
+        cells: vec![0, 1],
+        sum: 7,
+    };
+    let rules = Rules {
+        cages: vec![cage],
+        ..Rules::default()
+    };
+    let mut board = Board::from_givens(givens, rules);
+    assert_eq!(board.auto_candidates(0), Digit::new(5).as_set());
+
+    // 第 0 格填 5 之后第 1 格只能是 2, 但是第一行已经有 2 了
+    board.place(0, Digit::new(5));
+    assert!(board.auto_candidates(1).is_empty());
+    board.place(1, Digit::new(3));
+    assert_eq!(board.conflicts_of(0), vec![0, 1]);
+    assert!(!board.is_solved());
+}
+
+#[test]
+fn test_cage_validation() {
+    let solution: Vec<u8> = SOLVED.bytes().map(|byte| byte - b'0').collect();
+    // 每一行切成 4 + 5 两个笼子
+    let cages: Vec<Cage> = (0..9u16)
+        .flat_map(|row| [row * 9..row * 9 + 4, row * 9 + 4..row * 9 + 9])
+        .map(|range| Cage {
+            sum: range.clone().map(|cell| solution[cell as usize]).sum(),
+            cells: range.collect(),
+        })
+        .collect();
+    let rules = |cages: Vec<Cage>| Rules {
+        cages,
+        ..Rules::default()
+    };
+    assert!(rules(cages.clone()).is_valid());
+
+    // 有格子不在任何笼子里
+    assert!(!rules(cages[1..].to_vec()).is_valid());
+
+    // 两个笼子重叠
+    let mut overlapping = cages.clone();
+    overlapping[1].cells.push(3);
+    assert!(!rules(overlapping).is_valid());
+
+    // 4 个不同的数字最少加起来是 10
+    let mut unreachable = cages.clone();
+    unreachable[0].sum = 9;
+    assert!(!rules(unreachable).is_valid());
+
+    let mut outside = cages;
+    outside[0].cells[0] = 81;
+    assert!(!rules(outside).is_valid());
+}
+
+#[test]
+fn test_irregular_regions() {
+    let mut regions = CLASSIC_REGIONS;
+    regions.swap(2, 3);
+    let rules = Rules {
+        regions: regions.to_vec(),
+        ..Rules::default()
+    };
+    assert!(rules.is_valid());
+    assert!(!rules.has_classic_regions());
+
+    let board = Board::from_givens(vec![None; 81], rules);
+    assert!(board.sees(2, 12));
+    assert!(!board.sees(2, 18));
+    assert!(!Rules {
+        regions: vec![0; 81],
+        ..Rules::default()
+    }
+    .is_valid());
+}
+
+#[test]
+fn test_small_board() {
+    let mut givens = vec![0; 16];
+    givens[0] = 1;
+    givens[5] = 2;
+    let board = Board::with_rules(&givens, Rules::classic(GridSize::Four));
+    assert!(board.sees(0, 5));
+    assert!(!board.sees(0, 10));
+    assert_eq!(board.auto_candidates(1).len(), 2);
+    assert_eq!(board.auto_candidates(15).len(), 4);
+    assert!(!Rules {
+        cages: vec![Cage {
+            cells: vec![0, 1],
+            sum: 3,
+        }],
+        ..Rules::classic(GridSize::Four)
+    }
+    .is_valid());
+}
+
+#[test]
+fn test_overlays() {
+    let nine = GridSize::Nine;
+    assert!(Overlay::Diagonals.sees(nine, 0, 80));
+    assert!(Overlay::Diagonals.sees(nine, 8, 72));
+    assert!(!Overlay::Diagonals.sees(nine, 0, 8));
+    assert_eq!(windoku_box(10), Some(0));
+    assert_eq!(windoku_box(70), Some(3));
+    assert_eq!(windoku_box(40), None);
+    assert!(Overlay::AntiKnight.sees(nine, 0, 19));
+    assert!(!Overlay::AntiKnight.sees(nine, 0, 20));
+    assert!(Overlay::AntiKing.sees(nine, 0, 10));
+
+    let mut overlays = Overlays::NONE;
+    overlays.toggle(Overlay::AntiKing);
+    overlays.toggle(Overlay::Windoku);
+    overlays.toggle(Overlay::AntiKnight);
+    assert_eq!(overlays.to_string(), "Windoku + Anti-Knight + Anti-King");
+    assert!(!overlays.is_solvable());
+    overlays.toggle(Overlay::Windoku);
+    assert!(overlays.is_solvable());
+    let rules = Rules {
+        overlays,
+        ..Rules::default()
+    };
+    assert!(rules.is_valid());
+    assert!(!Rules {
+        overlays,
+        ..Rules::classic(GridSize::Six)
+    }
+    .is_valid());
+
+    // 第 3 格和第 10 格隔一个马步, 第 10 格和第 0 格斜着相邻
+    let mut board = Board::from_givens(board_without(&[3, 10]).givens, rules);
+    board.toggle_candidate(3, Digit::new(5), false);
+    board.place(10, Digit::new(5));
+    assert!(board.manual_candidates(3).is_empty());
+    assert!(board.conflicts_of(10).contains(&0));
+}
+
+#[cfg(test)]
+fn line(kind: LineKind, cells: &[u16]) -> Line {
+    Line {
+        kind,
+        path: cells
+            .iter()
+            .map(|&cell| CellPosition::new(cell, GridSize::Nine))
+            .collect(),
+    }
+}
+
+#[test]
+fn test_thermometer() {
+    let thermometer = line(LineKind::Thermometer, &[0, 10, 20]);
+    let rules = Rules {
+        lines: vec![thermometer.clone()],
+        ..Rules::default()
+    };
+    assert!(rules.is_valid());
+    // 第 0 格和第 2 格不相邻
+    assert!(!Rules {
+        lines: vec![line(LineKind::Thermometer, &[0, 2])],
+        ..Rules::default()
+    }
+    .is_valid());
+
+    // 灯泡后面还有两格, 最多是 7; 最上面一格最少是 3
+    let mut board = Board::from_givens(vec![None; 81], rules);
+    assert!(!board.auto_candidates(0).contains(Digit::new(8).as_set()));
+    assert!(!board.auto_candidates(20).contains(Digit::new(2).as_set()));
+    board.place(10, Digit::new(7));
+    let below = (1..=6).fold(DigitSet::NONE, |mut digits, num| {
+        digits ^= Digit::new(num);
+        digits
+    });
+    assert_eq!(board.auto_candidates(0), below);
+
+    board.place(20, Digit::new(6));
+    assert_eq!(board.conflicts_of(20), vec![10, 20]);
+    assert_eq!(board.conflicts_of(10), vec![10, 20]);
+    board.place(20, Digit::new(8));
+    board.place(0, Digit::new(5));
+    assert!(board.conflicts_of(0).is_empty());
+    assert!(!thermometer.allows(9, 0, 7, |cell| board.value(cell)));
+}
+
+#[test]
+fn test_arrow() {
+    // 圆圈在第 0 格, 箭身是第 10 格和第 11 格
+    let arrow = line(LineKind::Arrow, &[0, 10, 11]);
+    let rules = Rules {
+        lines: vec![arrow],
+        ..Rules::default()
+    };
+    assert!(rules.is_valid());
+    // 箭身两格最少加起来是 2, 圆圈空着时箭身一格最多是 8
+    let mut board = Board::from_givens(vec![None; 81], rules);
+    assert!(!board.auto_candidates(0).contains(Digit::new(1).as_set()));
+    assert!(!board.auto_candidates(10).contains(Digit::new(9).as_set()));
+
+    board.place(0, Digit::new(5));
+    board.place(10, Digit::new(3));
+    assert_eq!(board.auto_candidates(11), Digit::new(2).as_set());
+    board.place(11, Digit::new(4));
+    assert_eq!(board.conflicts_of(10), vec![0, 10, 11]);
+    board.place(11, Digit::new(2));
+    assert!(board.conflicts_of(10).is_empty());
+}
+
+#[test]
+fn test_line_format() {
+    let rules = Rules {
+        lines: vec![line(LineKind::Arrow, &[0, 10])],
+        ..Rules::default()
+    };
+    let json = serde_json::to_string(&rules).unwrap();
+    assert!(json.contains(r#""path":[[0,"Nine"],[10,"Nine"]]"#));
+    assert_eq!(serde_json::from_str::<Rules>(&json).unwrap(), rules);
+    // 旧存档没有线条
+    let old: Rules = serde_json::from_str(r#"{"size":"Nine"}"#).unwrap();
+    assert!(old.lines.is_empty());
+}
+
+#[cfg(test)]
+fn edge(kind: EdgeKind, a: u16, b: u16) -> Edge {
+    Edge {
+        kind,
+        cells: [a, b].map(|cell| CellPosition::new(cell, GridSize::Nine)),
+    }
+}
+
+#[test]
+fn test_edges() {
+    assert!(EdgeKind::White.holds(4, 3));
+    assert!(EdgeKind::Black.holds(3, 6));
+    assert!(!EdgeKind::Black.holds(3, 5));
+    assert!(EdgeKind::X.holds(1, 9));
+    assert!(EdgeKind::V.holds(2, 3));
+
+    let rules = Rules {
+        edges: vec![edge(EdgeKind::Black, 0, 1), edge(EdgeKind::X, 1, 10)],
+        ..Rules::default()
+    };
+    assert!(rules.is_valid());
+    // 第 0 格和第 10 格斜着相邻, 不能画标记
+    assert!(!Rules {
+        edges: vec![edge(EdgeKind::White, 0, 10)],
+        ..Rules::default()
+    }
+    .is_valid());
+
+    // 黑点两边不能是 5, 7, 9; X 两边不能是 5
+    let mut board = Board::from_givens(vec![None; 81], rules);
+    assert!(!board.auto_candidates(0).contains(Digit::new(7).as_set()));
+    assert!(!board.auto_candidates(1).contains(Digit::new(5).as_set()));
+    assert_eq!(board.auto_candidates(2).len(), 9);
+
+    board.place(0, Digit::new(3));
+    assert_eq!(board.auto_candidates(1), Digit::new(6).as_set());
+    board.place(1, Digit::new(4));
+    assert_eq!(board.conflicts_of(1), vec![0, 1]);
+    board.place(1, Digit::new(6));
+    board.place(10, Digit::new(2));
+    assert_eq!(board.conflicts_of(10), vec![1, 10]);
+    board.place(10, Digit::new(4));
+    assert!(board.conflicts_of(1).is_empty());
+}
+
+#[test]
+fn test_negative_edges() {
+    let rules = Rules {
+        edges: vec![edge(EdgeKind::White, 0, 1)],
+        negative: vec![EdgeKind::White, EdgeKind::Black],
+        ..Rules::default()
+    };
+    let mut board = Board::from_givens(vec![None; 81], rules);
+    board.place(0, Digit::new(4));
+    assert_eq!(board.auto_candidates(1).len(), 2);
+    // 第 9 格和第 0 格之间没有点, 不能相差 1 也不能是两倍
+    let neither = board.auto_candidates(9);
+    for num in [2, 3, 5, 8] {
+        assert!(!neither.contains(Digit::new(num).as_set()));
+    }
+    assert!(neither.contains(Digit::new(6).as_set()));
+    board.place(9, Digit::new(8));
+    assert_eq!(board.conflicts_of(9), vec![0, 9]);
+}
+
+#[test]
+fn test_samurai_conflicts() {
+    let size = GridSize::Samurai;
+    let cell = |row: u16, col: u16| row * 21 + col;
+    let mut board = Board::from_givens(vec![None; size.cell_count()], Rules::classic(size));
+    // 第 6 行第 6 列在左上和中间两个棋盘里, 两个棋盘的行和列都要检查
+    let shared = cell(6, 6);
+    board.place(shared, Digit::new(5));
+    for other in [cell(0, 6), cell(6, 0), cell(6, 14), cell(14, 6)] {
+        assert!(!board
+            .auto_candidates(other)
+            .contains(Digit::new(5).as_set()));
+    }
+    // 第 6 行第 20 列只在右上的棋盘里, 和共用的格子不在同一个棋盘
+    assert!(board
+        .auto_candidates(cell(6, 20))
+        .contains(Digit::new(5).as_set()));
+    board.place(cell(6, 14), Digit::new(5));
+    assert_eq!(board.conflicts_of(shared), vec![cell(6, 14)]);
+    assert!(Rules::classic(size).is_valid());
+}
+
+#[test]
+fn test_in_house() {
+    let rules = Rules {
+        cages: vec![Cage {
+            cells: vec![0, 80],
+            sum: 10,
+        }],
+        ..Default::default()
+    };
+    let board = Board::from_givens(vec![None; 81], rules);
+    assert!(board.in_house(0, 8));
+    assert!(board.in_house(0, 20));
+    // 同一个笼子不算同一个宫
+    assert!(!board.in_house(0, 80));
+    assert!(board.sees(0, 80));
+}
+
+#[test]
+fn test_colors() {
+    let mut board = board_without(&[0]);
+    let mut colors = CellColors {
+        cell: Some(2),
+        ..CellColors::default()
+    };
+    colors.set_candidate(Digit::new(5), Some(4));
+    board.set_colors(2, colors);
+    assert_eq!(board.colors(2).candidate(Digit::new(5)), Some(4));
+
+    // 填数字不会擦掉颜色
+    board.place(0, Digit::new(5));
+    assert_eq!(board.colors(2), colors);
+    board.clear_colors();
+    assert_eq!(board.colors(2), CellColors::default());
+}
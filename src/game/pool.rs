@@ -0,0 +1,157 @@
+use crate::game::{
+    generator::{generate_board, generate_daily},
+    model::{sudoku_cells, Board},
+    Difficulty, GeneratorOptions, PuzzleMode, StartPuzzle,
+};
+use crate::{share::today, GameState};
+use bevy::{
+    prelude::*,
+    tasks::{block_on, poll_once, AsyncComputeTaskPool, Task},
+    utils::HashMap,
+};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+/// 每个难度预先生成的随机谜题数量
+const POOL_SIZE: usize = 2;
+
+pub(crate) fn plugin(app: &mut App) {
+    app.init_resource::<PuzzlePool>().add_systems(
+        Update,
+        poll_generating
+            .run_if(in_state(GameState::Playing))
+            .run_if(resource_exists::<GeneratingPuzzle>),
+    );
+    // wasm 上的任务池跑在主线程, 预先生成会卡住界面, 池子一直为空, 每局现生成
+    #[cfg(not(target_arch = "wasm32"))]
+    app.add_systems(
+        Update,
+        fill_pool
+            .run_if(not(in_state(GameState::Loading)))
+            .run_if(not(resource_exists::<GeneratingPuzzle>)),
+    );
+}
+
+/// 在后台预先生成的随机谜题, "再来一局" 时直接取用
+///
+/// 按生成选项和难度分开存放, 改回之前的选项时生成好的谜题还能用
+#[derive(Resource, Default)]
+pub struct PuzzlePool {
+    ready: HashMap<(GeneratorOptions, Difficulty), Vec<(Vec<u8>, Board)>>,
+    tasks: Vec<(GeneratorOptions, Difficulty, Generation)>,
+}
+
+impl PuzzlePool {
+    /// 取出一个用这些选项生成好的谜题, 返回 (答案, 棋盘)
+    pub fn take(
+        &mut self,
+        difficulty: Difficulty,
+        options: GeneratorOptions,
+    ) -> Option<(Vec<u8>, Board)> {
+        self.ready.get_mut(&(options, difficulty))?.pop()
+    }
+}
+
+/// 收取生成完的谜题, 当前选项的谜题不够 [`POOL_SIZE`] 个时继续生成
+///
+/// 同时只跑一个生成任务, 玩家等着的 [`GeneratingPuzzle`] 不用排在一堆任务后面;
+/// 玩家在等的时候不补充. 生成选项改变后, 旧选项还没生成完的任务被丢掉, 生成循环随之停下
+#[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+fn fill_pool(mut pool: ResMut<PuzzlePool>, options: Res<GeneratorOptions>) {
+    let options = *options;
+    let PuzzlePool { ready, tasks } = &mut *pool;
+    tasks.retain_mut(|(other, difficulty, generation)| {
+        if *other != options {
+            return false;
+        }
+        match generation.poll() {
+            Some(puzzle) => {
+                ready
+                    .entry((options, *difficulty))
+                    .or_default()
+                    .extend(puzzle);
+                false
+            }
+            None => true,
+        }
+    });
+
+    if !tasks.is_empty() {
+        return;
+    }
+    let Some(difficulty) = [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard]
+        .into_iter()
+        .find(|&difficulty| ready.get(&(options, difficulty)).map_or(0, Vec::len) < POOL_SIZE)
+    else {
+        return;
+    };
+    let generation = Generation::spawn(move |cancel| generate_board(difficulty, options, cancel));
+    tasks.push((options, difficulty, generation));
+}
+
+/// 一个后台生成任务, 丢掉时通知生成循环停下
+///
+/// 丢掉 [`Task`] 只能取消还没开始的任务, 已经在跑的生成循环要自己检查取消标记
+struct Generation {
+    cancel: Arc<AtomicBool>,
+    task: Task<Option<(Vec<u8>, Board)>>,
+}
+
+impl Generation {
+    fn spawn(
+        generate: impl FnOnce(&AtomicBool) -> Option<(Vec<u8>, Board)> + Send + 'static,
+    ) -> Generation {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let flag = cancel.clone();
+        let task = AsyncComputeTaskPool::get().spawn(async move { generate(&flag) });
+        Generation { cancel, task }
+    }
+
+    /// 任务结束时返回 `Some`, 被取消的任务结果是 `Some(None)`
+    fn poll(&mut self) -> Option<Option<(Vec<u8>, Board)>> {
+        block_on(poll_once(&mut self.task))
+    }
+}
+
+impl Drop for Generation {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+/// 正在后台生成的下一局, 生成期间棋盘上显示 "Generating..."
+#[derive(Resource)]
+pub struct GeneratingPuzzle(Generation);
+
+impl GeneratingPuzzle {
+    pub fn spawn(
+        mode: PuzzleMode,
+        difficulty: Difficulty,
+        options: GeneratorOptions,
+    ) -> GeneratingPuzzle {
+        let date = today();
+        GeneratingPuzzle(Generation::spawn(move |cancel| match mode {
+            PuzzleMode::Daily => {
+                let (puzzle, solution) = generate_daily(date, difficulty);
+                Some((sudoku_cells(solution), Board::new(puzzle)))
+            }
+            PuzzleMode::Random => generate_board(difficulty, options, cancel),
+        }))
+    }
+}
+
+fn poll_generating(mut generating: ResMut<GeneratingPuzzle>, mut commands: Commands) {
+    let Some(puzzle) = generating.0.poll() else {
+        return;
+    };
+    commands.remove_resource::<GeneratingPuzzle>();
+    if let Some((solution, board)) = puzzle {
+        commands.trigger(StartPuzzle {
+            solution,
+            board,
+            saved: None,
+        });
+    }
+}
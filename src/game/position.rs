@@ -1,65 +1,63 @@
+use crate::game::grid::GridSize;
 use bevy::prelude::Component;
+use serde::{Deserialize, Serialize};
 use std::fmt::Display;
 
-/// 数独格子的位置
-#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash)]
-pub struct CellPosition(pub u8);
-
-/// 快速计算宫格索引
-#[rustfmt::skip]
-static BLOCK: [u8; 81] = [
-    0, 0, 0, 1, 1, 1, 2, 2, 2,
-    0, 0, 0, 1, 1, 1, 2, 2, 2,
-    0, 0, 0, 1, 1, 1, 2, 2, 2,
-    3, 3, 3, 4, 4, 4, 5, 5, 5,
-    3, 3, 3, 4, 4, 4, 5, 5, 5,
-    3, 3, 3, 4, 4, 4, 5, 5, 5,
-    6, 6, 6, 7, 7, 7, 8, 8, 8,
-    6, 6, 6, 7, 7, 7, 8, 8, 8,
-    6, 6, 6, 7, 7, 7, 8, 8, 8,
-];
+/// 数独格子的位置, 以及格子所在棋盘的大小
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CellPosition(pub u16, pub GridSize);
 
 impl CellPosition {
-    pub fn new(cell: u8) -> CellPosition {
-        assert!(cell < 81);
-        CellPosition(cell)
+    pub fn new(cell: u16, size: GridSize) -> CellPosition {
+        assert!(size.contains(cell));
+        CellPosition(cell, size)
     }
 
-    pub fn from_row_col(row: u8, col: u8) -> CellPosition {
-        CellPosition::new(row * 9 + col)
+    pub fn from_row_col(row: u8, col: u8, size: GridSize) -> CellPosition {
+        CellPosition::new(row as u16 * size.side() as u16 + col as u16, size)
     }
 
-    pub fn from_block_row_col(block: u8, inner_box_pos: u8) -> CellPosition {
-        // 计算宫格的起始位置
-        let box_row = block / 3;
-        let box_col = block % 3;
-
-        // 计算宫格内部的位置
-        let inner_row = inner_box_pos / 3;
-        let inner_col = inner_box_pos % 3;
-
-        // 计算整体网格中的行和列
-        let row = box_row * 3 + inner_row;
-        let col = box_col * 3 + inner_col;
-
-        // 将行列转换成线性索引
-        CellPosition::new(row * 9 + col)
+    pub fn size(&self) -> GridSize {
+        self.1
     }
 
     pub fn row(&self) -> u8 {
-        self.0 / 9
+        (self.0 / self.1.side() as u16) as u8
     }
 
     pub fn col(&self) -> u8 {
-        self.0 % 9
+        (self.0 % self.1.side() as u16) as u8
     }
 
+    /// 规则的宫, 不规则数独的宫要看 [`Board::region`]
+    ///
+    /// [`Board::region`]: crate::game::model::Board::region
     pub fn block(&self) -> u8 {
-        BLOCK[self.0 as usize]
+        let (box_rows, box_cols) = (self.1.box_rows(), self.1.box_cols());
+        self.row() / box_rows * (self.1.side() / box_cols) + self.col() / box_cols
     }
 
+    /// 往 (`rows`, `cols`) 方向走到的下一个格子, 跳过武士数独空着的位置, 走出棋盘时返回 `None`
+    pub fn step(&self, rows: i8, cols: i8) -> Option<CellPosition> {
+        let side = self.1.side() as i16;
+        let (mut row, mut col) = (self.row() as i16, self.col() as i16);
+        loop {
+            row += rows as i16;
+            col += cols as i16;
+            if !(0..side).contains(&row) || !(0..side).contains(&col) {
+                return None;
+            }
+            if self.1.contains((row * side + col) as u16) {
+                return Some(CellPosition::from_row_col(row as u8, col as u8, self.1));
+            }
+        }
+    }
+
+    /// 同一行, 同一列或者同一宫, 武士数独的行和列要在同一个棋盘里
     pub fn in_range(&self, other: &CellPosition) -> bool {
-        self.row() == other.row() || self.col() == other.col() || self.block() == other.block()
+        (self.row() == other.row() || self.col() == other.col())
+            && self.1.shares_grid(self.0, other.0)
+            || self.block() == other.block()
     }
 }
 
@@ -71,18 +69,42 @@ impl Display for CellPosition {
 
 #[test]
 fn test_row() {
-    assert_eq!(CellPosition::new(0).row(), 0);
-    assert_eq!(CellPosition::new(4).row(), 0);
-    assert_eq!(CellPosition::new(8).row(), 0);
-    assert_eq!(CellPosition::new(9).row(), 1);
-    assert_eq!(CellPosition::new(80).row(), 8);
+    let nine = GridSize::Nine;
+    assert_eq!(CellPosition::new(0, nine).row(), 0);
+    assert_eq!(CellPosition::new(4, nine).row(), 0);
+    assert_eq!(CellPosition::new(8, nine).row(), 0);
+    assert_eq!(CellPosition::new(9, nine).row(), 1);
+    assert_eq!(CellPosition::new(80, nine).row(), 8);
 }
 
 #[test]
 fn test_col() {
-    assert_eq!(CellPosition::new(0).col(), 0);
-    assert_eq!(CellPosition::new(4).col(), 4);
-    assert_eq!(CellPosition::new(8).col(), 8);
-    assert_eq!(CellPosition::new(9).col(), 0);
-    assert_eq!(CellPosition::new(80).col(), 8);
+    let nine = GridSize::Nine;
+    assert_eq!(CellPosition::new(0, nine).col(), 0);
+    assert_eq!(CellPosition::new(4, nine).col(), 4);
+    assert_eq!(CellPosition::new(8, nine).col(), 8);
+    assert_eq!(CellPosition::new(9, nine).col(), 0);
+    assert_eq!(CellPosition::new(80, nine).col(), 8);
+}
+
+#[test]
+fn test_other_sizes() {
+    let six = CellPosition::from_row_col(3, 4, GridSize::Six);
+    assert_eq!(six.0, 22);
+    assert_eq!(six.block(), 3);
+
+    let last = CellPosition::new(255, GridSize::Sixteen);
+    assert_eq!((last.row(), last.col(), last.block()), (15, 15, 15));
+}
+
+#[test]
+fn test_step() {
+    let nine = CellPosition::new(8, GridSize::Nine);
+    assert_eq!(nine.step(1, 0), Some(CellPosition::new(17, GridSize::Nine)));
+    assert_eq!(nine.step(0, 1), None);
+
+    // 第 0 行第 9 到 11 列是空着的, 往右直接到右上的棋盘
+    let samurai = CellPosition::from_row_col(0, 8, GridSize::Samurai);
+    assert_eq!(samurai.step(0, 1).map(|cell| cell.col()), Some(12));
+    assert_eq!(samurai.step(-1, 0), None);
 }
@@ -0,0 +1,353 @@
+use crate::game::{
+    cell_state::{
+        AutoCandidates, CellColor, CenterMarks, CorrectionCell, DigitValueCell, ManualCandidates,
+        RevealedCell,
+    },
+    grid::{cells_line, parse_cells_line, Digit, DigitSet, GridSize},
+    model::{Board, CellColors, Rules, PALETTE_SIZE},
+    position::CellPosition,
+    AutoCandidateMode, Difficulty, GameTimer, SudokuManager,
+};
+use crate::{
+    storage::{self, Folder},
+    GameState,
+};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// 存档格式版本, 格式不兼容时加一, 旧存档会被忽略
+pub const SAVE_VERSION: u32 = 1;
+
+const SAVE_FILE: &str = "save.json";
+
+pub(crate) fn plugin(app: &mut App) {
+    app.add_systems(
+        Last,
+        save_game
+            .run_if(in_state(GameState::Playing))
+            .run_if(board_changed.or(on_event::<AppExit>)),
+    );
+}
+
+/// 进行中的一局游戏
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SavedGame {
+    pub version: u32,
+    pub difficulty: Difficulty,
+    /// 每格一个字符, `.` 表示空格, 10 到 16 用字母 A 到 G
+    pub givens: String,
+    pub solution: String,
+    pub cells: Vec<SavedCell>,
+    pub auto_mode: bool,
+    pub elapsed_secs: f64,
+    #[serde(default)]
+    pub hints_used: u32,
+    #[serde(default)]
+    pub revealed_cells: u32,
+    /// 变体的规则, 经典数独是默认值
+    #[serde(default)]
+    pub rules: Rules,
+}
+
+/// 单个格子的存档
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct SavedCell {
+    pub digit: Option<u8>,
+    pub manual_candidates: Vec<u8>,
+    pub auto_candidates: Vec<u8>,
+    #[serde(default)]
+    pub center_marks: Vec<u8>,
+    /// 格子的颜色, 调色板里的序号
+    #[serde(default)]
+    pub color: Option<u8>,
+    /// 每个候选数的颜色, 没有涂色时是空的
+    #[serde(default)]
+    pub candidate_colors: Vec<Option<u8>>,
+    pub revealed: bool,
+    pub corrected: bool,
+}
+
+impl SavedCell {
+    /// 从棋盘模型的一个格子创建, 揭示和纠错的标记在格子组件上, 由调用者传入
+    fn new(board: &Board, cell: u16, revealed: bool, corrected: bool) -> SavedCell {
+        let (color, candidate_colors) = colors_to_saved(board.colors(cell));
+        SavedCell {
+            digit: board.digit(cell).map(Digit::get),
+            manual_candidates: candidates_to_vec(board.manual_candidates(cell)),
+            auto_candidates: candidates_to_vec(board.auto_candidates(cell)),
+            center_marks: candidates_to_vec(board.center_marks(cell)),
+            color,
+            candidate_colors,
+            revealed,
+            corrected,
+        }
+    }
+}
+
+impl SavedGame {
+    /// 版本、格子数、题目和规则都对得上, 才能继续这一局
+    fn is_compatible(&self) -> bool {
+        self.version == SAVE_VERSION
+            && self.cells.len() == self.rules.size.cell_count()
+            && self.puzzle().is_some()
+            && self.rules.is_valid()
+    }
+
+    /// 返回 (谜题, 答案), 0 表示空格
+    pub fn puzzle(&self) -> Option<(Vec<u8>, Vec<u8>)> {
+        let puzzle = parse_cells_line(&self.givens, self.rules.size)?;
+        let solution = parse_cells_line(&self.solution, self.rules.size)?;
+        Some((puzzle, solution))
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_secs_f64(self.elapsed_secs.max(0.0))
+    }
+
+    /// 恢复存档时的棋盘, 返回 (答案, 棋盘)
+    pub fn board(&self) -> Option<(Vec<u8>, Board)> {
+        let (puzzle, solution) = self.puzzle()?;
+        let mut board = Board::with_rules(&puzzle, self.rules.clone());
+        self.restore(&mut board);
+        Some((solution, board))
+    }
+
+    /// 把存档中玩家填的数字, 候选数, 中心标记和颜色恢复到棋盘上
+    fn restore(&self, board: &mut Board) {
+        let size = board.size();
+        for (index, cell) in self.cells.iter().enumerate() {
+            if !size.contains(index as u16) {
+                continue;
+            }
+            if let Some(digit) = cell.digit.filter(|&digit| in_size(size, digit)) {
+                board.place(index as u16, Digit::new(digit));
+            }
+        }
+        // 填数字会剔除候选数, 所以候选数最后恢复
+        for (index, cell) in self.cells.iter().enumerate() {
+            let index = index as u16;
+            let manual = candidates_from_vec(size, &cell.manual_candidates);
+            let auto = candidates_from_vec(size, &cell.auto_candidates);
+            board.set_candidates(index, false, manual);
+            board.set_candidates(index, true, auto);
+            board.set_center_marks(index, candidates_from_vec(size, &cell.center_marks));
+            board.set_colors(index, colors_from_saved(cell));
+        }
+    }
+}
+
+/// 读取存档, 版本不对或者内容损坏时返回 `None`
+pub fn load_game() -> Option<SavedGame> {
+    let saved: SavedGame = storage::load(Folder::Data, SAVE_FILE)?;
+    if !saved.is_compatible() {
+        warn!("Ignoring incompatible save (version {})", saved.version);
+        return None;
+    }
+    Some(saved)
+}
+
+pub fn delete_game() {
+    storage::remove(Folder::Data, SAVE_FILE);
+}
+
+fn in_size(size: GridSize, num: u8) -> bool {
+    (1..=size.digit_count()).contains(&num)
+}
+
+fn candidates_to_vec(candidates: DigitSet) -> Vec<u8> {
+    candidates.digits().map(Digit::get).collect()
+}
+
+fn candidates_from_vec(size: GridSize, candidates: &[u8]) -> DigitSet {
+    let mut set = DigitSet::NONE;
+    for &num in candidates {
+        if in_size(size, num) && !set.contains(Digit::new(num).as_set()) {
+            set ^= Digit::new(num);
+        }
+    }
+    set
+}
+
+fn colors_to_saved(colors: CellColors) -> (Option<u8>, Vec<Option<u8>>) {
+    let candidates = if colors.candidates.iter().any(Option::is_some) {
+        colors.candidates.to_vec()
+    } else {
+        vec![]
+    };
+    (colors.cell, candidates)
+}
+
+/// 调色板以外的颜色丢掉
+fn colors_from_saved(cell: &SavedCell) -> CellColors {
+    let valid = |color: &Option<u8>| color.filter(|&color| color < PALETTE_SIZE);
+    let mut colors = CellColors {
+        cell: valid(&cell.color),
+        ..CellColors::default()
+    };
+    for (color, saved) in colors.candidates.iter_mut().zip(&cell.candidate_colors) {
+        *color = valid(saved);
+    }
+    colors
+}
+
+fn board_changed(
+    q_cell: Query<
+        (),
+        Or<(
+            Changed<DigitValueCell>,
+            Changed<ManualCandidates>,
+            Changed<AutoCandidates>,
+            Changed<CenterMarks>,
+            Changed<CellColor>,
+            Changed<RevealedCell>,
+            Changed<CorrectionCell>,
+        )>,
+    >,
+) -> bool {
+    !q_cell.is_empty()
+}
+
+/// 每一步操作之后以及退出时写入存档, 已经完成的谜题不再存档
+pub(crate) fn save_game(
+    q_cell: Query<(&CellPosition, Has<RevealedCell>, Has<CorrectionCell>)>,
+    sudoku_manager: Option<Res<SudokuManager>>,
+    difficulty: Res<Difficulty>,
+    auto_mode: Res<AutoCandidateMode>,
+    game_timer: Option<Res<GameTimer>>,
+) {
+    let (Some(sudoku_manager), Some(game_timer)) = (sudoku_manager, game_timer) else {
+        return;
+    };
+    if sudoku_manager.is_solved {
+        return;
+    }
+
+    // 武士数独空着的位置没有格子, 存一个空的
+    let size = sudoku_manager.board.size();
+    if q_cell.iter().len() != size.cells().count() {
+        return;
+    }
+    let mut cells = vec![SavedCell::default(); size.cell_count()];
+    for (position, revealed, corrected) in &q_cell {
+        cells[position.0 as usize] =
+            SavedCell::new(&sudoku_manager.board, position.0, revealed, corrected);
+    }
+
+    storage::save(
+        Folder::Data,
+        SAVE_FILE,
+        &SavedGame {
+            version: SAVE_VERSION,
+            difficulty: *difficulty,
+            givens: cells_line(&sudoku_manager.board.givens()),
+            solution: cells_line(&sudoku_manager.solution),
+            cells,
+            auto_mode: auto_mode.0,
+            elapsed_secs: game_timer.0.elapsed_secs_f64(),
+            hints_used: sudoku_manager.hints_used,
+            revealed_cells: sudoku_manager.revealed_cells,
+            rules: sudoku_manager.board.rules().clone(),
+        },
+    );
+}
+
+#[cfg(test)]
+fn saved_game(solution: &[u8], board: &Board) -> SavedGame {
+    let size = board.size();
+    let mut cells = vec![SavedCell::default(); size.cell_count()];
+    for cell in size.cells() {
+        // 随便挑几个格子带上揭示和纠错的标记
+        cells[cell as usize] = SavedCell::new(board, cell, cell % 7 == 0, cell % 11 == 0);
+    }
+    SavedGame {
+        version: SAVE_VERSION,
+        difficulty: Difficulty::Easy,
+        givens: cells_line(&board.givens()),
+        solution: cells_line(solution),
+        cells,
+        auto_mode: true,
+        elapsed_secs: 42.0,
+        hints_used: 1,
+        revealed_cells: 2,
+        rules: board.rules().clone(),
+    }
+}
+
+#[test]
+fn test_save_round_trip() {
+    use crate::game::{generator::generate_board, GeneratorOptions, Variant};
+    use std::sync::atomic::AtomicBool;
+
+    let options = GeneratorOptions {
+        variant: Variant::Killer,
+        ..default()
+    };
+    let (solution, mut board) =
+        generate_board(Difficulty::Easy, options, &AtomicBool::new(false)).unwrap();
+    let empty: Vec<u16> = board
+        .size()
+        .cells()
+        .filter(|&cell| !board.is_given(cell))
+        .collect();
+    // 一个填对的数字, 一个填错的数字, 其余格子放各种标记
+    board.place(empty[0], Digit::new(solution[empty[0] as usize]));
+    board.place(empty[1], Digit::new(solution[empty[1] as usize] % 9 + 1));
+    board.toggle_candidate(empty[2], Digit::new(1), false);
+    board.toggle_candidate(empty[2], Digit::new(9), false);
+    board.toggle_candidate(empty[3], Digit::new(solution[empty[3] as usize]), true);
+    board.toggle_center_mark(empty[4], Digit::new(2));
+    board.toggle_center_mark(empty[4], Digit::new(5));
+    let mut colors = CellColors {
+        cell: Some(3),
+        ..default()
+    };
+    colors.set_candidate(Digit::new(4), Some(PALETTE_SIZE - 1));
+    board.set_colors(empty[5], colors);
+    board.set_colors(
+        0,
+        CellColors {
+            cell: Some(0),
+            ..default()
+        },
+    );
+
+    let saved = saved_game(&solution, &board);
+    let json = serde_json::to_string(&saved).unwrap();
+    let loaded: SavedGame = serde_json::from_str(&json).unwrap();
+    assert!(loaded.is_compatible());
+    let (loaded_solution, loaded_board) = loaded.board().unwrap();
+    assert_eq!(loaded_solution, solution);
+    assert_eq!(loaded_board, board);
+    assert!(!loaded_board.rules().cages.is_empty());
+    for (cell, original) in loaded.cells.iter().zip(&saved.cells) {
+        assert_eq!(cell.revealed, original.revealed);
+        assert_eq!(cell.corrected, original.corrected);
+    }
+    assert_eq!(loaded.elapsed(), Duration::from_secs(42));
+}
+
+#[test]
+fn test_incompatible_save() {
+    let solution: Vec<u8> =
+        "534678912672195348198342567859761423426853791713924856961537284287419635345286179"
+            .bytes()
+            .map(|byte| byte - b'0')
+            .collect();
+    let mut givens = solution.clone();
+    givens[0] = 0;
+    let board = Board::with_rules(&givens, Rules::default());
+    assert!(saved_game(&solution, &board).is_compatible());
+
+    let mut saved = saved_game(&solution, &board);
+    saved.version = SAVE_VERSION + 1;
+    assert!(!saved.is_compatible());
+
+    let mut saved = saved_game(&solution, &board);
+    saved.cells.pop();
+    assert!(!saved.is_compatible());
+
+    let mut saved = saved_game(&solution, &board);
+    saved.givens.pop();
+    assert!(!saved.is_compatible());
+}
@@ -0,0 +1,342 @@
+use crate::game::model::{orthogonal_neighbours, Board, Line, Rules};
+
+/// 按规则数 `cells` 的解, 0 表示空格, 数到 `limit` 个就停止
+///
+/// 和 `Sudoku::solution` 不同, 这里按棋盘的宫和笼子检查, 用来确认变体谜题的解是否唯一.
+/// 不用先建出 `Board`, 挖洞时每挖一次都要检查, 武士数独的格子多, 建棋盘太慢
+pub fn count_solutions(cells: &[u8], rules: &Rules, limit: usize) -> usize {
+    match Search::new(cells, rules, limit) {
+        Some(mut search) => {
+            search.run();
+            search.solutions
+        }
+        None => 0,
+    }
+}
+
+/// 找到的第一个解, 用来按变体规则补全随机的解
+pub fn first_solution(board: &Board) -> Option<Vec<u8>> {
+    let mut search = Search::new(&board.values(), board.rules(), 1)?;
+    search.run();
+    search.first
+}
+
+/// 回溯搜索, 每次选候选数最少的空格
+///
+/// 第 n 位表示数字 n, 16×16 的数字用到第 16 位
+struct Search {
+    digit_count: usize,
+    /// 棋盘上的格子, 不包括武士数独空着的位置
+    cells: Vec<usize>,
+    grid: Vec<u8>,
+    /// 每一行、每一列和每一宫用掉的数字, 以及每个格子所在的行、列和宫
+    houses: Vec<u32>,
+    houses_of: Vec<Vec<usize>>,
+    cage_of: Vec<Option<usize>>,
+    /// 额外约束下不能和这个格子相同的其他格子
+    peers: Vec<Vec<usize>>,
+    /// 温度计和箭头, 以及经过每个格子的线条
+    lines: Vec<Line>,
+    lines_of: Vec<Vec<usize>>,
+    /// 有边上的标记或者反约束时, 和每个格子上下左右相邻的格子
+    rules: Rules,
+    edge_neighbours: Vec<Vec<u16>>,
+    /// 每个笼子用掉的数字, 剩下的和以及空格数
+    cage_used: Vec<u32>,
+    cage_remaining: Vec<i32>,
+    cage_empty: Vec<usize>,
+    limit: usize,
+    solutions: usize,
+    first: Option<Vec<u8>>,
+}
+
+impl Search {
+    /// 棋盘上已有的数字互相冲突时返回 `None`
+    fn new(cells: &[u8], rules: &Rules, limit: usize) -> Option<Search> {
+        let cages = &rules.cages;
+        let size = rules.size;
+        let all_cells = || 0..size.cell_count() as u16;
+        let houses_of = all_cells()
+            .map(|cell| size.houses_of(cell, &rules.regions))
+            .collect();
+        let region_count = size
+            .cells()
+            .map(|cell| rules.regions[cell as usize] as usize + 1)
+            .max()
+            .unwrap_or(0);
+        let mut cage_of = vec![None; size.cell_count()];
+        for (index, cage) in cages.iter().enumerate() {
+            for &cell in &cage.cells {
+                cage_of[cell as usize] = Some(index);
+            }
+        }
+        let overlays = rules.overlays;
+        let peers = all_cells()
+            .map(|cell| {
+                if overlays.is_empty() {
+                    return vec![];
+                }
+                size.cells()
+                    .filter(|&other| other != cell && overlays.sees(size, cell, other))
+                    .map(usize::from)
+                    .collect()
+            })
+            .collect();
+        let lines = rules.lines.clone();
+        let lines_of = all_cells()
+            .map(|cell| {
+                (0..lines.len())
+                    .filter(|&index| lines[index].contains(cell))
+                    .collect()
+            })
+            .collect();
+        let edge_neighbours = all_cells()
+            .map(|cell| {
+                if rules.has_edge_rules() {
+                    orthogonal_neighbours(size, cell)
+                } else {
+                    vec![]
+                }
+            })
+            .collect();
+        let mut search = Search {
+            digit_count: size.digit_count() as usize,
+            cells: size.cells().map(usize::from).collect(),
+            grid: vec![0; size.cell_count()],
+            houses: vec![0; size.line_count() + region_count],
+            houses_of,
+            cage_of,
+            peers,
+            lines,
+            lines_of,
+            rules: rules.clone(),
+            edge_neighbours,
+            cage_used: vec![0; cages.len()],
+            cage_remaining: cages.iter().map(|cage| cage.sum as i32).collect(),
+            cage_empty: cages.iter().map(|cage| cage.cells.len()).collect(),
+            limit,
+            solutions: 0,
+            first: None,
+        };
+        for cell in size.cells().map(usize::from) {
+            let num = cells[cell];
+            if num != 0 {
+                if search.candidates(cell) & (1 << num) == 0 {
+                    return None;
+                }
+                search.set(cell, num);
+            }
+        }
+        Some(search)
+    }
+
+    fn set(&mut self, cell: usize, num: u8) {
+        let bit = 1 << num;
+        self.grid[cell] = num;
+        for &house in &self.houses_of[cell] {
+            self.houses[house] |= bit;
+        }
+        if let Some(cage) = self.cage_of[cell] {
+            self.cage_used[cage] |= bit;
+            self.cage_remaining[cage] -= num as i32;
+            self.cage_empty[cage] -= 1;
+        }
+    }
+
+    fn unset(&mut self, cell: usize, num: u8) {
+        let bit = !(1 << num);
+        self.grid[cell] = 0;
+        for &house in &self.houses_of[cell] {
+            self.houses[house] &= bit;
+        }
+        if let Some(cage) = self.cage_of[cell] {
+            self.cage_used[cage] &= bit;
+            self.cage_remaining[cage] += num as i32;
+            self.cage_empty[cage] += 1;
+        }
+    }
+
+    /// 格子可以填的数字, 第 n 位表示数字 n
+    fn candidates(&self, cell: usize) -> u32 {
+        let used = self.peers[cell]
+            .iter()
+            .fold(0, |used, &peer| used | 1 << self.grid[peer])
+            | self.houses_of[cell]
+                .iter()
+                .fold(0, |used, &house| used | self.houses[house]);
+        let mut mask = !used & ((2 << self.digit_count) - 2);
+        for &other in &self.edge_neighbours[cell] {
+            let value = self.grid[other as usize];
+            for num in 1..=self.digit_count as u8 {
+                if value != 0 && !self.rules.edge_allows(cell as u16, num, other, value) {
+                    mask &= !(1 << num);
+                }
+            }
+        }
+        for &line in &self.lines_of[cell] {
+            for num in 1..=self.digit_count as u8 {
+                if mask & (1 << num) != 0
+                    && !self.lines[line].allows(self.digit_count as u8, cell as u16, num, |other| {
+                        self.grid[other as usize]
+                    })
+                {
+                    mask &= !(1 << num);
+                }
+            }
+        }
+        let Some(cage) = self.cage_of[cell] else {
+            return mask;
+        };
+        mask &= !self.cage_used[cage];
+        let others = self.cage_empty[cage] - 1;
+        for num in 1..=9 {
+            if mask & (1 << num) == 0 {
+                continue;
+            }
+            // 剩下的空格用没用过的数字, 和必须在最小值和最大值之间
+            let remaining = self.cage_remaining[cage] - num;
+            let free: Vec<i32> = (1..=9)
+                .filter(|&other| (self.cage_used[cage] | 1 << num) & (1 << other) == 0)
+                .collect();
+            let fits = others <= free.len()
+                && free[..others].iter().sum::<i32>() <= remaining
+                && free[free.len() - others..].iter().sum::<i32>() >= remaining;
+            if !fits {
+                mask &= !(1 << num);
+            }
+        }
+        mask
+    }
+
+    fn run(&mut self) {
+        if self.solutions >= self.limit {
+            return;
+        }
+        let mut best: Option<(usize, u32)> = None;
+        for &cell in &self.cells {
+            if self.grid[cell] != 0 {
+                continue;
+            }
+            let mask = self.candidates(cell);
+            if best.is_none_or(|(_, best)| mask.count_ones() < best.count_ones()) {
+                best = Some((cell, mask));
+                if mask.count_ones() <= 1 {
+                    break;
+                }
+            }
+        }
+
+        let Some((cell, mask)) = best else {
+            self.solutions += 1;
+            if self.first.is_none() {
+                self.first = Some(self.grid.clone());
+            }
+            return;
+        };
+        for num in 1..=self.digit_count as u8 {
+            if mask & (1 << num) != 0 {
+                self.set(cell, num);
+                self.run();
+                self.unset(cell, num);
+                if self.solutions >= self.limit {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+const SOLVED: &str =
+    "534678912672195348198342567859761423426853791713924856961537284287419635345286179";
+
+#[test]
+fn test_cage_rules() {
+    use crate::game::model::{sudoku_cells, Cage, Rules};
+    use sudoku::Sudoku;
+
+    let mut line = SOLVED.to_string();
+    line.replace_range(0..2, "..");
+    line.replace_range(9..11, "..");
+    let puzzle = Sudoku::from_str_line(&line).unwrap();
+    assert_eq!(
+        count_solutions(&sudoku_cells(puzzle), &Rules::default(), 2),
+        1
+    );
+
+    let cage = |sum| Rules {
+        cages: vec![Cage {
+            cells: vec![0, 9],
+            sum,
+        }],
+        ..Rules::default()
+    };
+    let board = Board::with_rules(&sudoku_cells(puzzle), cage(11));
+    assert_eq!(count_solutions(&sudoku_cells(puzzle), &cage(11), 2), 1);
+    assert_eq!(first_solution(&board).map(|grid| grid[0]), Some(5));
+    // 第 0 格和第 9 格只能是 5 和 6, 凑不出 10
+    assert_eq!(count_solutions(&sudoku_cells(puzzle), &cage(10), 2), 0);
+
+    let empty = Sudoku::from_str_line(&".".repeat(81)).unwrap();
+    assert_eq!(
+        count_solutions(&sudoku_cells(empty), &Rules::default(), 2),
+        2
+    );
+}
+
+#[test]
+fn test_other_sizes() {
+    use crate::game::{grid::GridSize, model::Rules};
+
+    for size in [
+        GridSize::Four,
+        GridSize::Six,
+        GridSize::Sixteen,
+        GridSize::Samurai,
+    ] {
+        let empty = Board::with_rules(&vec![0; size.cell_count()], Rules::classic(size));
+        let solution = first_solution(&empty).unwrap();
+        let solved = Board::with_rules(&solution, Rules::classic(size));
+        assert!(solved.is_solved());
+        assert_eq!(count_solutions(&empty.values(), empty.rules(), 2), 2);
+    }
+}
+
+#[test]
+fn test_overlays() {
+    use crate::game::model::{Overlay, Rules};
+
+    let mut rules = Rules::default();
+    rules.overlays.toggle(Overlay::AntiKnight);
+    rules.overlays.toggle(Overlay::AntiKing);
+    let solution = first_solution(&Board::with_rules(&[0; 81], rules.clone())).unwrap();
+    assert!(Board::with_rules(&solution, rules).is_solved());
+    // 第 0 格的马步和王步范围里不能再有相同的数字
+    assert!([1, 9, 10, 11, 19]
+        .iter()
+        .all(|&other| solution[other] != solution[0]));
+}
+
+#[test]
+fn test_lines() {
+    use crate::game::{
+        grid::GridSize,
+        model::{LineKind, Rules},
+        position::CellPosition,
+    };
+
+    // 第一列从上往下是温度计, 只能是 1 到 9
+    let path = (0..9)
+        .map(|row| CellPosition::from_row_col(row, 0, GridSize::Nine))
+        .collect();
+    let rules = Rules {
+        lines: vec![Line {
+            kind: LineKind::Thermometer,
+            path,
+        }],
+        ..Rules::default()
+    };
+    let solution = first_solution(&Board::with_rules(&[0; 81], rules.clone())).unwrap();
+    assert!((0..9).all(|row| solution[row * 9] == row as u8 + 1));
+    assert!(Board::with_rules(&solution, rules).is_solved());
+}
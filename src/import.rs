@@ -0,0 +1,325 @@
+use crate::clipboard::Clipboard;
+use crate::color::{DARK_BLACK, DARK_GRAY, EXTRA_LIGHT_GRAY, HINT_ELIMINATE, WHITE_COLOR};
+use crate::game::{grade, parse_puzzle, Difficulty, PendingPuzzle};
+use crate::loading::FontAssets;
+use crate::share::title_bar;
+use crate::GameState;
+use bevy::input::{
+    keyboard::{Key, KeyboardInput},
+    ButtonState,
+};
+use bevy::prelude::*;
+
+pub struct ImportPlugin;
+
+/// 导入谜题: 输入, 粘贴或者把文件拖进窗口
+impl Plugin for ImportPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ImportText>()
+            .add_systems(OnEnter(GameState::Import), setup_import)
+            .add_systems(
+                Update,
+                (
+                    type_puzzle,
+                    drop_puzzle_file,
+                    show_import_text.run_if(resource_changed::<ImportText>),
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Import)),
+            )
+            .add_systems(OnExit(GameState::Import), cleanup_import)
+            .add_observer(on_paste_puzzle)
+            .add_observer(on_import_puzzle);
+    }
+}
+
+/// 正在输入的谜题文本
+#[derive(Resource, Default)]
+struct ImportText {
+    text: String,
+    error: Option<String>,
+}
+
+impl ImportText {
+    fn set(&mut self, text: String) {
+        self.text = text;
+        self.error = None;
+    }
+}
+
+#[derive(Event)]
+struct PastePuzzle;
+
+#[derive(Event)]
+struct ImportPuzzle;
+
+#[derive(Component)]
+struct ImportScreen;
+
+#[derive(Component)]
+struct ImportTextDisplay;
+
+#[derive(Component)]
+struct ImportErrorDisplay;
+
+fn setup_import(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    mut import: ResMut<ImportText>,
+) {
+    *import = ImportText::default();
+    commands.spawn((Camera2d, Msaa::Off, ImportScreen));
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                display: Display::Flex,
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
+            ImportScreen,
+        ))
+        .with_children(|builder| {
+            title_bar(&font_assets, builder);
+            builder
+                .spawn((
+                    Name::new("import-container"),
+                    Node {
+                        width: Val::Percent(100.0),
+                        display: Display::Flex,
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        padding: UiRect::axes(Val::Px(15.0), Val::Px(30.0)),
+                        border: UiRect::top(Val::Px(1.0)),
+                        ..default()
+                    },
+                    BorderColor(*EXTRA_LIGHT_GRAY),
+                ))
+                .with_children(|builder| {
+                    builder.spawn((
+                        Text::new("Import Puzzle"),
+                        TextFont {
+                            font_size: 36.0,
+                            font: font_assets.karnak.clone(),
+                            ..default()
+                        },
+                        TextColor(*DARK_BLACK),
+                        Node {
+                            margin: UiRect::bottom(Val::Px(12.0)),
+                            ..default()
+                        },
+                    ));
+                    builder.spawn((
+                        Text::new(
+                            "Type or paste 81 cells (1-9, with . or 0 for blanks) or a \
+                             pencil-mark grid, or drop a text file onto the window.",
+                        ),
+                        TextFont {
+                            font_size: 16.0,
+                            font: font_assets.franklin_500.clone(),
+                            ..default()
+                        },
+                        TextColor(*DARK_GRAY),
+                        Node {
+                            margin: UiRect::bottom(Val::Px(18.0)),
+                            ..default()
+                        },
+                    ));
+
+                    builder
+                        .spawn((
+                            Name::new("import-text"),
+                            Node {
+                                width: Val::Px(480.0),
+                                min_height: Val::Px(220.0),
+                                padding: UiRect::all(Val::Px(12.0)),
+                                border: UiRect::all(Val::Px(1.0)),
+                                ..default()
+                            },
+                            BorderColor(*DARK_BLACK),
+                        ))
+                        .with_child((
+                            ImportTextDisplay,
+                            Text::default(),
+                            TextFont {
+                                font_size: 16.0,
+                                font: font_assets.franklin_500.clone(),
+                                ..default()
+                            },
+                            TextColor(*DARK_BLACK),
+                        ));
+
+                    builder.spawn((
+                        ImportErrorDisplay,
+                        Text::default(),
+                        TextFont {
+                            font_size: 14.0,
+                            font: font_assets.franklin_500.clone(),
+                            ..default()
+                        },
+                        TextColor(*HINT_ELIMINATE),
+                        Node {
+                            margin: UiRect::vertical(Val::Px(12.0)),
+                            ..default()
+                        },
+                    ));
+
+                    builder
+                        .spawn(Node {
+                            display: Display::Flex,
+                            column_gap: Val::Px(12.0),
+                            ..default()
+                        })
+                        .with_children(|builder| {
+                            import_button(&font_assets, builder, "Paste", |_, mut commands| {
+                                commands.trigger(PastePuzzle);
+                            });
+                            import_button(&font_assets, builder, "Play", |_, mut commands| {
+                                commands.trigger(ImportPuzzle);
+                            });
+                            builder
+                                .spawn(import_button_node())
+                                .with_child(import_button_text(&font_assets, "Back"))
+                                .observe(
+                                    |_trigger: Trigger<Pointer<Click>>,
+                                     mut next_state: ResMut<NextState<GameState>>| {
+                                        next_state.set(GameState::Menu);
+                                    },
+                                );
+                        });
+                });
+        });
+}
+
+fn import_button_node() -> impl Bundle {
+    (
+        Name::new("import-button"),
+        Button,
+        Node {
+            padding: UiRect::horizontal(Val::Px(38.0)),
+            min_height: Val::Px(48.0),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        BorderRadius::all(Val::Px(40.0)),
+        BackgroundColor(*DARK_BLACK),
+    )
+}
+
+fn import_button_text(font_assets: &Res<FontAssets>, text: &str) -> impl Bundle {
+    (
+        Text::new(text),
+        TextFont {
+            font_size: 14.0,
+            font: font_assets.franklin_500.clone(),
+            ..default()
+        },
+        TextColor(WHITE_COLOR),
+    )
+}
+
+fn import_button(
+    font_assets: &Res<FontAssets>,
+    builder: &mut ChildBuilder,
+    text: &str,
+    on_click: fn(Trigger<Pointer<Click>>, Commands),
+) {
+    builder
+        .spawn(import_button_node())
+        .with_child(import_button_text(font_assets, text))
+        .observe(on_click);
+}
+
+/// 键盘输入, Ctrl+V / Cmd+V 粘贴
+fn type_puzzle(
+    mut events: EventReader<KeyboardInput>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut import: ResMut<ImportText>,
+    mut commands: Commands,
+) {
+    let modifier = keyboard_input.any_pressed([
+        KeyCode::ControlLeft,
+        KeyCode::ControlRight,
+        KeyCode::SuperLeft,
+        KeyCode::SuperRight,
+    ]);
+    for event in events.read() {
+        if event.state != ButtonState::Pressed {
+            continue;
+        }
+        if modifier {
+            if event.key_code == KeyCode::KeyV {
+                commands.trigger(PastePuzzle);
+            }
+            continue;
+        }
+
+        let mut text = import.text.clone();
+        match &event.logical_key {
+            Key::Character(chars) => text.push_str(chars),
+            Key::Space => text.push(' '),
+            Key::Enter => text.push('\n'),
+            Key::Backspace => {
+                text.pop();
+            }
+            _ => continue,
+        }
+        import.set(text);
+    }
+}
+
+fn drop_puzzle_file(mut events: EventReader<FileDragAndDrop>, mut import: ResMut<ImportText>) {
+    for event in events.read() {
+        if let FileDragAndDrop::DroppedFile { path_buf, .. } = event {
+            match std::fs::read_to_string(path_buf) {
+                Ok(text) => import.set(text),
+                Err(error) => import.error = Some(format!("Failed to read file: {error}")),
+            }
+        }
+    }
+}
+
+fn on_paste_puzzle(
+    _trigger: Trigger<PastePuzzle>,
+    mut import: ResMut<ImportText>,
+    mut clipboard: NonSendMut<Clipboard>,
+) {
+    match clipboard.get_text() {
+        Some(text) => import.set(text),
+        None => import.error = Some("Nothing to paste".to_string()),
+    }
+}
+
+fn on_import_puzzle(
+    _trigger: Trigger<ImportPuzzle>,
+    mut import: ResMut<ImportText>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut commands: Commands,
+) {
+    match parse_puzzle(&import.text) {
+        Ok(imported) => {
+            // 只靠已有技巧解不出的谜题算作困难
+            commands.insert_resource(grade(imported.puzzle).unwrap_or(Difficulty::Hard));
+            commands.insert_resource(PendingPuzzle::Import(imported));
+            next_state.set(GameState::Playing);
+        }
+        Err(error) => import.error = Some(error.to_string()),
+    }
+}
+
+fn show_import_text(
+    import: Res<ImportText>,
+    mut text: Single<&mut Text, (With<ImportTextDisplay>, Without<ImportErrorDisplay>)>,
+    mut error: Single<&mut Text, (With<ImportErrorDisplay>, Without<ImportTextDisplay>)>,
+) {
+    text.0 = import.text.clone();
+    error.0 = import.error.clone().unwrap_or_default();
+}
+
+fn cleanup_import(mut commands: Commands, screen: Query<Entity, With<ImportScreen>>) {
+    for entity in screen.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
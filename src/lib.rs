@@ -1,14 +1,23 @@
 #![allow(clippy::type_complexity)]
 
+mod clipboard;
 pub mod color;
 mod game;
+mod import;
 mod loading;
 mod menu;
+mod setup;
 mod share;
+mod stats;
+mod storage;
 
+use crate::clipboard::Clipboard;
 use crate::game::SudokuPlugin;
+use crate::import::ImportPlugin;
 use crate::loading::LoadingPlugin;
 use crate::menu::MenuPlugin;
+use crate::setup::SetupPlugin;
+use crate::stats::StatsPlugin;
 
 use crate::color::WHITE_COLOR;
 use bevy::app::App;
@@ -28,6 +37,12 @@ enum GameState {
     Playing,
     // Here the menu is drawn and waiting for player interaction
     Menu,
+    // Local statistics screen, reached from the menu
+    Stats,
+    // Import a puzzle typed, pasted or dropped as a file
+    Import,
+    // Difficulty, symmetry and clue count for a new random puzzle
+    Setup,
 }
 
 pub struct GamePlugin;
@@ -36,7 +51,16 @@ impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
         app.init_state::<GameState>()
             .insert_resource(ClearColor(WHITE_COLOR))
-            .add_plugins((LoadingPlugin, MenuPlugin, SudokuPlugin, AudioPlugin));
+            .init_non_send_resource::<Clipboard>()
+            .add_plugins((
+                LoadingPlugin,
+                MenuPlugin,
+                SudokuPlugin,
+                StatsPlugin,
+                ImportPlugin,
+                SetupPlugin,
+                AudioPlugin,
+            ));
 
         #[cfg(debug_assertions)]
         {
@@ -1,6 +1,7 @@
 use crate::color::DARK_BLACK;
+use crate::game::{load_game, Difficulty, GeneratorOptions, PendingPuzzle, PuzzleMode};
 use crate::loading::{FontAssets, TextureAssets};
-use crate::share::title_bar;
+use crate::share::{title_bar, today};
 use crate::GameState;
 use bevy::prelude::*;
 
@@ -149,11 +150,47 @@ fn setup_menu(mut commands: Commands, textures: Res<TextureAssets>, font_assets:
                                 },
                             ));
 
-                            button_item(&font_assets, children, "Easy");
-                            button_item(&font_assets, children, "Medium");
-                            button_item(&font_assets, children, "Hard");
+                            if let Some(saved) = load_game() {
+                                button_item(
+                                    &font_assets,
+                                    children,
+                                    format!("Continue {}", saved.difficulty),
+                                    (ChangeState(GameState::Playing), ResumeGame),
+                                );
+                            }
+                            for difficulty in
+                                [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard]
+                            {
+                                button_item(
+                                    &font_assets,
+                                    children,
+                                    difficulty.to_string(),
+                                    (
+                                        ChangeState(GameState::Playing),
+                                        ChangeDifficulty(difficulty),
+                                    ),
+                                );
+                            }
+                            button_item(
+                                &font_assets,
+                                children,
+                                "New Game".to_string(),
+                                ChangeState(GameState::Setup),
+                            );
+                            button_item(
+                                &font_assets,
+                                children,
+                                "Import Puzzle".to_string(),
+                                ChangeState(GameState::Import),
+                            );
+                            button_item(
+                                &font_assets,
+                                children,
+                                "Statistics".to_string(),
+                                ChangeState(GameState::Stats),
+                            );
 
-                            let date_str = chrono::Local::now().format("%B %d, %Y").to_string();
+                            let date_str = today().format("%B %d, %Y").to_string();
                             children.spawn((
                                 Text::new(date_str),
                                 TextFont {
@@ -263,7 +300,12 @@ fn setup_menu(mut commands: Commands, textures: Res<TextureAssets>, font_assets:
         });
 }
 
-fn button_item(font_assets: &Res<FontAssets>, children: &mut ChildBuilder, text: &str) {
+fn button_item(
+    font_assets: &Res<FontAssets>,
+    children: &mut ChildBuilder,
+    text: String,
+    action: impl Bundle,
+) {
     let button_colors = ButtonColors {
         normal: *DARK_BLACK,
         hovered: *DARK_BLACK,
@@ -288,7 +330,7 @@ fn button_item(font_assets: &Res<FontAssets>, children: &mut ChildBuilder, text:
             BorderRadius::all(Val::Px(24.0)),
             // BackgroundColor(button_colors.normal),
             button_colors,
-            ChangeState(GameState::Playing),
+            action,
         ))
         .with_child((
             Text::new(text),
@@ -304,10 +346,18 @@ fn button_item(font_assets: &Res<FontAssets>, children: &mut ChildBuilder, text:
 #[derive(Component)]
 struct ChangeState(GameState);
 
+#[derive(Component)]
+struct ChangeDifficulty(Difficulty);
+
+/// 继续上次没有完成的一局
+#[derive(Component)]
+struct ResumeGame;
+
 #[derive(Component)]
 struct OpenLink(&'static str);
 
 fn click_play_button(
+    mut commands: Commands,
     mut next_state: ResMut<NextState<GameState>>,
     mut interaction_query: Query<
         (
@@ -315,14 +365,40 @@ fn click_play_button(
             &mut BackgroundColor,
             &ButtonColors,
             Option<&ChangeState>,
+            Option<&ChangeDifficulty>,
+            Has<ResumeGame>,
             Option<&OpenLink>,
         ),
         (Changed<Interaction>, With<Button>),
     >,
 ) {
-    for (interaction, mut color, button_colors, change_state, open_link) in &mut interaction_query {
+    for (
+        interaction,
+        mut color,
+        button_colors,
+        change_state,
+        change_difficulty,
+        resume_game,
+        open_link,
+    ) in &mut interaction_query
+    {
         match *interaction {
             Interaction::Pressed => {
+                if let Some(difficulty) = change_difficulty {
+                    commands.insert_resource(difficulty.0);
+                    commands.insert_resource(PuzzleMode::Daily);
+                    // 每日谜题总是默认规则, 不能留着上次设置里选的选项
+                    commands.insert_resource(GeneratorOptions::default());
+                }
+                if resume_game {
+                    match load_game() {
+                        Some(saved) => {
+                            commands.insert_resource(saved.difficulty);
+                            commands.insert_resource(PendingPuzzle::Resume(saved));
+                        }
+                        None => continue,
+                    }
+                }
                 if let Some(state) = change_state {
                     next_state.set(state.0.clone());
                 } else if let Some(link) = open_link {
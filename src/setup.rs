@@ -0,0 +1,409 @@
+use crate::color::{DARK_BLACK, DARK_GRAY, EXTRA_LIGHT_GRAY, LIGHT_GRAY, WHITE_COLOR};
+use crate::game::{Difficulty, GeneratorOptions, GridSize, Overlay, PuzzleMode, Symmetry, Variant};
+use crate::loading::FontAssets;
+use crate::share::title_bar;
+use crate::GameState;
+use bevy::prelude::*;
+
+/// 线索数选项, `None` 是 "Minimal"
+const CLUE_TARGETS: [Option<usize>; 4] = [None, Some(26), Some(30), Some(34)];
+
+pub struct SetupPlugin;
+
+/// 新游戏设置: 棋盘大小, 规则变体, 额外约束, 反约束, 难度, 线索对称方式和线索数, 用这些选项生成随机谜题
+impl Plugin for SetupPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Setup), setup_new_game)
+            .add_systems(
+                Update,
+                show_setup_options.run_if(in_state(GameState::Setup)),
+            )
+            .add_systems(OnExit(GameState::Setup), cleanup_setup);
+    }
+}
+
+#[derive(Component)]
+struct SetupScreen;
+
+/// 设置面板里的一个选项按钮
+#[derive(Component, Clone, Copy, PartialEq)]
+enum SetupOption {
+    Size(GridSize),
+    Variant(Variant),
+    /// 额外约束可以选多个, 再点一次取消
+    Overlay(Overlay),
+    /// Kropki 和 XV 没有标记的地方是否也有约束
+    Negative(bool),
+    Difficulty(Difficulty),
+    Symmetry(Symmetry),
+    Clues(Option<usize>),
+}
+
+impl SetupOption {
+    fn label(&self) -> String {
+        match self {
+            SetupOption::Size(size) => size.to_string(),
+            SetupOption::Variant(variant) => variant.to_string(),
+            SetupOption::Overlay(overlay) => overlay.to_string(),
+            SetupOption::Negative(false) => "Off".to_string(),
+            SetupOption::Negative(true) => "On".to_string(),
+            SetupOption::Difficulty(difficulty) => difficulty.to_string(),
+            SetupOption::Symmetry(symmetry) => symmetry.to_string(),
+            SetupOption::Clues(Some(clues)) => clues.to_string(),
+            SetupOption::Clues(None) => "Minimal".to_string(),
+        }
+    }
+
+    fn is_selected(&self, difficulty: Difficulty, options: &GeneratorOptions) -> bool {
+        match *self {
+            SetupOption::Size(size) => size == options.size,
+            SetupOption::Variant(variant) => variant == options.variant,
+            SetupOption::Overlay(overlay) => options.overlays.contains(overlay),
+            SetupOption::Negative(negative) => negative == options.negative,
+            SetupOption::Difficulty(other) => other == difficulty,
+            SetupOption::Symmetry(symmetry) => symmetry == options.symmetry,
+            SetupOption::Clues(clues) => clues == options.clues,
+        }
+    }
+
+    /// 线索数是按 9×9 定的, 其他大小按难度决定保留多少数字;
+    /// 反约束只用于格子之间有标记的变体
+    fn is_enabled(&self, options: &GeneratorOptions) -> bool {
+        match self {
+            SetupOption::Clues(_) => options.size == GridSize::Nine,
+            SetupOption::Negative(_) => !options.variant.edge_kinds().is_empty(),
+            _ => true,
+        }
+    }
+}
+
+fn setup_new_game(mut commands: Commands, font_assets: Res<FontAssets>) {
+    commands.spawn((Camera2d, Msaa::Off, SetupScreen));
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                display: Display::Flex,
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
+            SetupScreen,
+        ))
+        .with_children(|builder| {
+            title_bar(&font_assets, builder);
+            builder
+                .spawn((
+                    Name::new("setup-container"),
+                    Node {
+                        width: Val::Percent(100.0),
+                        display: Display::Flex,
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        padding: UiRect::axes(Val::Px(15.0), Val::Px(30.0)),
+                        border: UiRect::top(Val::Px(1.0)),
+                        ..default()
+                    },
+                    BorderColor(*EXTRA_LIGHT_GRAY),
+                ))
+                .with_children(|builder| {
+                    builder.spawn((
+                        Text::new("New Game"),
+                        TextFont {
+                            font_size: 36.0,
+                            font: font_assets.karnak.clone(),
+                            ..default()
+                        },
+                        TextColor(*DARK_BLACK),
+                        Node {
+                            margin: UiRect::bottom(Val::Px(24.0)),
+                            ..default()
+                        },
+                    ));
+
+                    option_row(
+                        &font_assets,
+                        builder,
+                        "Size",
+                        [
+                            GridSize::Four,
+                            GridSize::Six,
+                            GridSize::Nine,
+                            GridSize::Sixteen,
+                            GridSize::Samurai,
+                        ]
+                        .map(SetupOption::Size),
+                    );
+                    option_row(
+                        &font_assets,
+                        builder,
+                        "Variant",
+                        [
+                            Variant::Classic,
+                            Variant::Killer,
+                            Variant::Jigsaw,
+                            Variant::Thermo,
+                            Variant::Arrow,
+                            Variant::Kropki,
+                            Variant::XV,
+                        ]
+                        .map(SetupOption::Variant),
+                    );
+                    option_row(
+                        &font_assets,
+                        builder,
+                        "Negative Constraint",
+                        [false, true].map(SetupOption::Negative),
+                    );
+                    option_row(
+                        &font_assets,
+                        builder,
+                        "Rules",
+                        Overlay::ALL.map(SetupOption::Overlay),
+                    );
+                    option_row(
+                        &font_assets,
+                        builder,
+                        "Difficulty",
+                        [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard]
+                            .map(SetupOption::Difficulty),
+                    );
+                    option_row(
+                        &font_assets,
+                        builder,
+                        "Symmetry",
+                        [
+                            Symmetry::None,
+                            Symmetry::Rotational,
+                            Symmetry::Diagonal,
+                            Symmetry::Mirror,
+                        ]
+                        .map(SetupOption::Symmetry),
+                    );
+                    option_row(
+                        &font_assets,
+                        builder,
+                        "Clues",
+                        CLUE_TARGETS.map(SetupOption::Clues),
+                    );
+
+                    builder
+                        .spawn(Node {
+                            display: Display::Flex,
+                            column_gap: Val::Px(12.0),
+                            margin: UiRect::top(Val::Px(24.0)),
+                            ..default()
+                        })
+                        .with_children(|builder| {
+                            builder
+                                .spawn(setup_button_node())
+                                .with_child(setup_button_text(&font_assets, "Play"))
+                                .observe(
+                                    |_trigger: Trigger<Pointer<Click>>,
+                                     mut commands: Commands,
+                                     mut next_state: ResMut<NextState<GameState>>| {
+                                        commands.insert_resource(PuzzleMode::Random);
+                                        next_state.set(GameState::Playing);
+                                    },
+                                );
+                            builder
+                                .spawn(setup_button_node())
+                                .with_child(setup_button_text(&font_assets, "Back"))
+                                .observe(
+                                    |_trigger: Trigger<Pointer<Click>>,
+                                     mut next_state: ResMut<NextState<GameState>>| {
+                                        next_state.set(GameState::Menu);
+                                    },
+                                );
+                        });
+                });
+        });
+}
+
+/// 一行标题加一组选项按钮
+fn option_row<const N: usize>(
+    font_assets: &Res<FontAssets>,
+    builder: &mut ChildBuilder,
+    title: &str,
+    options: [SetupOption; N],
+) {
+    builder.spawn((
+        Text::new(title),
+        TextFont {
+            font_size: 16.0,
+            font: font_assets.franklin_700.clone(),
+            ..default()
+        },
+        TextColor(*DARK_GRAY),
+        Node {
+            margin: UiRect::vertical(Val::Px(8.0)),
+            ..default()
+        },
+    ));
+    builder
+        .spawn(Node {
+            display: Display::Flex,
+            column_gap: Val::Px(8.0),
+            margin: UiRect::bottom(Val::Px(12.0)),
+            ..default()
+        })
+        .with_children(|builder| {
+            for option in options {
+                builder
+                    .spawn((
+                        Name::new("setup-option"),
+                        Button,
+                        Node {
+                            min_width: Val::Px(96.0),
+                            min_height: Val::Px(40.0),
+                            padding: UiRect::horizontal(Val::Px(16.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            border: UiRect::all(Val::Px(1.0)),
+                            ..default()
+                        },
+                        BorderColor(*DARK_BLACK),
+                        BorderRadius::all(Val::Px(40.0)),
+                        BackgroundColor(WHITE_COLOR),
+                        option,
+                    ))
+                    .with_child((
+                        Text::new(option.label()),
+                        TextFont {
+                            font_size: 14.0,
+                            font: font_assets.franklin_500.clone(),
+                            ..default()
+                        },
+                        TextColor(*DARK_BLACK),
+                    ))
+                    .observe(on_click_option);
+            }
+        });
+}
+
+fn setup_button_node() -> impl Bundle {
+    (
+        Name::new("setup-button"),
+        Button,
+        Node {
+            padding: UiRect::horizontal(Val::Px(38.0)),
+            min_height: Val::Px(48.0),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        BorderRadius::all(Val::Px(40.0)),
+        BackgroundColor(*DARK_BLACK),
+    )
+}
+
+fn setup_button_text(font_assets: &Res<FontAssets>, text: &str) -> impl Bundle {
+    (
+        Text::new(text),
+        TextFont {
+            font_size: 14.0,
+            font: font_assets.franklin_500.clone(),
+            ..default()
+        },
+        TextColor(WHITE_COLOR),
+    )
+}
+
+fn on_click_option(
+    trigger: Trigger<Pointer<Click>>,
+    q_option: Query<&SetupOption>,
+    mut difficulty: ResMut<Difficulty>,
+    mut options: ResMut<GeneratorOptions>,
+) {
+    let Ok(option) = q_option.get(trigger.entity()) else {
+        return;
+    };
+    if !option.is_enabled(&options) {
+        return;
+    }
+    // 变体只有 9×9 的棋盘, 额外约束只能叠加在 9×9 的经典数独上, 反约束只用于格子之间有标记的变体
+    match *option {
+        SetupOption::Size(size) => {
+            options.size = size;
+            if size != GridSize::Nine {
+                options.variant = Variant::Classic;
+                options.overlays = default();
+                options.negative = false;
+                options.clues = None;
+            }
+        }
+        SetupOption::Variant(variant) => {
+            options.variant = variant;
+            if variant.edge_kinds().is_empty() {
+                options.negative = false;
+            }
+            if variant != Variant::Classic {
+                options.size = GridSize::Nine;
+                options.overlays = default();
+            }
+        }
+        SetupOption::Overlay(overlay) => {
+            options.overlays.toggle(overlay);
+            if options.overlays.contains(overlay) {
+                options.size = GridSize::Nine;
+                options.variant = Variant::Classic;
+                options.negative = false;
+                // 和刚选的约束不能同时满足的约束取消掉
+                for other in Overlay::ALL {
+                    if !options.overlays.is_solvable()
+                        && other != overlay
+                        && options.overlays.contains(other)
+                    {
+                        options.overlays.toggle(other);
+                    }
+                }
+            }
+        }
+        SetupOption::Negative(negative) => options.negative = negative,
+        SetupOption::Difficulty(other) => *difficulty = other,
+        SetupOption::Symmetry(symmetry) => options.symmetry = symmetry,
+        SetupOption::Clues(clues) => options.clues = clues,
+    }
+}
+
+/// 选中的选项黑底白字, 不能选的选项是灰的
+fn show_setup_options(
+    difficulty: Res<Difficulty>,
+    options: Res<GeneratorOptions>,
+    mut q_option: Query<(
+        &SetupOption,
+        &Children,
+        &mut BackgroundColor,
+        &mut BorderColor,
+    )>,
+    mut q_text: Query<&mut TextColor>,
+) {
+    for (option, children, mut background, mut border) in q_option.iter_mut() {
+        let enabled = option.is_enabled(&options);
+        let (fill, text) = if !enabled {
+            (WHITE_COLOR, *LIGHT_GRAY)
+        } else if option.is_selected(*difficulty, &options) {
+            (*DARK_BLACK, WHITE_COLOR)
+        } else {
+            (WHITE_COLOR, *DARK_BLACK)
+        };
+        background.set_if_neq(BackgroundColor(fill));
+        let outline = if enabled { *DARK_BLACK } else { *LIGHT_GRAY };
+        if border.0 != outline {
+            border.0 = outline;
+        }
+        for child in children.iter() {
+            if let Ok(mut color) = q_text.get_mut(*child) {
+                if color.0 != text {
+                    color.0 = text;
+                }
+            }
+        }
+    }
+}
+
+fn cleanup_setup(mut commands: Commands, screen: Query<Entity, With<SetupScreen>>) {
+    for entity in screen.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -1,5 +1,11 @@
 use crate::loading::FontAssets;
 use bevy::prelude::*;
+use chrono::NaiveDate;
+
+/// 今天的日期, 标题栏和每日谜题都以它为准
+pub fn today() -> NaiveDate {
+    chrono::Local::now().date_naive()
+}
 
 /// 顶部标题栏
 pub fn title_bar(font_assets: &Res<FontAssets>, builder: &mut ChildBuilder) {
@@ -61,7 +67,7 @@ pub fn title_bar(font_assets: &Res<FontAssets>, builder: &mut ChildBuilder) {
                     // BackgroundColor(GRAY),
                 ))
                 .with_children(|p| {
-                    let date_str = chrono::Local::now().format("%B %d, %Y").to_string();
+                    let date_str = today().format("%B %d, %Y").to_string();
                     p.spawn((
                         Text::new(date_str),
                         TextFont {
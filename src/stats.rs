@@ -0,0 +1,434 @@
+use crate::color::{DARK_BLACK, EXTRA_LIGHT_GRAY, WHITE_COLOR};
+use crate::game::{Difficulty, GridSize, Variant};
+use crate::loading::FontAssets;
+use crate::share::title_bar;
+use crate::storage::{self, Folder};
+use crate::GameState;
+use bevy::prelude::*;
+use serde::{
+    de::{self, DeserializeOwned, IntoDeserializer},
+    Deserialize, Serialize,
+};
+use std::collections::HashMap;
+
+const STATS_FILE: &str = "stats.json";
+
+pub struct StatsPlugin;
+
+/// 本地统计: 每种棋盘大小、规则变体和难度的局数、胜率、连胜和最好成绩
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(
+            storage::load::<Statistics>(Folder::Data, STATS_FILE).unwrap_or_default(),
+        )
+        .add_systems(Update, save_stats.run_if(resource_changed::<Statistics>))
+        .add_systems(OnEnter(GameState::Stats), setup_stats)
+        .add_systems(OnExit(GameState::Stats), cleanup_stats);
+    }
+}
+
+/// 一类对局: 棋盘大小, 规则变体和难度, 统计按它分开
+///
+/// 保存成 `"Nine/Killer/Hard"` 这样的字符串, 才能当 JSON 对象的键.
+/// 旧版本只按难度统计, 只有难度的 `"Hard"` 读作 9×9 经典数独
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(into = "String", try_from = "String")]
+pub struct GameKind {
+    pub size: GridSize,
+    pub variant: Variant,
+    pub difficulty: Difficulty,
+}
+
+impl GameKind {
+    /// 9×9 的经典数独
+    pub fn classic(difficulty: Difficulty) -> GameKind {
+        GameKind {
+            size: GridSize::Nine,
+            variant: Variant::Classic,
+            difficulty,
+        }
+    }
+}
+
+impl std::fmt::Display for GameKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} {}", self.size, self.variant, self.difficulty)
+    }
+}
+
+impl From<GameKind> for String {
+    fn from(kind: GameKind) -> String {
+        format!("{:?}/{:?}/{:?}", kind.size, kind.variant, kind.difficulty)
+    }
+}
+
+impl TryFrom<String> for GameKind {
+    type Error = de::value::Error;
+
+    fn try_from(text: String) -> Result<GameKind, Self::Error> {
+        fn parse<T: DeserializeOwned>(part: &str) -> Result<T, de::value::Error> {
+            T::deserialize(part.into_deserializer())
+        }
+        match text.split('/').collect::<Vec<_>>()[..] {
+            [difficulty] => Ok(GameKind::classic(parse(difficulty)?)),
+            [size, variant, difficulty] => Ok(GameKind {
+                size: parse(size)?,
+                variant: parse(variant)?,
+                difficulty: parse(difficulty)?,
+            }),
+            _ => Err(de::Error::custom(format!("invalid game kind: {text}"))),
+        }
+    }
+}
+
+/// 一类对局的统计
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct DifficultyStats {
+    pub played: u32,
+    pub won: u32,
+    pub current_streak: u32,
+    pub best_streak: u32,
+    pub best_time_secs: Option<f64>,
+}
+
+impl DifficultyStats {
+    /// 胜率, 百分比
+    #[allow(clippy::manual_checked_ops)]
+    pub fn win_rate(&self) -> u32 {
+        if self.played == 0 {
+            0
+        } else {
+            self.won.min(self.played) * 100 / self.played
+        }
+    }
+}
+
+/// 一局完成的游戏
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GameRecord {
+    /// 旧版本只记录难度
+    #[serde(alias = "difficulty")]
+    pub kind: GameKind,
+    pub elapsed_secs: f64,
+    pub hints_used: u32,
+    pub revealed_cells: u32,
+    /// 使用了 "Reveal Puzzle", 不算赢
+    pub fully_revealed: bool,
+    #[serde(default)]
+    pub personal_best: bool,
+}
+
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct Statistics {
+    /// 旧版本的键只有难度
+    #[serde(alias = "difficulties")]
+    pub games: HashMap<GameKind, DifficultyStats>,
+    pub history: Vec<GameRecord>,
+    /// 已经开始还没有完成的一局
+    pub in_progress: Option<GameKind>,
+}
+
+impl Statistics {
+    pub fn get(&self, kind: GameKind) -> DifficultyStats {
+        self.games.get(&kind).cloned().unwrap_or_default()
+    }
+
+    fn get_mut(&mut self, kind: GameKind) -> &mut DifficultyStats {
+        self.games.entry(kind).or_default()
+    }
+
+    /// 统计界面显示的对局: 9×9 经典数独的三个难度, 以及玩过的其他对局
+    fn kinds(&self) -> Vec<GameKind> {
+        let mut kinds: Vec<GameKind> = [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard]
+            .map(GameKind::classic)
+            .into_iter()
+            .chain(self.games.keys().copied())
+            .collect();
+        kinds.sort_by_key(|kind| (kind.size.side(), kind.variant as u8, kind.difficulty as u8));
+        kinds.dedup();
+        kinds
+    }
+
+    /// 开始新的一局, 上一局没有完成就中断连胜
+    pub fn start(&mut self, kind: GameKind) {
+        if let Some(previous) = self.in_progress.take() {
+            self.get_mut(previous).current_streak = 0;
+        }
+        self.get_mut(kind).played += 1;
+        self.in_progress = Some(kind);
+    }
+
+    /// 记录完成的一局, 返回是否刷新了最好成绩
+    pub fn finish(&mut self, mut record: GameRecord) -> bool {
+        if self.in_progress.take() != Some(record.kind) {
+            self.get_mut(record.kind).played += 1;
+        }
+
+        let stats = self.get_mut(record.kind);
+        if record.fully_revealed {
+            stats.current_streak = 0;
+        } else {
+            stats.won += 1;
+            stats.current_streak += 1;
+            stats.best_streak = stats.best_streak.max(stats.current_streak);
+            record.personal_best = match stats.best_time_secs {
+                Some(best) => record.elapsed_secs < best,
+                None => true,
+            };
+            if record.personal_best {
+                stats.best_time_secs = Some(record.elapsed_secs);
+            }
+        }
+
+        let personal_best = record.personal_best;
+        self.history.push(record);
+        personal_best
+    }
+
+    /// 最近完成的一局是否是最好成绩
+    pub fn last_personal_best(&self) -> bool {
+        self.history
+            .last()
+            .is_some_and(|record| record.personal_best)
+    }
+}
+
+fn save_stats(statistics: Res<Statistics>) {
+    storage::save(Folder::Data, STATS_FILE, &*statistics);
+}
+
+/// 秒数格式化成 时:分:秒
+pub fn format_secs(secs: f64) -> String {
+    let seconds = secs as u64;
+    let minutes = seconds / 60;
+    let hours = minutes / 60;
+    format!("{:02}:{:02}:{:02}", hours, minutes % 60, seconds % 60)
+}
+
+#[derive(Component)]
+struct StatsScreen;
+
+fn setup_stats(mut commands: Commands, font_assets: Res<FontAssets>, statistics: Res<Statistics>) {
+    commands.spawn((Camera2d, Msaa::Off, StatsScreen));
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                display: Display::Flex,
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
+            StatsScreen,
+        ))
+        .with_children(|builder| {
+            title_bar(&font_assets, builder);
+            builder
+                .spawn((
+                    Name::new("stats-container"),
+                    Node {
+                        width: Val::Percent(100.0),
+                        display: Display::Flex,
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        padding: UiRect::axes(Val::Px(15.0), Val::Px(30.0)),
+                        border: UiRect::top(Val::Px(1.0)),
+                        ..default()
+                    },
+                    BorderColor(*EXTRA_LIGHT_GRAY),
+                ))
+                .with_children(|builder| {
+                    builder.spawn((
+                        Text::new("Statistics"),
+                        TextFont {
+                            font_size: 36.0,
+                            font: font_assets.karnak.clone(),
+                            ..default()
+                        },
+                        TextColor(*DARK_BLACK),
+                        Node {
+                            margin: UiRect::bottom(Val::Px(24.0)),
+                            ..default()
+                        },
+                    ));
+
+                    builder
+                        .spawn(Node {
+                            display: Display::Grid,
+                            grid_template_columns: vec![
+                                RepeatedGridTrack::px(1, 200.0),
+                                RepeatedGridTrack::px(5, 120.0),
+                            ],
+                            row_gap: Val::Px(12.0),
+                            ..default()
+                        })
+                        .with_children(|builder| {
+                            for header in
+                                ["", "Played", "Win %", "Streak", "Max Streak", "Best Time"]
+                            {
+                                stats_cell(&font_assets, builder, header.to_string(), true);
+                            }
+                            for kind in statistics.kinds() {
+                                let stats = statistics.get(kind);
+                                let best_time = stats
+                                    .best_time_secs
+                                    .map(format_secs)
+                                    .unwrap_or_else(|| "-".to_string());
+                                for value in [
+                                    kind.to_string(),
+                                    stats.played.to_string(),
+                                    stats.win_rate().to_string(),
+                                    stats.current_streak.to_string(),
+                                    stats.best_streak.to_string(),
+                                    best_time,
+                                ] {
+                                    stats_cell(&font_assets, builder, value, false);
+                                }
+                            }
+                        });
+
+                    builder
+                        .spawn((
+                            Name::new("stats-back"),
+                            Button,
+                            Node {
+                                margin: UiRect::top(Val::Px(36.0)),
+                                padding: UiRect::horizontal(Val::Px(38.0)),
+                                min_height: Val::Px(48.0),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            BorderRadius::all(Val::Px(40.0)),
+                            BackgroundColor(*DARK_BLACK),
+                        ))
+                        .with_child((
+                            Text::new("Back"),
+                            TextFont {
+                                font_size: 14.0,
+                                font: font_assets.franklin_500.clone(),
+                                ..default()
+                            },
+                            TextColor(WHITE_COLOR),
+                        ))
+                        .observe(
+                            |_trigger: Trigger<Pointer<Click>>,
+                             mut next_state: ResMut<NextState<GameState>>| {
+                                next_state.set(GameState::Menu);
+                            },
+                        );
+                });
+        });
+}
+
+fn stats_cell(
+    font_assets: &Res<FontAssets>,
+    builder: &mut ChildBuilder,
+    text: String,
+    header: bool,
+) {
+    let font = if header {
+        font_assets.franklin_700.clone()
+    } else {
+        font_assets.franklin_500.clone()
+    };
+    builder.spawn((
+        Text::new(text),
+        TextFont {
+            font_size: 16.0,
+            font,
+            ..default()
+        },
+        TextColor(*DARK_BLACK),
+        TextLayout::new_with_justify(JustifyText::Center),
+    ));
+}
+
+fn cleanup_stats(mut commands: Commands, screen: Query<Entity, With<StatsScreen>>) {
+    for entity in screen.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+#[cfg(test)]
+fn record(kind: GameKind, elapsed_secs: f64, fully_revealed: bool) -> GameRecord {
+    GameRecord {
+        kind,
+        elapsed_secs,
+        hints_used: 0,
+        revealed_cells: 0,
+        fully_revealed,
+        personal_best: false,
+    }
+}
+
+#[test]
+fn test_streaks() {
+    let easy = GameKind::classic(Difficulty::Easy);
+    let mut statistics = Statistics::default();
+    statistics.start(easy);
+    statistics.finish(record(easy, 300.0, false));
+    statistics.start(easy);
+    statistics.finish(record(easy, 200.0, false));
+    assert_eq!(statistics.get(easy).current_streak, 2);
+
+    // 放弃的一局中断连胜
+    statistics.start(easy);
+    statistics.start(easy);
+    let stats = statistics.get(easy);
+    assert_eq!(stats.current_streak, 0);
+    assert_eq!(stats.best_streak, 2);
+    assert_eq!(stats.played, 4);
+    assert_eq!(stats.win_rate(), 50);
+}
+
+#[test]
+fn test_personal_best() {
+    let hard = GameKind::classic(Difficulty::Hard);
+    let mut statistics = Statistics::default();
+    assert!(statistics.finish(record(hard, 600.0, false)));
+    assert!(!statistics.finish(record(hard, 700.0, false)));
+    assert!(statistics.finish(record(hard, 500.0, false)));
+    assert!(!statistics.finish(record(hard, 100.0, true)));
+    assert!(!statistics.last_personal_best());
+    assert_eq!(statistics.get(hard).best_time_secs, Some(500.0));
+}
+
+#[test]
+fn test_game_kinds() {
+    let classic = GameKind::classic(Difficulty::Hard);
+    let killer = GameKind {
+        variant: Variant::Killer,
+        ..classic
+    };
+    let mut statistics = Statistics::default();
+    statistics.finish(record(killer, 600.0, false));
+    assert_eq!(statistics.get(killer).won, 1);
+    assert_eq!(statistics.get(classic).won, 0);
+    assert_eq!(statistics.kinds().len(), 4);
+
+    let json = serde_json::to_string(&statistics).unwrap();
+    assert!(json.contains("\"Nine/Killer/Hard\""));
+    let loaded: Statistics = serde_json::from_str(&json).unwrap();
+    assert_eq!(loaded.get(killer).won, 1);
+}
+
+#[test]
+fn test_legacy_statistics() {
+    let json = r#"{
+        "difficulties": {"Easy": {"played": 3, "won": 2}},
+        "history": [{"difficulty": "Easy", "elapsed_secs": 90.0, "hints_used": 0,
+                     "revealed_cells": 0, "fully_revealed": false}],
+        "in_progress": "Medium"
+    }"#;
+    let statistics: Statistics = serde_json::from_str(json).unwrap();
+    let easy = GameKind::classic(Difficulty::Easy);
+    assert_eq!(statistics.get(easy).played, 3);
+    assert_eq!(statistics.history[0].kind, easy);
+    assert_eq!(
+        statistics.in_progress,
+        Some(GameKind::classic(Difficulty::Medium))
+    );
+}
@@ -0,0 +1,120 @@
+//! 本地存储
+//!
+//! 桌面端写到平台数据目录或配置目录下的 `bevy_sudoku` 文件夹, web 端写到 `localStorage`
+use bevy::prelude::*;
+use serde::{de::DeserializeOwned, Serialize};
+
+const APP_DIR: &str = "bevy_sudoku";
+
+/// 存放位置
+#[derive(Clone, Copy, Debug)]
+pub enum Folder {
+    /// 存档等数据
+    Data,
+    /// 设置
+    Config,
+}
+
+/// 读取并反序列化, 不存在或格式不对时返回 `None`
+pub fn load<T: DeserializeOwned>(folder: Folder, name: &str) -> Option<T> {
+    let contents = read(folder, name)?;
+    match serde_json::from_str(&contents) {
+        Ok(value) => Some(value),
+        Err(error) => {
+            warn!("Failed to parse {name}: {error}");
+            None
+        }
+    }
+}
+
+pub fn save<T: Serialize>(folder: Folder, name: &str, value: &T) {
+    match serde_json::to_string(value) {
+        Ok(contents) => write(folder, name, &contents),
+        Err(error) => warn!("Failed to serialize {name}: {error}"),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn path(folder: Folder, name: &str) -> Option<std::path::PathBuf> {
+    let dir = match folder {
+        Folder::Data => dirs::data_dir(),
+        Folder::Config => dirs::config_dir(),
+    };
+    dir.map(|dir| dir.join(APP_DIR).join(name))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read(folder: Folder, name: &str) -> Option<String> {
+    let path = path(folder, name)?;
+    match std::fs::read_to_string(&path) {
+        Ok(contents) => Some(contents),
+        Err(error) => {
+            // 第一次运行时还没有文件
+            if error.kind() != std::io::ErrorKind::NotFound {
+                warn!("Failed to read {path:?}: {error}");
+            }
+            None
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write(folder: Folder, name: &str, contents: &str) {
+    let Some(path) = path(folder, name) else {
+        warn!("No {folder:?} directory, {name} is not saved");
+        return;
+    };
+    if let Some(parent) = path.parent() {
+        if let Err(error) = std::fs::create_dir_all(parent) {
+            warn!("Failed to create {parent:?}: {error}");
+            return;
+        }
+    }
+    if let Err(error) = std::fs::write(&path, contents) {
+        warn!("Failed to write {path:?}: {error}");
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn remove(folder: Folder, name: &str) {
+    if let Some(path) = path(folder, name) {
+        if let Err(error) = std::fs::remove_file(&path) {
+            if error.kind() != std::io::ErrorKind::NotFound {
+                warn!("Failed to remove {path:?}: {error}");
+            }
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn key(folder: Folder, name: &str) -> String {
+    format!("{APP_DIR}/{folder:?}/{name}")
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+fn read(folder: Folder, name: &str) -> Option<String> {
+    local_storage()?.get_item(&key(folder, name)).ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+fn write(folder: Folder, name: &str, contents: &str) {
+    let Some(storage) = local_storage() else {
+        warn!("No local storage, {name} is not saved");
+        return;
+    };
+    if storage.set_item(&key(folder, name), contents).is_err() {
+        warn!("Failed to write {name} to local storage");
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn remove(folder: Folder, name: &str) {
+    if let Some(storage) = local_storage() {
+        let _ = storage.remove_item(&key(folder, name));
+    }
+}